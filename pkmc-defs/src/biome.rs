@@ -14,6 +14,10 @@ impl Biome {
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn id(&self, mapper: &IdTable<Biome>) -> Option<i32> {
         mapper.get(self).cloned()
     }
@@ -1,9 +1,12 @@
-use std::{collections::BTreeMap, sync::LazyLock};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
 
-use pkmc_util::{nbt::NBT, IdTable};
+use pkmc_util::{nbt::NBT, normalize_identifier, IdTable};
 use serde::{Deserialize, Serialize};
 
-use crate::generated::{generated, DATA};
+use crate::generated::DATA;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
 #[serde(transparent)]
@@ -78,21 +81,245 @@ impl Block {
         Self::new("minecraft:air")
     }
 
+    /// Air blocks don't have any properties, so checking the name is enough & avoids hashing the
+    /// block to look up its id.
+    /// If only the id is known, use [`crate::generated::generated::block::is_air`] instead.
     pub fn is_air(&self) -> bool {
-        //matches!(
-        //    self.name.as_ref(),
-        //    "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
-        //)
-        self.id().map(generated::block::is_air).unwrap_or(false)
+        matches!(
+            self.name.as_ref(),
+            "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+        )
     }
 
     pub fn id(&self) -> Option<i32> {
         BLOCKS_TO_IDS.get(self).copied()
     }
 
+    pub fn from_id(id: i32) -> Option<Block> {
+        IDS_TO_BLOCKS.get(usize::try_from(id).ok()?).cloned()
+    }
+
+    /// Default state of the block with all of its properties, "minecraft:" namespace is
+    /// optional. None if the block doesn't exist.
+    pub fn default_for<N: ToString>(name: N) -> Option<Self> {
+        let name = normalize_identifier(&name.to_string(), "minecraft");
+        Block::from_id(Block::new(name).id()?)
+    }
+
+    /// Default state of the block with the properties changed, None if the block doesn't exist
+    /// or any of the properties are invalid.
+    pub fn from_properties<N: ToString, P: Into<BlockProperties>>(
+        name: N,
+        properties: P,
+    ) -> Option<Self> {
+        let mut block = Block::default_for(name)?;
+        properties.into().iter().for_each(|(key, value)| {
+            block.properties.insert(key, value);
+        });
+        block.id()?;
+        Some(block)
+    }
+
+    /// Block from a name with optional properties, like `oak_stairs[facing=east,half=top]`, as
+    /// used in commands. Unspecified properties are the default state's.
+    pub fn from_state_string(string: &str) -> Option<Self> {
+        let Some((name, properties)) = string.split_once('[') else {
+            return Block::from_properties(string, None::<(&str, &str)>);
+        };
+        let properties = properties
+            .strip_suffix(']')?
+            .split(',')
+            .filter(|property| !property.is_empty())
+            .map(|property| property.split_once('='))
+            .collect::<Option<Vec<_>>>()?;
+        Block::from_properties(name, properties)
+    }
+
+    /// Inverse of [`Block::from_state_string`], with all properties.
+    pub fn to_state_string(&self) -> String {
+        if self.properties.iter().next().is_none() {
+            return self.name.clone();
+        }
+        format!(
+            "{}[{}]",
+            self.name,
+            self.properties
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name)
+    }
+
+    /// Block with a single property changed, None if the block doesn't have that property or the
+    /// value isn't valid for it.
+    pub fn with_property(mut self, name: &str, value: &str) -> Option<Self> {
+        if !self.properties.contains(name) {
+            return None;
+        }
+        self.properties.insert(name, value);
+        self.id()?;
+        Some(self)
+    }
+
+    pub fn is_waterlogged(&self) -> bool {
+        self.get_property("waterlogged") == Some("true")
+    }
+
+    /// Does nothing for blocks that can't be waterlogged.
+    pub fn set_waterlogged(mut self, waterlogged: bool) -> Self {
+        if self.properties.contains("waterlogged") {
+            self.properties.insert("waterlogged", waterlogged);
+        }
+        self
+    }
+
+    /// Block light level that the block emits (0-15), this isn't in the data reports so it's
+    /// maintained by hand.
+    pub fn light_emission(&self) -> u8 {
+        let lit = self.get_property("lit") == Some("true");
+        let lit_or_zero = |level: u8| if lit { level } else { 0 };
+        let property = |name: &str| {
+            self.get_property(name)
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or(0)
+        };
+        match self.name.as_str() {
+            "minecraft:light" => property("level"),
+            "minecraft:beacon"
+            | "minecraft:conduit"
+            | "minecraft:end_gateway"
+            | "minecraft:end_portal"
+            | "minecraft:fire"
+            | "minecraft:glowstone"
+            | "minecraft:jack_o_lantern"
+            | "minecraft:lantern"
+            | "minecraft:lava"
+            | "minecraft:sea_lantern"
+            | "minecraft:shroomlight"
+            | "minecraft:ochre_froglight"
+            | "minecraft:verdant_froglight"
+            | "minecraft:pearlescent_froglight" => 15,
+            "minecraft:campfire"
+            | "minecraft:redstone_lamp"
+            | "minecraft:copper_bulb"
+            | "minecraft:waxed_copper_bulb" => lit_or_zero(15),
+            "minecraft:torch" | "minecraft:wall_torch" | "minecraft:end_rod" => 14,
+            "minecraft:cave_vines" | "minecraft:cave_vines_plant"
+                if self.get_property("berries") == Some("true") =>
+            {
+                14
+            }
+            "minecraft:furnace" | "minecraft:blast_furnace" | "minecraft:smoker" => lit_or_zero(13),
+            "minecraft:exposed_copper_bulb" | "minecraft:waxed_exposed_copper_bulb" => {
+                lit_or_zero(12)
+            }
+            "minecraft:nether_portal" => 11,
+            "minecraft:crying_obsidian"
+            | "minecraft:soul_fire"
+            | "minecraft:soul_torch"
+            | "minecraft:soul_wall_torch"
+            | "minecraft:soul_lantern" => 10,
+            "minecraft:soul_campfire" => lit_or_zero(10),
+            "minecraft:redstone_ore" | "minecraft:deepslate_redstone_ore" => lit_or_zero(9),
+            "minecraft:weathered_copper_bulb" | "minecraft:waxed_weathered_copper_bulb" => {
+                lit_or_zero(8)
+            }
+            "minecraft:enchanting_table" | "minecraft:ender_chest" | "minecraft:glow_lichen" => 7,
+            "minecraft:redstone_torch" | "minecraft:redstone_wall_torch" => lit_or_zero(7),
+            "minecraft:sculk_catalyst" => 6,
+            "minecraft:vault" => match self.get_property("vault_state") {
+                Some("inactive") => 6,
+                _ => 12,
+            },
+            "minecraft:trial_spawner" => match self.get_property("trial_spawner_state") {
+                Some("inactive") | Some("cooldown") => 4,
+                _ => 8,
+            },
+            "minecraft:amethyst_cluster" => 5,
+            "minecraft:large_amethyst_bud" => 4,
+            "minecraft:oxidized_copper_bulb" | "minecraft:waxed_oxidized_copper_bulb" => {
+                lit_or_zero(4)
+            }
+            "minecraft:magma_block" => 3,
+            "minecraft:medium_amethyst_bud" => 2,
+            "minecraft:small_amethyst_bud"
+            | "minecraft:brewing_stand"
+            | "minecraft:brown_mushroom"
+            | "minecraft:dragon_egg"
+            | "minecraft:end_portal_frame"
+            | "minecraft:sculk_sensor"
+            | "minecraft:calibrated_sculk_sensor" => 1,
+            "minecraft:respawn_anchor" => match property("charges") {
+                0 => 0,
+                charges => charges * 4 - 1,
+            },
+            // Only alive (waterlogged) sea pickles glow.
+            "minecraft:sea_pickle" if self.is_waterlogged() => 3 + 3 * property("pickles"),
+            name if name.ends_with("candle") => lit_or_zero(3 * property("candles")),
+            name if name.ends_with("candle_cake") => lit_or_zero(3),
+            _ => 0,
+        }
+    }
+
+    /// How much block light is reduced when passing through the block (0-15), approximated from
+    /// the block's type in the data reports as there's no shape data. Light is always reduced by
+    /// at least 1 per block it travels.
+    pub fn light_opacity(&self) -> u8 {
+        let Some(r#type) = DATA
+            .block
+            .get(&self.name)
+            .map(|block| block.definition.r#type.as_str())
+        else {
+            return 0;
+        };
+        if OPAQUE_BLOCK_TYPES.contains(&r#type) {
+            15
+        } else if DAMPENING_BLOCK_TYPES.contains(&r#type) || self.is_waterlogged() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// If the block stops movement or contains fluid, what the `MOTION_BLOCKING` heightmap
+    /// tracks.
+    pub fn is_motion_blocking(&self) -> bool {
+        let Some(r#type) = DATA
+            .block
+            .get(&self.name)
+            .map(|block| block.definition.r#type.as_str())
+        else {
+            return false;
+        };
+        !NON_MOTION_BLOCKING_BLOCK_TYPES.contains(&r#type) || self.is_waterlogged()
+    }
+
     pub fn id_with_default_fallback(&self) -> Option<i32> {
         self.id().or_else(|| self.without_properties().id())
     }
+
+    /// Block state as stored in chunk palettes & structures.
+    pub fn to_nbt(&self) -> NBT {
+        let mut compound = HashMap::new();
+        compound.insert("Name".to_owned(), NBT::String(self.name.clone()));
+        if self.properties.iter().next().is_some() {
+            compound.insert(
+                "Properties".to_owned(),
+                NBT::Compound(
+                    self.properties
+                        .iter()
+                        .map(|(k, v)| (k.to_owned(), NBT::String(v.to_owned())))
+                        .collect(),
+                ),
+            );
+        }
+        NBT::Compound(compound)
+    }
 }
 
 impl Default for Block {
@@ -101,6 +328,20 @@ impl Default for Block {
     }
 }
 
+/// Deserializes a block like `{ "name": "oak_stairs", "properties": { "facing": "north" } }` with
+/// [`Block::from_properties`], erroring on invalid states.
+///
+/// For config files with `#[serde(deserialize_with = "deserialize_block_state")]`, [`Block`]'s
+/// own [`Deserialize`] keeps the state as is.
+pub fn deserialize_block_state<'de, D>(deserializer: D) -> Result<Block, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let block = Block::deserialize(deserializer)?;
+    Block::from_properties(&block.name, block.properties.clone())
+        .ok_or_else(|| serde::de::Error::custom(format!("Invalid block state {:?}", block)))
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BlockEntity {
     pub block: Block,
@@ -126,6 +367,182 @@ impl BlockEntity {
     }
 }
 
+/// Block types that are full blocks, see [`Block::light_opacity`].
+const OPAQUE_BLOCK_TYPES: &[&str] = &[
+    "minecraft:amethyst",
+    "minecraft:barrel",
+    "minecraft:beehive",
+    "minecraft:blast_furnace",
+    "minecraft:block",
+    "minecraft:brushable",
+    "minecraft:budding_amethyst",
+    "minecraft:cartography_table",
+    "minecraft:chiseled_book_shelf",
+    "minecraft:colored_falling",
+    "minecraft:command",
+    "minecraft:concrete_powder",
+    "minecraft:copper_bulb_block",
+    "minecraft:crafter",
+    "minecraft:crafting_table",
+    "minecraft:creaking_heart",
+    "minecraft:crying_obsidian",
+    "minecraft:dispenser",
+    "minecraft:drop_experience",
+    "minecraft:dropper",
+    "minecraft:fletching_table",
+    "minecraft:furnace",
+    "minecraft:glazed_terracotta",
+    "minecraft:hay",
+    "minecraft:huge_mushroom",
+    "minecraft:infested",
+    "minecraft:infested_rotated_pillar",
+    "minecraft:jack_o_lantern",
+    "minecraft:jigsaw",
+    "minecraft:jukebox",
+    "minecraft:loom",
+    "minecraft:magma",
+    "minecraft:mud",
+    "minecraft:mycelium",
+    "minecraft:netherrack",
+    "minecraft:note",
+    "minecraft:nylium",
+    "minecraft:observer",
+    "minecraft:powered",
+    "minecraft:pumpkin",
+    "minecraft:redstone_lamp",
+    "minecraft:redstone_ore",
+    "minecraft:respawn_anchor",
+    "minecraft:rooted_dirt",
+    "minecraft:rotated_pillar",
+    "minecraft:sculk",
+    "minecraft:sculk_catalyst",
+    "minecraft:smithing_table",
+    "minecraft:smoker",
+    "minecraft:snowy_dirt",
+    "minecraft:soul_sand",
+    "minecraft:sponge",
+    "minecraft:structure",
+    "minecraft:target",
+    "minecraft:tinted_glass",
+    "minecraft:tnt",
+    "minecraft:weathering_copper_bulb",
+    "minecraft:weathering_copper_full",
+    "minecraft:wet_sponge",
+];
+
+/// Block types that light passes through but is slightly dampened by, see
+/// [`Block::light_opacity`].
+const DAMPENING_BLOCK_TYPES: &[&str] = &[
+    "minecraft:bubble_column",
+    "minecraft:frosted_ice",
+    "minecraft:honey",
+    "minecraft:ice",
+    "minecraft:leaves",
+    "minecraft:liquid",
+    "minecraft:mangrove_leaves",
+    "minecraft:particle_leaves",
+    "minecraft:powder_snow",
+    "minecraft:slime",
+    "minecraft:web",
+];
+
+/// Block types that entities can move through, waterlogged blocks still count as motion blocking.
+/// See [`Block::is_motion_blocking`].
+const NON_MOTION_BLOCKING_BLOCK_TYPES: &[&str] = &[
+    "minecraft:air",
+    "minecraft:attached_stem",
+    "minecraft:azalea",
+    "minecraft:bamboo_sapling",
+    "minecraft:beetroot",
+    "minecraft:big_dripleaf",
+    "minecraft:big_dripleaf_stem",
+    "minecraft:button",
+    "minecraft:candle",
+    "minecraft:carpet",
+    "minecraft:carrot",
+    "minecraft:cave_vines",
+    "minecraft:cave_vines_plant",
+    "minecraft:chorus_flower",
+    "minecraft:chorus_plant",
+    "minecraft:cocoa",
+    "minecraft:comparator",
+    "minecraft:coral_fan",
+    "minecraft:coral_plant",
+    "minecraft:coral_wall_fan",
+    "minecraft:crop",
+    "minecraft:dead_bush",
+    "minecraft:detector_rail",
+    "minecraft:double_plant",
+    "minecraft:end_gateway",
+    "minecraft:end_portal",
+    "minecraft:end_rod",
+    "minecraft:eyeblossom",
+    "minecraft:fire",
+    "minecraft:flower",
+    "minecraft:flower_pot",
+    "minecraft:frogspawn",
+    "minecraft:fungus",
+    "minecraft:glow_lichen",
+    "minecraft:hanging_moss",
+    "minecraft:hanging_roots",
+    "minecraft:heavy_core",
+    "minecraft:ladder",
+    "minecraft:lever",
+    "minecraft:light",
+    "minecraft:mangrove_propagule",
+    "minecraft:mossy_carpet",
+    "minecraft:multiface",
+    "minecraft:mushroom",
+    "minecraft:nether_portal",
+    "minecraft:nether_sprouts",
+    "minecraft:nether_wart",
+    "minecraft:piglinwallskull",
+    "minecraft:pink_petals",
+    "minecraft:pitcher_crop",
+    "minecraft:player_head",
+    "minecraft:player_wall_head",
+    "minecraft:potato",
+    "minecraft:powder_snow",
+    "minecraft:powered_rail",
+    "minecraft:rail",
+    "minecraft:redstone_torch",
+    "minecraft:redstone_wall_torch",
+    "minecraft:redstone_wire",
+    "minecraft:repeater",
+    "minecraft:roots",
+    "minecraft:sapling",
+    "minecraft:scaffolding",
+    "minecraft:sea_pickle",
+    "minecraft:skull",
+    "minecraft:small_dripleaf",
+    "minecraft:snow_layer",
+    "minecraft:soul_fire",
+    "minecraft:spore_blossom",
+    "minecraft:stem",
+    "minecraft:structure_void",
+    "minecraft:sugar_cane",
+    "minecraft:sweet_berry_bush",
+    "minecraft:tall_flower",
+    "minecraft:tall_grass",
+    "minecraft:torch",
+    "minecraft:torchflower_crop",
+    "minecraft:trip_wire_hook",
+    "minecraft:tripwire",
+    "minecraft:twisting_vines",
+    "minecraft:twisting_vines_plant",
+    "minecraft:vine",
+    "minecraft:wall_skull",
+    "minecraft:wall_torch",
+    "minecraft:waterlily",
+    "minecraft:web",
+    "minecraft:weeping_vines",
+    "minecraft:weeping_vines_plant",
+    "minecraft:wither_rose",
+    "minecraft:wither_skull",
+    "minecraft:wither_wall_skull",
+    "minecraft:wool_carpet",
+];
+
 pub static BLOCKS_TO_IDS: LazyLock<IdTable<Block>> = LazyLock::new(|| {
     let mut blocks_to_ids = IdTable::new();
     DATA.block.iter().for_each(|(name, block)| {
@@ -139,6 +556,16 @@ pub static BLOCKS_TO_IDS: LazyLock<IdTable<Block>> = LazyLock::new(|| {
     blocks_to_ids
 });
 
+pub static IDS_TO_BLOCKS: LazyLock<Box<[Block]>> = LazyLock::new(|| {
+    let mut ids_to_blocks = vec![Block::air(); DATA.block.values().map(|b| b.states.len()).sum()];
+    DATA.block.iter().for_each(|(name, block)| {
+        block.states.iter().for_each(|state| {
+            ids_to_blocks[state.id as usize] = Block::new_p(name, state.properties.iter());
+        });
+    });
+    ids_to_blocks.into_boxed_slice()
+});
+
 pub static BLOCK_ENTITIES_TO_IDS: LazyLock<IdTable<String>> = LazyLock::new(|| {
     let registry = DATA.registries.get("minecraft:block_entity_type").unwrap();
     let mut blocks_entities_to_ids = IdTable::new();
@@ -150,7 +577,12 @@ pub static BLOCK_ENTITIES_TO_IDS: LazyLock<IdTable<String>> = LazyLock::new(|| {
 
 #[cfg(test)]
 mod test {
-    use crate::block::{Block, BLOCKS_TO_IDS};
+    use serde::Deserialize;
+
+    use crate::{
+        block::{deserialize_block_state, Block, BLOCKS_TO_IDS, IDS_TO_BLOCKS},
+        generated::generated,
+    };
 
     #[test]
     fn test_blocks_to_ids() {
@@ -176,4 +608,212 @@ mod test {
             Some(6969)
         );
     }
+
+    #[test]
+    fn test_is_air() {
+        assert_eq!(Block::from_id(1), Some(Block::new("minecraft:stone")));
+        assert_eq!(Block::from_id(IDS_TO_BLOCKS.len() as i32), None);
+        assert_eq!(Block::from_id(-1), None);
+
+        (0..IDS_TO_BLOCKS.len() as i32).for_each(|id| {
+            let block = Block::from_id(id).unwrap();
+            assert_eq!(block.id(), Some(id));
+            assert_eq!(generated::block::is_air(id), block.is_air(), "{:?}", block);
+        });
+    }
+
+    #[test]
+    fn test_from_properties() {
+        assert_eq!(
+            Block::from_properties("stone", None::<(&str, &str)>),
+            Some(Block::new("minecraft:stone"))
+        );
+        let stairs = Block::from_properties("oak_stairs", [("facing", "east")]).unwrap();
+        assert_eq!(stairs.properties.get("facing"), Some("east"));
+        assert_eq!(stairs.properties.get("half"), Some("bottom"));
+        assert!(stairs.id().is_some());
+
+        assert_eq!(
+            Block::from_properties("oak_stairs", [("facing", "up")]),
+            None
+        );
+        assert_eq!(
+            Block::from_properties("not_a_block", None::<(&str, &str)>),
+            None
+        );
+    }
+
+    #[test]
+    fn test_with_property() {
+        let stairs = Block::from_properties("minecraft:oak_stairs", [("facing", "east")]).unwrap();
+        assert_eq!(stairs.get_property("facing"), Some("east"));
+        assert_eq!(stairs.get_property("age"), None);
+
+        let north = stairs.clone().with_property("facing", "north").unwrap();
+        assert_eq!(north.get_property("facing"), Some("north"));
+        assert_eq!(north.get_property("half"), stairs.get_property("half"));
+        assert_eq!(
+            north,
+            Block::from_properties("minecraft:oak_stairs", [("facing", "north")]).unwrap()
+        );
+
+        assert_eq!(stairs.clone().with_property("facing", "up"), None);
+        assert_eq!(stairs.with_property("age", "1"), None);
+        assert_eq!(Block::air().with_property("facing", "north"), None);
+    }
+
+    #[test]
+    fn test_default_for() {
+        assert_eq!(
+            Block::default_for("stone"),
+            Some(Block::new("minecraft:stone"))
+        );
+        assert_eq!(
+            Block::default_for("minecraft:oak_stairs"),
+            Some(Block::new_p(
+                "minecraft:oak_stairs",
+                [
+                    ("facing", "north"),
+                    ("half", "bottom"),
+                    ("shape", "straight"),
+                    ("waterlogged", "false"),
+                ]
+            ))
+        );
+        assert_eq!(Block::default_for("not_a_block"), None);
+    }
+
+    #[test]
+    fn test_light_emission() {
+        let block = |string| Block::from_state_string(string).unwrap();
+        assert_eq!(block("glowstone").light_emission(), 15);
+        assert_eq!(block("torch").light_emission(), 14);
+        assert_eq!(block("soul_lantern[hanging=true]").light_emission(), 10);
+        assert_eq!(block("stone").light_emission(), 0);
+        assert_eq!(block("air").light_emission(), 0);
+
+        assert_eq!(block("furnace[lit=false]").light_emission(), 0);
+        assert_eq!(block("furnace[lit=true]").light_emission(), 13);
+        assert_eq!(block("light[level=7]").light_emission(), 7);
+        assert_eq!(block("red_candle[candles=3,lit=true]").light_emission(), 9);
+        assert_eq!(block("red_candle[candles=3,lit=false]").light_emission(), 0);
+        assert_eq!(block("candle_cake[lit=true]").light_emission(), 3);
+        assert_eq!(block("respawn_anchor[charges=0]").light_emission(), 0);
+        assert_eq!(block("respawn_anchor[charges=4]").light_emission(), 15);
+        assert_eq!(
+            block("sea_pickle[pickles=4,waterlogged=true]").light_emission(),
+            15
+        );
+        assert_eq!(
+            block("sea_pickle[pickles=4,waterlogged=false]").light_emission(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_light_opacity() {
+        let block = |string| Block::from_state_string(string).unwrap();
+        assert_eq!(block("air").light_opacity(), 0);
+        assert_eq!(block("stone").light_opacity(), 15);
+        assert_eq!(block("oak_log").light_opacity(), 15);
+        assert_eq!(block("glass").light_opacity(), 0);
+        assert_eq!(block("torch").light_opacity(), 0);
+        assert_eq!(block("oak_leaves").light_opacity(), 1);
+        assert_eq!(block("water").light_opacity(), 1);
+        assert_eq!(block("oak_slab[waterlogged=false]").light_opacity(), 0);
+        assert_eq!(block("oak_slab[waterlogged=true]").light_opacity(), 1);
+    }
+
+    #[test]
+    fn test_motion_blocking() {
+        let block = |string| Block::from_state_string(string).unwrap();
+        assert!(!block("air").is_motion_blocking());
+        assert!(block("stone").is_motion_blocking());
+        assert!(block("water").is_motion_blocking());
+        assert!(block("glass").is_motion_blocking());
+        assert!(!block("torch").is_motion_blocking());
+        assert!(!block("ladder[waterlogged=false]").is_motion_blocking());
+        assert!(block("ladder[waterlogged=true]").is_motion_blocking());
+    }
+
+    #[test]
+    fn test_state_string() {
+        let stairs = Block::from_state_string("oak_stairs[facing=east,half=top]").unwrap();
+        assert_eq!(
+            stairs,
+            Block::from_properties(
+                "minecraft:oak_stairs",
+                [("facing", "east"), ("half", "top")]
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            stairs.to_state_string(),
+            "minecraft:oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]"
+        );
+        assert_eq!(
+            Block::from_state_string(&stairs.to_state_string()),
+            Some(stairs)
+        );
+
+        let stone = Block::from_state_string("minecraft:stone").unwrap();
+        assert_eq!(stone, Block::new("minecraft:stone"));
+        assert_eq!(stone.to_state_string(), "minecraft:stone");
+        assert_eq!(Block::from_state_string("stone[]"), Some(stone));
+
+        assert_eq!(Block::from_state_string("not_a_block"), None);
+        assert_eq!(Block::from_state_string("oak_stairs[facing=up]"), None);
+        assert_eq!(Block::from_state_string("oak_stairs[facing]"), None);
+        assert_eq!(Block::from_state_string("oak_stairs[facing=east"), None);
+    }
+
+    #[test]
+    fn test_waterlogged() {
+        let stairs = Block::from_properties("minecraft:oak_stairs", [("facing", "east")]).unwrap();
+        assert!(!stairs.is_waterlogged());
+        let waterlogged = stairs.clone().set_waterlogged(true);
+        assert!(waterlogged.is_waterlogged());
+        assert!(waterlogged.id().is_some());
+        assert_eq!(waterlogged.get_property("facing"), Some("east"));
+        assert_eq!(waterlogged.set_waterlogged(false), stairs);
+
+        let stone = Block::new("minecraft:stone");
+        assert!(!stone.is_waterlogged());
+        assert_eq!(stone.clone().set_waterlogged(true), stone);
+    }
+
+    #[test]
+    fn test_block_state_json() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(deserialize_with = "deserialize_block_state")]
+            block: Block,
+        }
+
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "block": { "name": "oak_stairs", "properties": { "facing": "north" } }
+        }))
+        .unwrap();
+        assert_eq!(
+            config.block,
+            Block::from_properties("minecraft:oak_stairs", [("facing", "north")]).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&config.block).unwrap(),
+            serde_json::json!({
+                "name": "minecraft:oak_stairs",
+                "properties": {
+                    "facing": "north",
+                    "half": "bottom",
+                    "shape": "straight",
+                    "waterlogged": "false",
+                },
+            })
+        );
+
+        assert!(serde_json::from_value::<Config>(serde_json::json!({
+            "block": { "name": "oak_stairs", "properties": { "facing": "up" } }
+        }))
+        .is_err());
+    }
 }
@@ -0,0 +1,105 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use pkmc_util::{normalize_identifier, IdTable};
+
+use crate::generated::DATA;
+
+pub static ENTITY_TYPES_TO_IDS: LazyLock<IdTable<String>> = LazyLock::new(|| {
+    let registry = DATA.registries.get("minecraft:entity_type").unwrap();
+    let mut entity_types_to_ids = IdTable::new();
+    registry.entries.iter().for_each(|(name, id)| {
+        entity_types_to_ids.insert(name.to_owned(), *id);
+    });
+    entity_types_to_ids
+});
+
+/// Entity types that aren't living entities (no health, armor, etc.), besides boats & minecarts.
+const NON_LIVING_ENTITY_TYPES: &[&str] = &[
+    "minecraft:area_effect_cloud",
+    "minecraft:arrow",
+    "minecraft:block_display",
+    "minecraft:breeze_wind_charge",
+    "minecraft:dragon_fireball",
+    "minecraft:egg",
+    "minecraft:end_crystal",
+    "minecraft:ender_pearl",
+    "minecraft:evoker_fangs",
+    "minecraft:experience_bottle",
+    "minecraft:experience_orb",
+    "minecraft:eye_of_ender",
+    "minecraft:falling_block",
+    "minecraft:fireball",
+    "minecraft:firework_rocket",
+    "minecraft:fishing_bobber",
+    "minecraft:glow_item_frame",
+    "minecraft:interaction",
+    "minecraft:item",
+    "minecraft:item_display",
+    "minecraft:item_frame",
+    "minecraft:leash_knot",
+    "minecraft:lightning_bolt",
+    "minecraft:llama_spit",
+    "minecraft:marker",
+    "minecraft:ominous_item_spawner",
+    "minecraft:painting",
+    "minecraft:potion",
+    "minecraft:shulker_bullet",
+    "minecraft:small_fireball",
+    "minecraft:snowball",
+    "minecraft:spectral_arrow",
+    "minecraft:text_display",
+    "minecraft:tnt",
+    "minecraft:trident",
+    "minecraft:wind_charge",
+    "minecraft:wither_skull",
+];
+
+static NON_LIVING_ENTITY_TYPE_IDS: LazyLock<HashSet<i32>> = LazyLock::new(|| {
+    ENTITY_TYPES_TO_IDS
+        .iter()
+        .filter(|(name, _)| {
+            NON_LIVING_ENTITY_TYPES.contains(&name.as_str())
+                || name.ends_with("_boat")
+                || name.ends_with("_raft")
+                || name.ends_with("minecart")
+        })
+        .map(|(_, id)| *id)
+        .collect()
+});
+
+/// Entity type ID from its identifier, "minecraft:" namespace is optional.
+pub fn entity_type_id(name: &str) -> Option<i32> {
+    ENTITY_TYPES_TO_IDS
+        .get(&normalize_identifier(name, "minecraft"))
+        .copied()
+}
+
+/// If the entity type is a living entity, unknown types are assumed to be living.
+pub fn is_living_entity_type(entity_type: i32) -> bool {
+    !NON_LIVING_ENTITY_TYPE_IDS.contains(&entity_type)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::entity::{entity_type_id, is_living_entity_type};
+
+    #[test]
+    fn test_entity_type_id() {
+        assert_eq!(entity_type_id("minecraft:acacia_boat"), Some(0));
+        assert_eq!(entity_type_id("allay"), Some(2));
+        assert_eq!(entity_type_id("minecraft:not_an_entity"), None);
+    }
+
+    #[test]
+    fn test_is_living_entity_type() {
+        let living = |name| is_living_entity_type(entity_type_id(name).unwrap());
+        assert!(living("player"));
+        assert!(living("armor_stand"));
+        assert!(living("zombie"));
+        assert!(!living("item"));
+        assert!(!living("oak_boat"));
+        assert!(!living("bamboo_chest_raft"));
+        assert!(!living("tnt_minecart"));
+        assert!(!living("text_display"));
+    }
+}
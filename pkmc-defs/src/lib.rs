@@ -1,6 +1,8 @@
 pub mod biome;
 pub mod block;
+pub mod entity;
 pub mod generated;
+pub mod mob_effect;
 pub mod packet;
 pub mod registry;
 pub mod text_component;
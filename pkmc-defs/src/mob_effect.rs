@@ -0,0 +1,33 @@
+use std::sync::LazyLock;
+
+use pkmc_util::{normalize_identifier, IdTable};
+
+use crate::generated::DATA;
+
+pub static MOB_EFFECTS_TO_IDS: LazyLock<IdTable<String>> = LazyLock::new(|| {
+    let registry = DATA.registries.get("minecraft:mob_effect").unwrap();
+    let mut mob_effects_to_ids = IdTable::new();
+    registry.entries.iter().for_each(|(name, id)| {
+        mob_effects_to_ids.insert(name.to_owned(), *id);
+    });
+    mob_effects_to_ids
+});
+
+/// Mob effect ID from its identifier, "minecraft:" namespace is optional.
+pub fn mob_effect_id(name: &str) -> Option<i32> {
+    MOB_EFFECTS_TO_IDS
+        .get(&normalize_identifier(name, "minecraft"))
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mob_effect::mob_effect_id;
+
+    #[test]
+    fn test_mob_effect_id() {
+        assert_eq!(mob_effect_id("minecraft:speed"), Some(0));
+        assert_eq!(mob_effect_id("night_vision"), Some(15));
+        assert_eq!(mob_effect_id("minecraft:not_an_effect"), None);
+    }
+}
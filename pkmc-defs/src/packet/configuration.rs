@@ -58,7 +58,7 @@ impl ClientboundPacket for CustomPayload {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClientInformation {
     pub locale: String,
     pub view_distance: i8,
@@ -70,6 +70,22 @@ pub struct ClientInformation {
     pub allow_server_listings: bool,
 }
 
+/// Vanilla client defaults, for clients that never send their information.
+impl Default for ClientInformation {
+    fn default() -> Self {
+        Self {
+            locale: "en_us".to_owned(),
+            view_distance: 10,
+            chat_mode: 0,
+            chat_colors: true,
+            displayed_skin_parts: 0x7F,
+            left_handed: false,
+            enable_text_filtering: false,
+            allow_server_listings: true,
+        }
+    }
+}
+
 impl ServerboundPacket for ClientInformation {
     const SERVERBOUND_ID: i32 =
         generated::packet::configuration::SERVERBOUND_MINECRAFT_CLIENT_INFORMATION;
@@ -92,13 +108,32 @@ impl ServerboundPacket for ClientInformation {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KnownPack {
     pub namespace: String,
     pub id: String,
     pub version: String,
 }
 
+impl KnownPack {
+    pub fn new(
+        namespace: impl Into<String>,
+        id: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        Self {
+            namespace: namespace.into(),
+            id: id.into(),
+            version: version.into(),
+        }
+    }
+
+    /// The builtin vanilla data pack, that contains all the vanilla registry entries.
+    pub fn vanilla() -> Self {
+        Self::new("minecraft", "core", "1.21.4")
+    }
+}
+
 #[derive(Debug)]
 pub struct SelectKnownPacks {
     pub packs: Vec<KnownPack>,
@@ -204,6 +239,60 @@ impl ClientboundPacket for UpdateTags {
     }
 }
 
+/// Feature flags that are enabled, needed for experimental content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateEnabledFeatures {
+    pub features: Vec<String>,
+}
+
+impl Default for UpdateEnabledFeatures {
+    fn default() -> Self {
+        Self {
+            features: vec!["minecraft:vanilla".to_owned()],
+        }
+    }
+}
+
+impl ClientboundPacket for UpdateEnabledFeatures {
+    const CLIENTBOUND_ID: i32 =
+        generated::packet::configuration::CLIENTBOUND_MINECRAFT_UPDATE_ENABLED_FEATURES;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.features.len() as i32)?;
+        self.features
+            .iter()
+            .try_for_each(|feature| writer.write_string(feature))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct KeepAlive {
+    pub id: i64,
+}
+
+impl ClientboundPacket for KeepAlive {
+    const CLIENTBOUND_ID: i32 = generated::packet::configuration::CLIENTBOUND_MINECRAFT_KEEP_ALIVE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.id.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+impl ServerboundPacket for KeepAlive {
+    const SERVERBOUND_ID: i32 = generated::packet::configuration::SERVERBOUND_MINECRAFT_KEEP_ALIVE;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            id: i64::from_be_bytes(reader.read_const()?),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct FinishConfiguration;
 
@@ -233,4 +322,41 @@ serverbound_packet_enum!(pub ConfigurationPacket;
     ClientInformation, ClientInformation;
     SelectKnownPacks, SelectKnownPacks;
     FinishConfiguration, FinishConfiguration;
+    KeepAlive, KeepAlive;
 );
+
+#[cfg(test)]
+mod test {
+    use pkmc_util::packet::ClientboundPacket as _;
+
+    use crate::packet::configuration::UpdateEnabledFeatures;
+
+    #[test]
+    fn test_update_enabled_features() {
+        let mut data = Vec::new();
+        UpdateEnabledFeatures::default()
+            .packet_write(&mut data)
+            .unwrap();
+        assert_eq!(data, [&[1, 17][..], b"minecraft:vanilla"].concat());
+
+        let mut data = Vec::new();
+        UpdateEnabledFeatures {
+            features: vec![
+                "minecraft:vanilla".to_owned(),
+                "minecraft:trade_rebalance".to_owned(),
+            ],
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(
+            data,
+            [
+                &[2, 17][..],
+                b"minecraft:vanilla",
+                &[25],
+                b"minecraft:trade_rebalance"
+            ]
+            .concat()
+        );
+    }
+}
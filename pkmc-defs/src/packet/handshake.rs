@@ -22,9 +22,10 @@ impl TryFrom<i32> for IntentionNextState {
             1 => Ok(IntentionNextState::Status),
             2 => Ok(IntentionNextState::Login),
             3 => Ok(IntentionNextState::Transfer),
-            _ => Err(ConnectionError::Other(
-                format!("Packet Intention next_state invalid value {}", value).into(),
-            )),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::handshake::IntentionNextState",
+                value,
+            }),
         }
     }
 }
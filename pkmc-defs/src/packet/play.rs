@@ -1,16 +1,23 @@
-use std::io::{Read, Write};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{Read, Write},
+};
 
 use pkmc_util::{
     nbt::NBT,
     nbt_compound,
     packet::{
-        to_paletted_data_singular, BitSet, ClientboundPacket, ConnectionError, ReadExtPacket as _,
-        ServerboundPacket, WriteExtPacket,
+        to_paletted_data_singular, BitSet, ClientboundPacket, ConnectionError, FixedBitSet,
+        ReadExtPacket as _, ServerboundPacket, WriteExtPacket,
     },
     serverbound_packet_enum, Position, ReadExt as _, Transmutable, UUID,
 };
 
-use crate::{generated::generated, text_component::TextComponent};
+use crate::{
+    entity::{entity_type_id, is_living_entity_type},
+    generated::generated,
+    text_component::TextComponent,
+};
 
 pub struct Login {
     pub entity_id: i32,
@@ -149,6 +156,69 @@ impl ServerboundPacket for PlayerLoaded {
     }
 }
 
+/// Which values of [`PlayerPosition`] are relative to the player's current values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RelativeFlags {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+    pub yaw: bool,
+    pub pitch: bool,
+    pub delta_x: bool,
+    pub delta_y: bool,
+    pub delta_z: bool,
+    /// Rotate velocity by the change in rotation.
+    pub rotate_delta: bool,
+}
+
+impl RelativeFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_position(mut self, relative: bool) -> Self {
+        self.x = relative;
+        self.y = relative;
+        self.z = relative;
+        self
+    }
+
+    pub fn with_angle(mut self, relative: bool) -> Self {
+        self.yaw = relative;
+        self.pitch = relative;
+        self
+    }
+
+    pub fn with_delta(mut self, relative: bool) -> Self {
+        self.delta_x = relative;
+        self.delta_y = relative;
+        self.delta_z = relative;
+        self
+    }
+
+    pub fn with_rotate_delta(mut self, rotate_delta: bool) -> Self {
+        self.rotate_delta = rotate_delta;
+        self
+    }
+
+    pub fn mask(&self) -> i32 {
+        [
+            self.x,
+            self.y,
+            self.z,
+            self.yaw,
+            self.pitch,
+            self.delta_x,
+            self.delta_y,
+            self.delta_z,
+            self.rotate_delta,
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(0, |mask, (bit, set)| mask | ((set as i32) << bit))
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct PlayerPosition {
     pub x: f64,
@@ -163,9 +233,27 @@ pub struct PlayerPosition {
     pub pitch: f32,
     pub angle_relative: bool,
     pub rotate_delta: bool,
+    /// Combined with the other relative bools.
+    pub relative: RelativeFlags,
     pub teleport_id: i32,
 }
 
+impl PlayerPosition {
+    pub fn relative_flags(&self) -> RelativeFlags {
+        RelativeFlags {
+            x: self.relative.x || self.pos_relative,
+            y: self.relative.y || self.pos_relative,
+            z: self.relative.z || self.pos_relative,
+            yaw: self.relative.yaw || self.angle_relative,
+            pitch: self.relative.pitch || self.angle_relative,
+            delta_x: self.relative.delta_x || self.delta_relative,
+            delta_y: self.relative.delta_y || self.delta_relative,
+            delta_z: self.relative.delta_z || self.delta_relative,
+            rotate_delta: self.relative.rotate_delta || self.rotate_delta,
+        }
+    }
+}
+
 impl ClientboundPacket for PlayerPosition {
     const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_PLAYER_POSITION;
 
@@ -179,13 +267,7 @@ impl ClientboundPacket for PlayerPosition {
         writer.write_all(&self.delta_z.to_be_bytes())?;
         writer.write_all(&self.yaw.to_be_bytes())?;
         writer.write_all(&self.pitch.to_be_bytes())?;
-        writer.write_all(
-            &(if self.pos_relative { 0b111i32 } else { 0 }
-                | if self.angle_relative { 0b11000 } else { 0 }
-                | if self.delta_relative { 0b11100000 } else { 0 }
-                | if self.rotate_delta { 0b100000000 } else { 0 })
-            .to_be_bytes(),
-        )?;
+        writer.write_all(&self.relative_flags().mask().to_be_bytes())?;
         Ok(())
     }
 }
@@ -588,9 +670,10 @@ impl TryFrom<i32> for PlayerCommandAction {
             6 => Ok(PlayerCommandAction::StopJumpWithHorse),
             7 => Ok(PlayerCommandAction::OpenVehicleInventory),
             8 => Ok(PlayerCommandAction::StartFlyingWithElytra),
-            _ => Err(ConnectionError::Other(
-                "packet::play::PlayerActionCommand invalid varint value".into(),
-            )),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::PlayerCommandAction",
+                value,
+            }),
         }
     }
 }
@@ -633,6 +716,103 @@ impl ClientboundPacket for SystemChat {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTypeParameter {
+    Sender,
+    Target,
+    Content,
+}
+
+impl ChatTypeParameter {
+    fn write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(match self {
+            ChatTypeParameter::Sender => 0,
+            ChatTypeParameter::Target => 1,
+            ChatTypeParameter::Content => 2,
+        })?;
+        Ok(())
+    }
+}
+
+/// How a chat message is formatted, the translation key gets the parameters in order.
+#[derive(Debug, Clone)]
+pub struct ChatTypeDecoration {
+    pub translation_key: String,
+    pub parameters: Vec<ChatTypeParameter>,
+    /// Style compound, same fields as a text component without the content.
+    pub style: NBT,
+}
+
+impl ChatTypeDecoration {
+    pub fn new(translation_key: &str, parameters: &[ChatTypeParameter]) -> Self {
+        Self {
+            translation_key: translation_key.to_owned(),
+            parameters: parameters.to_vec(),
+            style: NBT::Compound(Default::default()),
+        }
+    }
+
+    fn write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.translation_key)?;
+        writer.write_varint(self.parameters.len() as i32)?;
+        self.parameters
+            .iter()
+            .try_for_each(|parameter| parameter.write(&mut writer))?;
+        writer.write_nbt(&self.style)?;
+        Ok(())
+    }
+}
+
+/// Unsigned player chat message.
+///
+/// The chat type is sent inline instead of referencing the minecraft:chat_type registry, as that
+/// registry isn't sent to the client.
+#[derive(Debug)]
+pub struct DisguisedChatMessage {
+    pub content: TextComponent,
+    pub chat: ChatTypeDecoration,
+    pub narration: ChatTypeDecoration,
+    pub sender_name: TextComponent,
+    pub target_name: Option<TextComponent>,
+}
+
+impl DisguisedChatMessage {
+    /// Formatted like vanilla "<sender> content" chat.
+    pub fn new(sender_name: TextComponent, content: TextComponent) -> Self {
+        Self {
+            content,
+            chat: ChatTypeDecoration::new(
+                "chat.type.text",
+                &[ChatTypeParameter::Sender, ChatTypeParameter::Content],
+            ),
+            narration: ChatTypeDecoration::new(
+                "chat.type.text.narrate",
+                &[ChatTypeParameter::Sender, ChatTypeParameter::Content],
+            ),
+            sender_name,
+            target_name: None,
+        }
+    }
+}
+
+impl ClientboundPacket for DisguisedChatMessage {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_DISGUISED_CHAT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_nbt(&self.content.to_nbt())?;
+        // Holder ID, 0 means inline, otherwise it's registry ID + 1.
+        writer.write_varint(0)?;
+        self.chat.write(&mut writer)?;
+        self.narration.write(&mut writer)?;
+        writer.write_nbt(&self.sender_name.to_nbt())?;
+        writer.write_bool(self.target_name.is_some())?;
+        if let Some(target_name) = &self.target_name {
+            writer.write_nbt(&target_name.to_nbt())?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct SetActionBarText(pub TextComponent);
 
@@ -645,6 +825,287 @@ impl ClientboundPacket for SetActionBarText {
     }
 }
 
+#[derive(Debug)]
+pub struct SetTitleText(pub TextComponent);
+
+impl ClientboundPacket for SetTitleText {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_TITLE_TEXT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_nbt(&self.0.to_nbt())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SetSubtitleText(pub TextComponent);
+
+impl ClientboundPacket for SetSubtitleText {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_SUBTITLE_TEXT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_nbt(&self.0.to_nbt())?;
+        Ok(())
+    }
+}
+
+/// Title timings, all in ticks.
+#[derive(Debug)]
+pub struct SetTitleAnimationTimes {
+    pub fade_in: i32,
+    pub stay: i32,
+    pub fade_out: i32,
+}
+
+impl ClientboundPacket for SetTitleAnimationTimes {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_TITLES_ANIMATION;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.fade_in.to_be_bytes())?;
+        writer.write_all(&self.stay.to_be_bytes())?;
+        writer.write_all(&self.fade_out.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveRenderType {
+    Integer,
+    Hearts,
+}
+
+#[derive(Debug)]
+pub enum SetObjectiveAction {
+    Create {
+        display_name: TextComponent,
+        render_type: ObjectiveRenderType,
+    },
+    Remove,
+    Update {
+        display_name: TextComponent,
+        render_type: ObjectiveRenderType,
+    },
+}
+
+#[derive(Debug)]
+pub struct SetObjective {
+    pub name: String,
+    pub action: SetObjectiveAction,
+}
+
+impl ClientboundPacket for SetObjective {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_OBJECTIVE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.name)?;
+        let (mode, display) = match &self.action {
+            SetObjectiveAction::Create {
+                display_name,
+                render_type,
+            } => (0u8, Some((display_name, render_type))),
+            SetObjectiveAction::Remove => (1u8, None),
+            SetObjectiveAction::Update {
+                display_name,
+                render_type,
+            } => (2u8, Some((display_name, render_type))),
+        };
+        writer.write_all(&mode.to_be_bytes())?;
+        if let Some((display_name, render_type)) = display {
+            writer.write_nbt(&display_name.to_nbt())?;
+            writer.write_varint(match render_type {
+                ObjectiveRenderType::Integer => 0,
+                ObjectiveRenderType::Hearts => 1,
+            })?;
+            // Number format, client uses the default styled numbers.
+            writer.write_bool(false)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplaySlot {
+    List,
+    Sidebar,
+    BelowName,
+}
+
+/// Shows an objective in a display slot, an empty objective name clears the slot.
+#[derive(Debug)]
+pub struct SetDisplayObjective {
+    pub slot: DisplaySlot,
+    pub objective: String,
+}
+
+impl ClientboundPacket for SetDisplayObjective {
+    const CLIENTBOUND_ID: i32 =
+        generated::packet::play::CLIENTBOUND_MINECRAFT_SET_DISPLAY_OBJECTIVE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(match self.slot {
+            DisplaySlot::List => 0,
+            DisplaySlot::Sidebar => 1,
+            DisplaySlot::BelowName => 2,
+        })?;
+        writer.write_string(&self.objective)?;
+        Ok(())
+    }
+}
+
+/// Creates or updates a score, the entity name is a player name or entity UUID.
+#[derive(Debug)]
+pub struct SetScore {
+    pub entity_name: String,
+    pub objective: String,
+    pub value: i32,
+    /// Shown instead of the entity name.
+    pub display_name: Option<TextComponent>,
+}
+
+impl ClientboundPacket for SetScore {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_SCORE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.entity_name)?;
+        writer.write_string(&self.objective)?;
+        writer.write_varint(self.value)?;
+        writer.write_bool(self.display_name.is_some())?;
+        if let Some(display_name) = &self.display_name {
+            writer.write_nbt(&display_name.to_nbt())?;
+        }
+        // Number format
+        writer.write_bool(false)?;
+        Ok(())
+    }
+}
+
+/// Removes a score, from all objectives if no objective is given.
+#[derive(Debug)]
+pub struct ResetScore {
+    pub entity_name: String,
+    pub objective: Option<String>,
+}
+
+impl ClientboundPacket for ResetScore {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_RESET_SCORE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.entity_name)?;
+        writer.write_bool(self.objective.is_some())?;
+        if let Some(objective) = &self.objective {
+            writer.write_string(objective)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets the whole world border state, sent on join.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitializeBorder {
+    pub x: f64,
+    pub z: f64,
+    pub old_diameter: f64,
+    pub new_diameter: f64,
+    /// Milliseconds to lerp from the old diameter to the new diameter.
+    pub speed: i64,
+    pub portal_teleport_boundary: i32,
+    /// In blocks.
+    pub warning_distance: i32,
+    /// In seconds.
+    pub warning_time: i32,
+}
+
+impl ClientboundPacket for InitializeBorder {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_INITIALIZE_BORDER;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.x.to_be_bytes())?;
+        writer.write_all(&self.z.to_be_bytes())?;
+        writer.write_all(&self.old_diameter.to_be_bytes())?;
+        writer.write_all(&self.new_diameter.to_be_bytes())?;
+        writer.write_varlong(self.speed)?;
+        writer.write_varint(self.portal_teleport_boundary)?;
+        writer.write_varint(self.warning_distance)?;
+        writer.write_varint(self.warning_time)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderCenter {
+    pub x: f64,
+    pub z: f64,
+}
+
+impl ClientboundPacket for SetBorderCenter {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_BORDER_CENTER;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.x.to_be_bytes())?;
+        writer.write_all(&self.z.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderLerpSize {
+    pub old_diameter: f64,
+    pub new_diameter: f64,
+    /// Milliseconds to lerp from the old diameter to the new diameter.
+    pub speed: i64,
+}
+
+impl ClientboundPacket for SetBorderLerpSize {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_BORDER_LERP_SIZE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.old_diameter.to_be_bytes())?;
+        writer.write_all(&self.new_diameter.to_be_bytes())?;
+        writer.write_varlong(self.speed)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderSize(pub f64);
+
+impl ClientboundPacket for SetBorderSize {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_BORDER_SIZE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.0.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+/// In seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderWarningDelay(pub i32);
+
+impl ClientboundPacket for SetBorderWarningDelay {
+    const CLIENTBOUND_ID: i32 =
+        generated::packet::play::CLIENTBOUND_MINECRAFT_SET_BORDER_WARNING_DELAY;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.0)?;
+        Ok(())
+    }
+}
+
+/// In blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBorderWarningDistance(pub i32);
+
+impl ClientboundPacket for SetBorderWarningDistance {
+    const CLIENTBOUND_ID: i32 =
+        generated::packet::play::CLIENTBOUND_MINECRAFT_SET_BORDER_WARNING_DISTANCE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.0)?;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum ServerLink {
     BugReport,
@@ -750,7 +1211,14 @@ impl ServerboundPacket for SwingArm {
     where
         Self: Sized,
     {
-        Ok(Self(reader.read_varint()?))
+        match reader.read_varint()? {
+            // Main hand or off hand
+            hand @ (0 | 1) => Ok(Self(hand)),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::SwingArm",
+                value,
+            }),
+        }
     }
 }
 
@@ -823,18 +1291,1308 @@ impl ClientboundPacket for AddEntity {
     }
 }
 
-serverbound_packet_enum!(pub PlayPacket;
-    KeepAlive, KeepAlive;
-    PlayerLoaded, PlayerLoaded;
-    AcceptTeleportation, AcceptTeleportation;
-    MovePlayerPosRot, MovePlayerPosRot;
-    MovePlayerPos, MovePlayerPos;
-    MovePlayerRot, MovePlayerRot;
-    MovePlayerStatusOnly, MovePlayerStatusOnly;
-    ClientTickEnd, ClientTickEnd;
-    PlayerInput, PlayerInput;
-    PlayerAbilities_Serverbound, PlayerAbilities;
-    PlayerCommand, PlayerCommand;
-    SetCarriedItem, SetHeldItem;
-    SwingArm, SwingArm;
-);
+/// Absolute position & velocity of an entity, velocity is in blocks per tick and is used by the
+/// client to interpolate between syncs.
+#[derive(Debug)]
+pub struct EntityPositionSync {
+    pub id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub velocity_x: f64,
+    pub velocity_y: f64,
+    pub velocity_z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+impl ClientboundPacket for EntityPositionSync {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_ENTITY_POSITION_SYNC;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.id)?;
+        writer.write_all(&self.x.to_be_bytes())?;
+        writer.write_all(&self.y.to_be_bytes())?;
+        writer.write_all(&self.z.to_be_bytes())?;
+        writer.write_all(&self.velocity_x.to_be_bytes())?;
+        writer.write_all(&self.velocity_y.to_be_bytes())?;
+        writer.write_all(&self.velocity_z.to_be_bytes())?;
+        writer.write_all(&self.yaw.to_be_bytes())?;
+        writer.write_all(&self.pitch.to_be_bytes())?;
+        writer.write_bool(self.on_ground)?;
+        Ok(())
+    }
+}
+
+/// A single entity metadata value, only the commonly used types are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityMetadata {
+    Byte(i8),
+    VarInt(i32),
+    VarLong(i64),
+    Float(f32),
+    String(String),
+    TextComponent(TextComponent),
+    OptionalTextComponent(Option<TextComponent>),
+    Boolean(bool),
+    Rotations(f32, f32, f32),
+    Position(Position),
+    BlockState(i32),
+    Pose(i32),
+}
+
+impl EntityMetadata {
+    fn type_id(&self) -> i32 {
+        match self {
+            EntityMetadata::Byte(..) => 0,
+            EntityMetadata::VarInt(..) => 1,
+            EntityMetadata::VarLong(..) => 2,
+            EntityMetadata::Float(..) => 3,
+            EntityMetadata::String(..) => 4,
+            EntityMetadata::TextComponent(..) => 5,
+            EntityMetadata::OptionalTextComponent(..) => 6,
+            EntityMetadata::Boolean(..) => 8,
+            EntityMetadata::Rotations(..) => 9,
+            EntityMetadata::Position(..) => 10,
+            EntityMetadata::BlockState(..) => 14,
+            EntityMetadata::Pose(..) => 21,
+        }
+    }
+
+    fn write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.type_id())?;
+        match self {
+            EntityMetadata::Byte(value) => writer.write_all(&value.to_be_bytes())?,
+            EntityMetadata::VarInt(value) => writer.write_varint(*value)?,
+            EntityMetadata::VarLong(value) => writer.write_varlong(*value)?,
+            EntityMetadata::Float(value) => writer.write_all(&value.to_be_bytes())?,
+            EntityMetadata::String(value) => writer.write_string(value)?,
+            EntityMetadata::TextComponent(value) => writer.write_nbt(&value.to_nbt())?,
+            EntityMetadata::OptionalTextComponent(value) => {
+                writer.write_bool(value.is_some())?;
+                if let Some(value) = value {
+                    writer.write_nbt(&value.to_nbt())?;
+                }
+            }
+            EntityMetadata::Boolean(value) => writer.write_bool(*value)?,
+            EntityMetadata::Rotations(x, y, z) => {
+                writer.write_all(&x.to_be_bytes())?;
+                writer.write_all(&y.to_be_bytes())?;
+                writer.write_all(&z.to_be_bytes())?;
+            }
+            EntityMetadata::Position(value) => writer.write_position(value)?,
+            EntityMetadata::BlockState(value) => writer.write_varint(*value)?,
+            EntityMetadata::Pose(value) => writer.write_varint(*value)?,
+        }
+        Ok(())
+    }
+}
+
+/// Entity metadata values by index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntityMetadataBundle(pub BTreeMap<u8, EntityMetadata>);
+
+impl EntityMetadataBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Client side defaults of the values that every entity has.
+    pub fn entity_default() -> Self {
+        Self(BTreeMap::from([
+            (0, EntityMetadata::Byte(0)),
+            (1, EntityMetadata::VarInt(300)),
+            (2, EntityMetadata::OptionalTextComponent(None)),
+            (3, EntityMetadata::Boolean(false)),
+            (4, EntityMetadata::Boolean(false)),
+            (5, EntityMetadata::Boolean(false)),
+            (6, EntityMetadata::Pose(0)),
+            (7, EntityMetadata::VarInt(0)),
+        ]))
+    }
+
+    /// Client side defaults for living entities, besides values that can't be represented yet.
+    pub fn living_entity_default() -> Self {
+        let mut bundle = Self::entity_default();
+        bundle.0.extend([
+            (8, EntityMetadata::Byte(0)),
+            (9, EntityMetadata::Float(1.0)),
+            (11, EntityMetadata::Boolean(false)),
+            (12, EntityMetadata::VarInt(0)),
+            (13, EntityMetadata::VarInt(0)),
+        ]);
+        bundle
+    }
+
+    pub fn player_default() -> Self {
+        let mut bundle = Self::living_entity_default();
+        bundle.0.extend([
+            (15, EntityMetadata::Float(0.0)),
+            (16, EntityMetadata::VarInt(0)),
+            (17, EntityMetadata::Byte(0)),
+            (18, EntityMetadata::Byte(1)),
+        ]);
+        bundle
+    }
+
+    /// Defaults for the entity type, for types without their own defaults this is only the
+    /// entity or living entity defaults.
+    pub fn default_for_type(entity_type: i32) -> Self {
+        if Some(entity_type) == entity_type_id("minecraft:player") {
+            Self::player_default()
+        } else if is_living_entity_type(entity_type) {
+            Self::living_entity_default()
+        } else {
+            Self::entity_default()
+        }
+    }
+
+    /// Bundle with only the values that are different in other.
+    pub fn changed_from(&self, other: &Self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|(index, value)| other.0.get(index) != Some(value))
+                .map(|(index, value)| (*index, value.clone()))
+                .collect(),
+        )
+    }
+
+    /// Bundle with only the values at the indices.
+    pub fn subset<'a>(&self, indices: impl IntoIterator<Item = &'a u8>) -> Self {
+        Self(
+            indices
+                .into_iter()
+                .filter_map(|index| Some((*index, self.0.get(index)?.clone())))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct SetEntityMetadata {
+    pub entity_id: i32,
+    pub metadata: EntityMetadataBundle,
+}
+
+impl ClientboundPacket for SetEntityMetadata {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_ENTITY_DATA;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.entity_id)?;
+        for (index, value) in self.metadata.0.iter() {
+            writer.write_all(&index.to_be_bytes())?;
+            value.write(&mut writer)?;
+        }
+        writer.write_all(&[0xFF])?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Chat {
+    pub message: String,
+    pub timestamp: i64,
+    pub salt: i64,
+    pub signature: Option<Box<[u8; 256]>>,
+    pub message_count: i32,
+    pub acknowledged: FixedBitSet<20>,
+}
+
+impl ServerboundPacket for Chat {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CHAT;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            message: reader.read_string()?,
+            timestamp: i64::from_be_bytes(reader.read_const()?),
+            salt: i64::from_be_bytes(reader.read_const()?),
+            signature: if reader.read_bool()? {
+                Some(Box::new(reader.read_const()?))
+            } else {
+                None
+            },
+            message_count: reader.read_varint()?,
+            acknowledged: reader.read_fixed_bitset()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ChatAck {
+    pub message_count: i32,
+}
+
+impl ServerboundPacket for ChatAck {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CHAT_ACK;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            message_count: reader.read_varint()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ChatCommand {
+    pub command: String,
+}
+
+impl ServerboundPacket for ChatCommand {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CHAT_COMMAND;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            command: reader.read_string()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockFace {
+    Bottom,
+    Top,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl BlockFace {
+    /// Offset to the block that is touching this face.
+    pub fn offset(&self) -> Position {
+        match self {
+            BlockFace::Bottom => Position::new(0, -1, 0),
+            BlockFace::Top => Position::new(0, 1, 0),
+            BlockFace::North => Position::new(0, 0, -1),
+            BlockFace::South => Position::new(0, 0, 1),
+            BlockFace::West => Position::new(-1, 0, 0),
+            BlockFace::East => Position::new(1, 0, 0),
+        }
+    }
+}
+
+impl TryFrom<i32> for BlockFace {
+    type Error = ConnectionError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(BlockFace::Bottom),
+            1 => Ok(BlockFace::Top),
+            2 => Ok(BlockFace::North),
+            3 => Ok(BlockFace::South),
+            4 => Ok(BlockFace::West),
+            5 => Ok(BlockFace::East),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::BlockFace",
+                value,
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UseItemOn {
+    pub hand: i32,
+    pub location: Position,
+    pub face: BlockFace,
+    pub cursor_x: f32,
+    pub cursor_y: f32,
+    pub cursor_z: f32,
+    pub inside_block: bool,
+    pub world_border_hit: bool,
+    pub sequence: i32,
+}
+
+impl ServerboundPacket for UseItemOn {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_USE_ITEM_ON;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            hand: reader.read_varint()?,
+            location: reader.read_position()?,
+            face: BlockFace::try_from(reader.read_varint()?)?,
+            cursor_x: f32::from_be_bytes(reader.read_const()?),
+            cursor_y: f32::from_be_bytes(reader.read_const()?),
+            cursor_z: f32::from_be_bytes(reader.read_const()?),
+            inside_block: reader.read_bool()?,
+            world_border_hit: reader.read_bool()?,
+            sequence: reader.read_varint()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InteractKind {
+    /// Right click on the entity.
+    Interact {
+        hand: i32,
+    },
+    Attack,
+    /// Right click at a position relative to the entity, sent alongside [`InteractKind::Interact`].
+    InteractAt {
+        x: f32,
+        y: f32,
+        z: f32,
+        hand: i32,
+    },
+}
+
+#[derive(Debug)]
+pub struct Interact {
+    pub entity_id: i32,
+    pub kind: InteractKind,
+    pub sneaking: bool,
+}
+
+impl ServerboundPacket for Interact {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_INTERACT;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let entity_id = reader.read_varint()?;
+        let kind = match reader.read_varint()? {
+            0 => InteractKind::Interact {
+                hand: reader.read_varint()?,
+            },
+            1 => InteractKind::Attack,
+            2 => InteractKind::InteractAt {
+                x: f32::from_be_bytes(reader.read_const()?),
+                y: f32::from_be_bytes(reader.read_const()?),
+                z: f32::from_be_bytes(reader.read_const()?),
+                hand: reader.read_varint()?,
+            },
+            value => {
+                return Err(ConnectionError::InvalidEnumValue {
+                    packet: "packet::play::Interact",
+                    value,
+                })
+            }
+        };
+        Ok(Self {
+            entity_id,
+            kind,
+            sneaking: reader.read_bool()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockUpdate {
+    pub location: Position,
+    pub block_id: i32,
+}
+
+impl ClientboundPacket for BlockUpdate {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_BLOCK_UPDATE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_position(&self.location)?;
+        writer.write_varint(self.block_id)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockEntityData {
+    pub location: Position,
+    pub r#type: i32,
+    pub data: NBT,
+}
+
+impl ClientboundPacket for BlockEntityData {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_BLOCK_ENTITY_DATA;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_position(&self.location)?;
+        writer.write_varint(self.r#type)?;
+        writer.write_nbt(&self.data)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockChangedAck(pub i32);
+
+impl ClientboundPacket for BlockChangedAck {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_BLOCK_CHANGED_ACK;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.0)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoveEntities(pub HashSet<i32>);
+
+impl ClientboundPacket for RemoveEntities {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_REMOVE_ENTITIES;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.0.len() as i32)?;
+        for id in self.0.iter() {
+            writer.write_varint(*id)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct UpdateMobEffect {
+    pub entity_id: i32,
+    /// See [`crate::mob_effect::mob_effect_id`].
+    pub effect_id: i32,
+    pub amplifier: i32,
+    /// Duration in ticks, -1 for infinite.
+    pub duration: i32,
+    /// 0x01: Ambient, 0x02: Show particles, 0x04: Show icon, 0x08: Blend
+    pub flags: u8,
+}
+
+impl ClientboundPacket for UpdateMobEffect {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_UPDATE_MOB_EFFECT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.entity_id)?;
+        writer.write_varint(self.effect_id)?;
+        writer.write_varint(self.amplifier)?;
+        writer.write_varint(self.duration)?;
+        writer.write_all(&self.flags.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoveMobEffect {
+    pub entity_id: i32,
+    pub effect_id: i32,
+}
+
+impl ClientboundPacket for RemoveMobEffect {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_REMOVE_MOB_EFFECT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.entity_id)?;
+        writer.write_varint(self.effect_id)?;
+        Ok(())
+    }
+}
+
+/// Makes the client view from the entity, set to the player's own entity to reset.
+#[derive(Debug)]
+pub struct SetCamera {
+    pub entity_id: i32,
+}
+
+impl ClientboundPacket for SetCamera {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_CAMERA;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.entity_id)?;
+        Ok(())
+    }
+}
+
+/// Tells the client to connect to another server.
+#[derive(Debug)]
+pub struct Transfer {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ClientboundPacket for Transfer {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_TRANSFER;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.host)?;
+        writer.write_varint(self.port as i32)?;
+        Ok(())
+    }
+}
+
+/// Max size of a cookie payload the client will accept.
+pub const COOKIE_MAX_SIZE: usize = 5120;
+
+/// Stores a cookie on the client, this is kept across transfers.
+#[derive(Debug)]
+pub struct StoreCookie {
+    pub key: String,
+    pub payload: Box<[u8]>,
+}
+
+impl ClientboundPacket for StoreCookie {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_STORE_COOKIE;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.key)?;
+        writer.write_varint(self.payload.len() as i32)?;
+        writer.write_all(&self.payload)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CookieRequest {
+    pub key: String,
+}
+
+impl ClientboundPacket for CookieRequest {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_COOKIE_REQUEST;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_string(&self.key)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CookieResponse {
+    pub key: String,
+    /// None if the client doesn't have the cookie stored.
+    pub payload: Option<Box<[u8]>>,
+}
+
+impl ServerboundPacket for CookieResponse {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_COOKIE_RESPONSE;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let key = reader.read_string()?;
+        let payload = if reader.read_bool()? {
+            let length = reader.read_varint()? as usize;
+            if length > COOKIE_MAX_SIZE {
+                return Err(ConnectionError::Other(
+                    format!("Cookie payload too large ({} bytes)", length).into(),
+                ));
+            }
+            Some(reader.read_var(length)?)
+        } else {
+            None
+        };
+        Ok(Self { key, payload })
+    }
+}
+
+#[derive(Debug)]
+pub struct AddResourcePack {
+    pub uuid: UUID,
+    pub url: String,
+    /// Hex SHA-1 hash of the resource pack, may be empty.
+    pub hash: String,
+    /// If the client disconnects when declining the resource pack.
+    pub forced: bool,
+    pub prompt: Option<TextComponent>,
+}
+
+impl ClientboundPacket for AddResourcePack {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_RESOURCE_PACK_PUSH;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_uuid(&self.uuid)?;
+        writer.write_string(&self.url)?;
+        writer.write_string(&self.hash)?;
+        writer.write_bool(self.forced)?;
+        writer.write_bool(self.prompt.is_some())?;
+        if let Some(prompt) = &self.prompt {
+            writer.write_nbt(&prompt.to_nbt())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoveResourcePack {
+    /// None to remove all resource packs.
+    pub uuid: Option<UUID>,
+}
+
+impl ClientboundPacket for RemoveResourcePack {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_RESOURCE_PACK_POP;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_bool(self.uuid.is_some())?;
+        if let Some(uuid) = &self.uuid {
+            writer.write_uuid(uuid)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePackStatus {
+    SuccessfullyDownloaded,
+    Declined,
+    FailedDownload,
+    Accepted,
+    Downloaded,
+    InvalidUrl,
+    FailedReload,
+    Discarded,
+}
+
+impl TryFrom<i32> for ResourcePackStatus {
+    type Error = ConnectionError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ResourcePackStatus::SuccessfullyDownloaded),
+            1 => Ok(ResourcePackStatus::Declined),
+            2 => Ok(ResourcePackStatus::FailedDownload),
+            3 => Ok(ResourcePackStatus::Accepted),
+            4 => Ok(ResourcePackStatus::Downloaded),
+            5 => Ok(ResourcePackStatus::InvalidUrl),
+            6 => Ok(ResourcePackStatus::FailedReload),
+            7 => Ok(ResourcePackStatus::Discarded),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::ResourcePackStatus",
+                value,
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResourcePackResponse {
+    pub uuid: UUID,
+    pub status: ResourcePackStatus,
+}
+
+impl ServerboundPacket for ResourcePackResponse {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_RESOURCE_PACK;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            uuid: reader.read_uuid()?,
+            status: ResourcePackStatus::try_from(reader.read_varint()?)?,
+        })
+    }
+}
+
+/// Plugin message, same as [`super::configuration::CustomPayload`] but for the play state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomPayload {
+    Unknown { channel: String, data: Box<[u8]> },
+    Brand(String),
+}
+
+impl ServerboundPacket for CustomPayload {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        let channel = reader.read_string()?;
+        match channel.as_ref() {
+            "minecraft:brand" => Ok(CustomPayload::Brand(reader.read_string()?)),
+            _ => Ok(CustomPayload::Unknown {
+                channel,
+                data: reader.read_all()?,
+            }),
+        }
+    }
+}
+
+impl ClientboundPacket for CustomPayload {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_CUSTOM_PAYLOAD;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        match self {
+            CustomPayload::Unknown { channel, data } => {
+                writer.write_string(channel)?;
+                writer.write_all(data)?;
+            }
+            CustomPayload::Brand(brand) => {
+                writer.write_string("minecraft:brand")?;
+                writer.write_string(brand)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SetHealth {
+    pub health: f32,
+    pub food: i32,
+    pub food_saturation: f32,
+}
+
+impl ClientboundPacket for SetHealth {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_SET_HEALTH;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_all(&self.health.to_be_bytes())?;
+        writer.write_varint(self.food)?;
+        writer.write_all(&self.food_saturation.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+/// Plays the hurt animation & sound on an entity.
+#[derive(Debug)]
+pub struct DamageEvent {
+    pub entity_id: i32,
+    /// Id in the minecraft:damage_type registry.
+    pub source_type_id: i32,
+    pub source_cause_id: Option<i32>,
+    pub source_direct_id: Option<i32>,
+    pub source_position: Option<(f64, f64, f64)>,
+}
+
+impl ClientboundPacket for DamageEvent {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_DAMAGE_EVENT;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.entity_id)?;
+        writer.write_varint(self.source_type_id)?;
+        // Entity ids are offset by 1, 0 being no entity.
+        writer.write_varint(self.source_cause_id.map(|id| id + 1).unwrap_or(0))?;
+        writer.write_varint(self.source_direct_id.map(|id| id + 1).unwrap_or(0))?;
+        if let Some((x, y, z)) = self.source_position {
+            writer.write_bool(true)?;
+            writer.write_all(&x.to_be_bytes())?;
+            writer.write_all(&y.to_be_bytes())?;
+            writer.write_all(&z.to_be_bytes())?;
+        } else {
+            writer.write_bool(false)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows the death screen.
+#[derive(Debug)]
+pub struct PlayerCombatKill {
+    pub player_id: i32,
+    pub message: TextComponent,
+}
+
+impl ClientboundPacket for PlayerCombatKill {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_PLAYER_COMBAT_KILL;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.player_id)?;
+        writer.write_nbt(&self.message.to_nbt())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Respawn {
+    pub dimension_type: i32,
+    pub dimension_name: String,
+    pub hashed_seed: i64,
+    pub game_mode: u8,
+    pub previous_game_mode: i8,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    pub death: Option<(String, Position)>,
+    pub portal_cooldown: i32,
+    pub sea_level: i32,
+    /// 0x01 keeps attributes, 0x02 keeps metadata.
+    pub data_kept: u8,
+}
+
+impl ClientboundPacket for Respawn {
+    const CLIENTBOUND_ID: i32 = generated::packet::play::CLIENTBOUND_MINECRAFT_RESPAWN;
+
+    fn packet_write(&self, mut writer: impl Write) -> Result<(), ConnectionError> {
+        writer.write_varint(self.dimension_type)?;
+        writer.write_string(&self.dimension_name)?;
+        writer.write_all(&self.hashed_seed.to_be_bytes())?;
+        writer.write_all(&self.game_mode.to_be_bytes())?;
+        writer.write_all(&self.previous_game_mode.to_be_bytes())?;
+        writer.write_bool(self.is_debug)?;
+        writer.write_bool(self.is_flat)?;
+        if let Some(death) = &self.death {
+            writer.write_bool(true)?;
+            writer.write_string(&death.0)?;
+            writer.write_position(&death.1)?;
+        } else {
+            writer.write_bool(false)?;
+        }
+        writer.write_varint(self.portal_cooldown)?;
+        writer.write_varint(self.sea_level)?;
+        writer.write_all(&self.data_kept.to_be_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientCommand {
+    PerformRespawn,
+    RequestStats,
+}
+
+impl ServerboundPacket for ClientCommand {
+    const SERVERBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CLIENT_COMMAND;
+
+    fn packet_read(mut reader: impl Read) -> Result<Self, ConnectionError>
+    where
+        Self: Sized,
+    {
+        match reader.read_varint()? {
+            0 => Ok(ClientCommand::PerformRespawn),
+            1 => Ok(ClientCommand::RequestStats),
+            value => Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::ClientCommand",
+                value,
+            }),
+        }
+    }
+}
+
+serverbound_packet_enum!(pub PlayPacket;
+    KeepAlive, KeepAlive;
+    PlayerLoaded, PlayerLoaded;
+    AcceptTeleportation, AcceptTeleportation;
+    MovePlayerPosRot, MovePlayerPosRot;
+    MovePlayerPos, MovePlayerPos;
+    MovePlayerRot, MovePlayerRot;
+    MovePlayerStatusOnly, MovePlayerStatusOnly;
+    ClientTickEnd, ClientTickEnd;
+    PlayerInput, PlayerInput;
+    PlayerAbilities_Serverbound, PlayerAbilities;
+    PlayerCommand, PlayerCommand;
+    SetCarriedItem, SetHeldItem;
+    SwingArm, SwingArm;
+    Chat, Chat;
+    ChatAck, ChatAck;
+    ChatCommand, ChatCommand;
+    UseItemOn, UseItemOn;
+    CookieResponse, CookieResponse;
+    ResourcePackResponse, ResourcePackResponse;
+    CustomPayload, CustomPayload;
+    Interact, Interact;
+    ClientCommand, ClientCommand;
+);
+
+#[cfg(test)]
+mod test {
+    use std::io::Write as _;
+
+    use pkmc_util::packet::{
+        ClientboundPacket as _, ConnectionError, ServerboundPacket as _, WriteExtPacket as _,
+    };
+
+    use pkmc_util::UUID;
+
+    use crate::{
+        generated::generated,
+        mob_effect::mob_effect_id,
+        packet::play::{
+            AddResourcePack, CookieResponse, CustomPayload, DisguisedChatMessage, EntityMetadata,
+            EntityMetadataBundle, Interact, InteractKind, ObjectiveRenderType, PlayerCommand,
+            PlayerCommandAction, PlayerPosition, RelativeFlags, RemoveMobEffect,
+            RemoveResourcePack, ResetScore, SetActionBarText, SetCamera, SetEntityMetadata,
+            SetObjective, SetObjectiveAction, SetScore, SetSubtitleText, SetTitleAnimationTimes,
+            SetTitleText, StoreCookie, SwingArm, Transfer, UpdateMobEffect, COOKIE_MAX_SIZE,
+        },
+        text_component::TextComponent,
+    };
+
+    #[test]
+    fn test_relative_flags() {
+        let flag = |f: fn(&mut RelativeFlags)| {
+            let mut flags = RelativeFlags::new();
+            f(&mut flags);
+            flags.mask()
+        };
+        assert_eq!(RelativeFlags::new().mask(), 0);
+        assert_eq!(flag(|f| f.x = true), 1 << 0);
+        assert_eq!(flag(|f| f.y = true), 1 << 1);
+        assert_eq!(flag(|f| f.z = true), 1 << 2);
+        assert_eq!(flag(|f| f.yaw = true), 1 << 3);
+        assert_eq!(flag(|f| f.pitch = true), 1 << 4);
+        assert_eq!(flag(|f| f.delta_x = true), 1 << 5);
+        assert_eq!(flag(|f| f.delta_y = true), 1 << 6);
+        assert_eq!(flag(|f| f.delta_z = true), 1 << 7);
+        assert_eq!(flag(|f| f.rotate_delta = true), 1 << 8);
+
+        assert_eq!(RelativeFlags::new().with_position(true).mask(), 0b111);
+        assert_eq!(RelativeFlags::new().with_angle(true).mask(), 0b11000);
+        assert_eq!(RelativeFlags::new().with_delta(true).mask(), 0b11100000);
+
+        // Old bools still work.
+        let packet = PlayerPosition {
+            pos_relative: true,
+            rotate_delta: true,
+            relative: RelativeFlags {
+                yaw: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(packet.relative_flags().mask(), 0b100001111);
+    }
+
+    #[test]
+    fn test_update_mob_effect() {
+        let mut data = Vec::new();
+        UpdateMobEffect {
+            entity_id: 300,
+            effect_id: mob_effect_id("speed").unwrap(),
+            amplifier: 1,
+            duration: -1,
+            flags: 0x06,
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(
+            data,
+            vec![0xAC, 0x02, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x06]
+        );
+    }
+
+    #[test]
+    fn test_remove_mob_effect() {
+        let mut data = Vec::new();
+        RemoveMobEffect {
+            entity_id: 300,
+            effect_id: mob_effect_id("night_vision").unwrap(),
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(data, vec![0xAC, 0x02, 0x0F]);
+    }
+
+    #[test]
+    fn test_set_camera() {
+        let mut data = Vec::new();
+        SetCamera { entity_id: 1234 }
+            .packet_write(&mut data)
+            .unwrap();
+        assert_eq!(data, vec![0xD2, 0x09]);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut data = Vec::new();
+        Transfer {
+            host: "localhost".to_owned(),
+            port: 25565,
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(data, [&[9][..], b"localhost", &[0xDD, 0xC7, 0x01]].concat());
+    }
+
+    #[test]
+    fn test_cookie_round_trip() {
+        let mut data = Vec::new();
+        StoreCookie {
+            key: "pkmc:last_location".to_owned(),
+            payload: Box::new([1, 2, 3, 4]),
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(
+            data,
+            [&[18][..], b"pkmc:last_location", &[4, 1, 2, 3, 4]].concat()
+        );
+
+        // Client responds with the same cookie, which is the same layout with a "has payload" bool.
+        let response = [&data[..19], &[1], &data[19..]].concat();
+        let cookie = CookieResponse::packet_read(&response[..]).unwrap();
+        assert_eq!(cookie.key, "pkmc:last_location");
+        assert_eq!(cookie.payload.as_deref(), Some(&[1, 2, 3, 4][..]));
+
+        let empty = [&data[..19], &[0]].concat();
+        let cookie = CookieResponse::packet_read(&empty[..]).unwrap();
+        assert_eq!(cookie.key, "pkmc:last_location");
+        assert_eq!(cookie.payload, None);
+
+        let mut too_large = [&data[..19], &[1]].concat();
+        too_large.write_varint(COOKIE_MAX_SIZE as i32 + 1).unwrap();
+        assert!(CookieResponse::packet_read(&too_large[..]).is_err());
+    }
+
+    #[test]
+    fn test_add_resource_pack() {
+        let uuid = UUID([0xAB; 16]);
+        let mut data = Vec::new();
+        AddResourcePack {
+            uuid,
+            url: "https://a.b/c.zip".to_owned(),
+            hash: "".to_owned(),
+            forced: true,
+            prompt: None,
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(
+            data,
+            [&[0xAB; 16][..], &[17], b"https://a.b/c.zip", &[0, 1, 0]].concat()
+        );
+
+        let mut with_prompt = Vec::new();
+        AddResourcePack {
+            uuid,
+            url: "https://a.b/c.zip".to_owned(),
+            hash: "".to_owned(),
+            forced: false,
+            prompt: Some(TextComponent::new("Please")),
+        }
+        .packet_write(&mut with_prompt)
+        .unwrap();
+        assert_eq!(with_prompt[data.len() - 2..data.len()], [0, 1]);
+        assert!(with_prompt.len() > data.len());
+    }
+
+    #[test]
+    fn test_remove_resource_pack() {
+        let mut data = Vec::new();
+        RemoveResourcePack { uuid: None }
+            .packet_write(&mut data)
+            .unwrap();
+        assert_eq!(data, vec![0]);
+
+        let mut data = Vec::new();
+        RemoveResourcePack {
+            uuid: Some(UUID([0xAB; 16])),
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(data, [&[1][..], &[0xAB; 16]].concat());
+    }
+
+    #[test]
+    fn test_custom_payload_round_trip() {
+        let payloads = [
+            CustomPayload::Unknown {
+                channel: "bungeecord:main".to_owned(),
+                data: Box::new([0, 7, b'C', b'o', b'n', b'n', b'e', b'c', b't']),
+            },
+            CustomPayload::Unknown {
+                channel: "example:empty".to_owned(),
+                data: Box::new([]),
+            },
+            CustomPayload::Brand("pkmc".to_owned()),
+        ];
+        payloads.into_iter().for_each(|payload| {
+            let mut data = Vec::new();
+            payload.packet_write(&mut data).unwrap();
+            assert_eq!(CustomPayload::packet_read(&data[..]).unwrap(), payload);
+        });
+    }
+
+    #[test]
+    fn test_interact() {
+        let attack = Interact::packet_read(&[0xAC, 0x02, 1, 1][..]).unwrap();
+        assert_eq!(attack.entity_id, 300);
+        assert_eq!(attack.kind, InteractKind::Attack);
+        assert!(attack.sneaking);
+
+        let mut data = vec![5, 2];
+        data.extend(0.5f32.to_be_bytes());
+        data.extend(1.25f32.to_be_bytes());
+        data.extend((-0.5f32).to_be_bytes());
+        data.extend([1, 0]);
+        let interact_at = Interact::packet_read(&data[..]).unwrap();
+        assert_eq!(interact_at.entity_id, 5);
+        assert_eq!(
+            interact_at.kind,
+            InteractKind::InteractAt {
+                x: 0.5,
+                y: 1.25,
+                z: -0.5,
+                hand: 1
+            }
+        );
+        assert!(!interact_at.sneaking);
+
+        assert!(Interact::packet_read(&[5, 3, 0][..]).is_err());
+    }
+
+    #[test]
+    fn test_set_entity_metadata() {
+        let mut metadata = EntityMetadataBundle::new();
+        metadata.0.insert(0, EntityMetadata::Byte(0x20));
+        metadata.0.insert(16, EntityMetadata::VarInt(300));
+        let mut data = Vec::new();
+        SetEntityMetadata {
+            entity_id: 5,
+            metadata: metadata.subset(&[16]),
+        }
+        .packet_write(&mut data)
+        .unwrap();
+        assert_eq!(data, vec![5, 16, 1, 0xAC, 0x02, 0xFF]);
+    }
+
+    #[test]
+    fn test_invalid_enum_values() {
+        assert_eq!(SwingArm::packet_read(&[1][..]).unwrap().0, 1);
+        assert!(matches!(
+            SwingArm::packet_read(&[2][..]),
+            Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::SwingArm",
+                value: 2
+            })
+        ));
+
+        assert_eq!(
+            PlayerCommand::packet_read(&[0, 8, 0][..]).unwrap().action,
+            PlayerCommandAction::StartFlyingWithElytra
+        );
+        assert!(matches!(
+            PlayerCommand::packet_read(&[0, 9, 0][..]),
+            Err(ConnectionError::InvalidEnumValue {
+                packet: "packet::play::PlayerCommandAction",
+                value: 9
+            })
+        ));
+    }
+
+    #[test]
+    fn test_title_texts() -> Result<(), ConnectionError> {
+        let text = || TextComponent::new("Hi");
+        // Plain text is sent as a nameless NBT string.
+        let expected = [0x08, 0x00, 0x02, b'H', b'i'];
+
+        let raw = SetTitleText(text()).raw_packet()?;
+        assert_eq!(
+            raw.id,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_SET_TITLE_TEXT
+        );
+        assert_eq!(*raw.data, expected);
+
+        let raw = SetSubtitleText(text()).raw_packet()?;
+        assert_eq!(
+            raw.id,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_SET_SUBTITLE_TEXT
+        );
+        assert_eq!(*raw.data, expected);
+
+        let raw = SetActionBarText(text()).raw_packet()?;
+        assert_eq!(
+            raw.id,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_SET_ACTION_BAR_TEXT
+        );
+        assert_eq!(*raw.data, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_title_animation_times() -> Result<(), ConnectionError> {
+        let raw = SetTitleAnimationTimes {
+            fade_in: 10,
+            stay: 70,
+            fade_out: 20,
+        }
+        .raw_packet()?;
+        assert_eq!(
+            raw.id,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_SET_TITLES_ANIMATION
+        );
+        // Plain big endian ints, not varints.
+        assert_eq!(*raw.data, [0, 0, 0, 10, 0, 0, 0, 70, 0, 0, 0, 20]);
+
+        let raw = SetTitleAnimationTimes {
+            fade_in: 0,
+            stay: 300,
+            fade_out: -1,
+        }
+        .raw_packet()?;
+        assert_eq!(
+            *raw.data,
+            [0, 0, 0, 0, 0, 0, 0x01, 0x2C, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disguised_chat_message() -> Result<(), ConnectionError> {
+        let sender = TextComponent::new("Vulae");
+        let content = TextComponent::new("Hello");
+        let raw = DisguisedChatMessage::new(sender.clone(), content.clone()).raw_packet()?;
+        assert_eq!(
+            raw.id,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_DISGUISED_CHAT
+        );
+
+        let mut expected = Vec::new();
+        expected.write_nbt(&content.to_nbt())?;
+        expected.write_varint(0)?;
+        expected.write_string("chat.type.text")?;
+        expected.write_all(&[2, 0, 2])?;
+        expected.write_all(&[0x0A, 0x00])?;
+        expected.write_string("chat.type.text.narrate")?;
+        expected.write_all(&[2, 0, 2])?;
+        expected.write_all(&[0x0A, 0x00])?;
+        expected.write_nbt(&sender.to_nbt())?;
+        expected.write_bool(false)?;
+        assert_eq!(*raw.data, *expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoreboard() -> Result<(), ConnectionError> {
+        let display_name = TextComponent::new("Kills");
+
+        let mut data = Vec::new();
+        SetObjective {
+            name: "kills".to_owned(),
+            action: SetObjectiveAction::Create {
+                display_name: display_name.clone(),
+                render_type: ObjectiveRenderType::Hearts,
+            },
+        }
+        .packet_write(&mut data)?;
+        let mut expected = vec![5, b'k', b'i', b'l', b'l', b's', 0];
+        expected.write_nbt(&display_name.to_nbt())?;
+        expected.extend([1, 0]);
+        assert_eq!(data, expected);
+
+        let mut data = Vec::new();
+        SetScore {
+            entity_name: "Vulae".to_owned(),
+            objective: "kills".to_owned(),
+            value: 300,
+            display_name: None,
+        }
+        .packet_write(&mut data)?;
+        let mut expected = vec![5, b'V', b'u', b'l', b'a', b'e'];
+        expected.extend([5, b'k', b'i', b'l', b'l', b's']);
+        expected.extend([0xAC, 0x02, 0, 0]);
+        assert_eq!(data, expected);
+
+        let mut data = Vec::new();
+        ResetScore {
+            entity_name: "Vulae".to_owned(),
+            objective: None,
+        }
+        .packet_write(&mut data)?;
+        assert_eq!(data, [5, b'V', b'u', b'l', b'a', b'e', 0]);
+
+        Ok(())
+    }
+}
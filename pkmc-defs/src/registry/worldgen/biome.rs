@@ -20,6 +20,56 @@ pub enum BiomeEffectsGrassColorModifier {
     Swamp,
 }
 
+/// Either just the sound id, or the sound id with a fixed range.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum BiomeSoundEvent {
+    Id(String),
+    Ranged {
+        sound_id: String,
+        range: Option<f32>,
+    },
+}
+
+impl BiomeSoundEvent {
+    pub fn sound_id(&self) -> &str {
+        match self {
+            BiomeSoundEvent::Id(sound_id) => sound_id,
+            BiomeSoundEvent::Ranged { sound_id, .. } => sound_id,
+        }
+    }
+}
+
+/// Sound that plays in dark places, like cave ambience.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BiomeMoodSound {
+    pub sound: BiomeSoundEvent,
+    pub tick_delay: i32,
+    pub block_search_extent: i32,
+    pub offset: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BiomeAdditionsSound {
+    pub sound: BiomeSoundEvent,
+    pub tick_chance: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BiomeParticleOptions {
+    pub r#type: String,
+    /// Extra options some particles have.
+    #[serde(flatten)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Particles that randomly appear in the biome.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BiomeParticle {
+    pub options: BiomeParticleOptions,
+    pub probability: f32,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct BiomeEffects {
     pub fog_color: i32,
@@ -30,14 +80,10 @@ pub struct BiomeEffects {
     pub grass_color: Option<i32>,
     #[serde(default)]
     pub grass_color_modifier: BiomeEffectsGrassColorModifier,
-    // TODO:
-    pub particle: Option<()>,
-    // TODO:
-    pub ambient_sound: Option<()>,
-    // TODO:
-    pub mood_sound: Option<()>,
-    // TODO:
-    pub additions_sound: Option<()>,
+    pub particle: Option<BiomeParticle>,
+    pub ambient_sound: Option<BiomeSoundEvent>,
+    pub mood_sound: Option<BiomeMoodSound>,
+    pub additions_sound: Option<BiomeAdditionsSound>,
     // TODO:
     pub music: Option<()>,
 }
@@ -51,3 +97,53 @@ pub struct Biome {
     pub downfall: f32,
     pub effects: BiomeEffects,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::registry::worldgen::biome::{Biome, BiomeSoundEvent};
+
+    #[test]
+    fn test_biome_effects() {
+        let crimson_forest: Biome = serde_json::from_str(
+            r#"{
+                "downfall": 0.0,
+                "effects": {
+                    "additions_sound": { "sound": "minecraft:ambient.crimson_forest.additions", "tick_chance": 0.0111 },
+                    "ambient_sound": "minecraft:ambient.crimson_forest.loop",
+                    "fog_color": 3343107,
+                    "mood_sound": { "block_search_extent": 8, "offset": 2.0, "sound": "minecraft:ambient.crimson_forest.mood", "tick_delay": 6000 },
+                    "particle": { "options": { "type": "minecraft:crimson_spore" }, "probability": 0.025 },
+                    "sky_color": 7254527,
+                    "water_color": 4159204,
+                    "water_fog_color": 329011
+                },
+                "has_precipitation": false,
+                "temperature": 2.0
+            }"#,
+        )
+        .unwrap();
+        let effects = crimson_forest.effects;
+        let particle = effects.particle.unwrap();
+        assert_eq!(particle.options.r#type, "minecraft:crimson_spore");
+        assert_eq!(particle.probability, 0.025);
+        assert_eq!(
+            effects.ambient_sound,
+            Some(BiomeSoundEvent::Id(
+                "minecraft:ambient.crimson_forest.loop".to_owned()
+            ))
+        );
+        assert_eq!(effects.mood_sound.unwrap().tick_delay, 6000);
+        assert_eq!(
+            effects.additions_sound.unwrap().sound.sound_id(),
+            "minecraft:ambient.crimson_forest.additions"
+        );
+
+        // Everything is optional.
+        let lush_caves: Biome = serde_json::from_str(
+            r#"{ "downfall": 0.5, "effects": { "fog_color": 12638463, "sky_color": 8103167, "water_color": 4159204, "water_fog_color": 329011 }, "has_precipitation": true, "temperature": 0.5 }"#,
+        )
+        .unwrap();
+        assert!(lush_caves.effects.particle.is_none());
+        assert!(lush_caves.effects.mood_sound.is_none());
+    }
+}
@@ -82,11 +82,24 @@ impl Keybind {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Content {
-    Text { text: String },
-    // TODO: Translatable,
-    // TODO: Score,
-    // TODO: Selector,
-    Keybind { keybind: Keybind },
+    Text {
+        text: String,
+    },
+    Translatable {
+        translate: String,
+        with: Vec<TextComponent>,
+    },
+    Score {
+        name: String,
+        objective: String,
+    },
+    Selector {
+        selector: String,
+        separator: Option<Box<TextComponent>>,
+    },
+    Keybind {
+        keybind: Keybind,
+    },
     // TODO: Nbt,
 }
 
@@ -133,6 +146,37 @@ impl Content {
                 //map.insert("type".to_owned(), "text".into());
                 map.insert("text".to_owned(), text.to_owned().into());
             }
+            Content::Translatable { translate, with } => {
+                //map.insert("type".to_owned(), "translatable".into());
+                map.insert("translate".to_owned(), translate.to_owned().into());
+                // NBT can't have empty lists converted from JSON, so just leave it out.
+                if !with.is_empty() {
+                    map.insert(
+                        "with".to_owned(),
+                        with.iter()
+                            .map(|arg| arg.to_json_inner(false))
+                            .collect::<Vec<_>>()
+                            .into(),
+                    );
+                }
+            }
+            Content::Score { name, objective } => {
+                //map.insert("type".to_owned(), "score".into());
+                let mut score = serde_json::Map::new();
+                score.insert("name".to_owned(), name.to_owned().into());
+                score.insert("objective".to_owned(), objective.to_owned().into());
+                map.insert("score".to_owned(), score.into());
+            }
+            Content::Selector {
+                selector,
+                separator,
+            } => {
+                //map.insert("type".to_owned(), "selector".into());
+                map.insert("selector".to_owned(), selector.to_owned().into());
+                if let Some(separator) = separator {
+                    map.insert("separator".to_owned(), separator.to_json_inner(false));
+                }
+            }
             Content::Keybind { keybind } => {
                 //map.insert("type".to_owned(), "keybind".into());
                 map.insert("keybind".to_owned(), keybind.identifier().into());
@@ -166,6 +210,39 @@ impl Color {
         )
     }
 
+    pub const fn r(&self) -> u8 {
+        self.r
+    }
+
+    pub const fn g(&self) -> u8 {
+        self.g
+    }
+
+    pub const fn b(&self) -> u8 {
+        self.b
+    }
+
+    /// 0x00RRGGBB
+    pub const fn to_rgb888(&self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    }
+
+    /// 0xAARRGGBB, used by most of the protocol (e.g. entity effect particles).
+    ///
+    /// NOTE: Dust particles encode their color as RGB only, and the client ignores the alpha
+    /// channel. Use [`Color::OPAQUE`] there anyways, so that the value is still correct if it
+    /// ever gets interpreted as ARGB.
+    pub const fn to_argb8888(&self, alpha: u8) -> u32 {
+        ((alpha as u32) << 24) | self.to_rgb888()
+    }
+
+    /// 0xRRGGBBAA
+    pub const fn to_rgba8888(&self, alpha: u8) -> u32 {
+        (self.to_rgb888() << 8) | (alpha as u32)
+    }
+
+    pub const OPAQUE: u8 = 0xFF;
+
     pub const BLACK: Color = Color::new(0x00, 0x00, 0x00);
     pub const DARK_BLUE: Color = Color::new(0x00, 0x00, 0xAA);
     pub const DARK_GREEN: Color = Color::new(0x00, 0xAA, 0x00);
@@ -228,6 +305,24 @@ impl std::fmt::Display for Color {
     }
 }
 
+/// Converts "&" formatting codes (e.g. "&aGreen &lBold") to legacy "§" formatting codes, used
+/// for places that only support legacy formatting like the server list description.
+///
+/// "&" not followed by a formatting code is left as is.
+pub fn convert_ampersand_formatting_codes(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('&', Some(code)) if "0123456789abcdefklmnorABCDEFKLMNOR".contains(*code) => {
+                converted.push('§');
+            }
+            _ => converted.push(c),
+        }
+    }
+    converted
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Formatting {
     color: Option<Color>,
@@ -284,6 +379,35 @@ impl TextComponent {
         }
     }
 
+    /// Client-localized text, with args filling in the "%s" placeholders.
+    pub fn translate<S: Into<String>>(key: S, args: Vec<TextComponent>) -> Self {
+        Self::new(Content::Translatable {
+            translate: key.into(),
+            with: args,
+        })
+    }
+
+    /// Shows the key the player has bound.
+    pub fn keybind(keybind: Keybind) -> Self {
+        Self::new(Content::Keybind { keybind })
+    }
+
+    /// Scoreboard value of name (player name, UUID, or selector) in objective.
+    pub fn score<N: Into<String>, O: Into<String>>(name: N, objective: O) -> Self {
+        Self::new(Content::Score {
+            name: name.into(),
+            objective: objective.into(),
+        })
+    }
+
+    /// Names of the entities matching the selector.
+    pub fn selector<S: Into<String>>(selector: S, separator: Option<TextComponent>) -> Self {
+        Self::new(Content::Selector {
+            selector: selector.into(),
+            separator: separator.map(Box::new),
+        })
+    }
+
     pub fn empty() -> Self {
         // TODO: If no content type is specified, would it still work and render the children?
         Self {
@@ -416,19 +540,133 @@ impl<T: Into<Content>> From<T> for TextComponent {
     }
 }
 
-//#[cfg(test)]
-//mod test {
-//    use super::{Color, TextComponent};
-//
-//    #[test]
-//    pub fn simple() {
-//        let component = TextComponent::new("Hello, World!")
-//            .with_color(Color::GOLD)
-//            .with_bold(true)
-//            .with_italic(true)
-//            .with_underline(true);
-//        println!("{:#?}", component);
-//        println!("{:#?}", component.to_json());
-//        println!("{:#?}", component.to_nbt());
-//    }
-//}
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use pkmc_util::nbt::NBT;
+
+    use crate::text_component::{
+        convert_ampersand_formatting_codes, Color, Keybind, TextComponent,
+    };
+
+    #[test]
+    fn test_translate_nbt() {
+        let component = TextComponent::translate(
+            "death.attack.player",
+            vec![
+                TextComponent::new("Steve"),
+                TextComponent::new("Alex").with_color(Color::RED),
+            ],
+        );
+        assert_eq!(
+            component.to_nbt(),
+            NBT::Compound(HashMap::from([
+                (
+                    "translate".to_owned(),
+                    NBT::String("death.attack.player".to_owned())
+                ),
+                (
+                    "with".to_owned(),
+                    NBT::List(vec![
+                        NBT::Compound(HashMap::from([(
+                            "text".to_owned(),
+                            NBT::String("Steve".to_owned())
+                        )])),
+                        NBT::Compound(HashMap::from([
+                            ("text".to_owned(), NBT::String("Alex".to_owned())),
+                            ("color".to_owned(), NBT::String("red".to_owned())),
+                        ])),
+                    ])
+                ),
+            ]))
+        );
+
+        assert_eq!(
+            TextComponent::translate("block.minecraft.stone", Vec::new()).to_nbt(),
+            NBT::Compound(HashMap::from([(
+                "translate".to_owned(),
+                NBT::String("block.minecraft.stone".to_owned())
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_keybind_score_selector_nbt() {
+        assert_eq!(
+            TextComponent::keybind(Keybind::Jump).to_nbt(),
+            NBT::Compound(HashMap::from([(
+                "keybind".to_owned(),
+                NBT::String("key.jump".to_owned())
+            )]))
+        );
+
+        assert_eq!(
+            TextComponent::score("@s", "kills").to_nbt(),
+            NBT::Compound(HashMap::from([(
+                "score".to_owned(),
+                NBT::Compound(HashMap::from([
+                    ("name".to_owned(), NBT::String("@s".to_owned())),
+                    ("objective".to_owned(), NBT::String("kills".to_owned())),
+                ]))
+            )]))
+        );
+
+        assert_eq!(
+            TextComponent::selector("@a", None).to_nbt(),
+            NBT::Compound(HashMap::from([(
+                "selector".to_owned(),
+                NBT::String("@a".to_owned())
+            )]))
+        );
+        assert_eq!(
+            TextComponent::selector("@e[type=pig]", Some(TextComponent::new(" | "))).to_nbt(),
+            NBT::Compound(HashMap::from([
+                (
+                    "selector".to_owned(),
+                    NBT::String("@e[type=pig]".to_owned())
+                ),
+                (
+                    "separator".to_owned(),
+                    NBT::Compound(HashMap::from([(
+                        "text".to_owned(),
+                        NBT::String(" | ".to_owned())
+                    )]))
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_convert_ampersand_formatting_codes() {
+        assert_eq!(
+            convert_ampersand_formatting_codes("&aGreen &lBold&r"),
+            "§aGreen §lBold§r"
+        );
+        assert_eq!(
+            convert_ampersand_formatting_codes("Fish & Chips &z&"),
+            "Fish & Chips &z&"
+        );
+    }
+
+    #[test]
+    fn test_color_encoding() {
+        let color = Color::new(0x12, 0x34, 0x56);
+        assert_eq!(color.to_rgb888(), 0x00123456);
+        assert_eq!(color.to_argb8888(0x78), 0x78123456);
+        assert_eq!(color.to_rgba8888(0x78), 0x12345678);
+        assert_eq!(
+            color.to_argb8888(0x78).to_be_bytes(),
+            [0x78, 0x12, 0x34, 0x56]
+        );
+        assert_eq!(
+            color.to_rgba8888(0x78).to_be_bytes(),
+            [0x12, 0x34, 0x56, 0x78]
+        );
+
+        // Dust particle colors should never end up transparent.
+        let dust = Color::RED.to_argb8888(Color::OPAQUE);
+        assert_eq!(dust >> 24, 0xFF);
+        assert_eq!(dust & 0x00FFFFFF, Color::RED.to_rgb888());
+    }
+}
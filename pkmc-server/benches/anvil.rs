@@ -0,0 +1,93 @@
+//! Run with `cargo bench -p pkmc-server`, uses the debug world from the anvil tests as fixture.
+
+use std::{
+    error::Error,
+    hint::black_box,
+    net::{TcpListener, TcpStream},
+};
+
+use pkmc_defs::{biome::Biome, block::Block};
+use pkmc_server::world::{anvil::AnvilWorld, World as _, WorldBlock, SECTION_SIZE};
+use pkmc_util::{packet::Connection, Position, Vec3};
+
+const WORLD_PATH: &str = "./src/world/anvil-test-server/world/";
+
+fn world() -> AnvilWorld {
+    AnvilWorld::new(
+        WORLD_PATH,
+        "minecraft:overworld",
+        -4..=20,
+        [(Biome::default(), 0)].into_iter().collect(),
+    )
+}
+
+fn report(name: &str, iterations: usize, elapsed: std::time::Duration) {
+    println!(
+        "{:<48} {:>10.2}us/iter ({} iterations)",
+        name,
+        elapsed.as_secs_f64() * 1_000_000.0 / iterations as f64,
+        iterations
+    );
+}
+
+/// A layer of a section, each set to a block not yet in the palette (staying within the 8 bit
+/// indirect palette).
+fn bench_set_block_palette_grow() -> Result<(), Box<dyn Error>> {
+    let mut world = world();
+    let positions = Position::iter_cube(SECTION_SIZE as i32, 1, SECTION_SIZE as i32 - 1)
+        .map(|p| p + Position::new(0, 128, 0))
+        .collect::<Vec<_>>();
+    world.get_block(positions[0])?;
+    let start = std::time::Instant::now();
+    positions.iter().enumerate().try_for_each(|(i, position)| {
+        world.set_block(
+            *position,
+            WorldBlock::Block(Block::from_id(i as i32 + 1).unwrap()),
+        )
+    })?;
+    report("set_block (palette grow)", positions.len(), start.elapsed());
+
+    // Same blocks shuffled around, which are already all in the palette.
+    let start = std::time::Instant::now();
+    positions.iter().enumerate().try_for_each(|(i, position)| {
+        world.set_block(
+            *position,
+            WorldBlock::Block(Block::from_id(((i + 1) % positions.len()) as i32 + 1).unwrap()),
+        )
+    })?;
+    report("set_block (in place)", positions.len(), start.elapsed());
+
+    Ok(())
+}
+
+/// Encoding & sending every chunk around the viewer.
+fn bench_chunk_streaming() -> Result<(), Box<dyn Error>> {
+    let mut world = world();
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut client = TcpStream::connect(listener.local_addr()?)?;
+    let server = Connection::new(listener.accept()?.0)?;
+    let drain = std::thread::spawn(move || std::io::copy(&mut client, &mut std::io::sink()));
+
+    let viewer = world.add_viewer(server.sender());
+    viewer.lock().unwrap().position = Vec3::new(8.0, 100.0, 8.0);
+    let radius = 6;
+    viewer.lock().unwrap().loader.update_radius(radius);
+
+    // Each update sends a single chunk.
+    let chunks = ((radius * 2 + 1) * (radius * 2 + 1)) as usize;
+    let start = std::time::Instant::now();
+    (0..chunks).try_for_each(|_| world.update_viewers())?;
+    report("update_viewers (chunk per call)", chunks, start.elapsed());
+
+    drop(viewer);
+    drop(server);
+    drop(world);
+    black_box(drain.join().unwrap()?);
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    bench_set_block_palette_grow()?;
+    bench_chunk_streaming()?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use pkmc_util::packet::{
+    ClientboundPacket, ConnectionError, ConnectionSender, WeakConnectionSender,
+};
+
+/// Sends packets to every connection that was added, dead connections get pruned on broadcast.
+#[derive(Debug, Default)]
+pub struct Broadcaster {
+    senders: Vec<WeakConnectionSender>,
+}
+
+impl Broadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, sender: &ConnectionSender) {
+        self.senders.push(sender.downgrade());
+    }
+
+    /// Number of connections that may still be alive.
+    pub fn len(&self) -> usize {
+        self.senders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    pub fn broadcast(&mut self, packet: &impl ClientboundPacket) -> Result<(), ConnectionError> {
+        let raw = packet.raw_packet()?;
+        let mut senders = Vec::new();
+        self.senders.retain(|weak| match weak.upgrade() {
+            Some(sender) if !sender.is_closed() => {
+                senders.push(sender);
+                true
+            }
+            _ => false,
+        });
+        senders
+            .into_iter()
+            .try_for_each(|sender| sender.send_raw(raw.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pkmc_defs::packet;
+    use pkmc_util::{
+        packet::{ClientboundPacket as _, Connection, ConnectionError},
+        test_util::{connection_pair, poll_until, recieve_all},
+    };
+
+    use crate::broadcaster::Broadcaster;
+
+    fn recieved_keepalives(
+        server: &Connection,
+        client: &mut Connection,
+    ) -> Result<usize, ConnectionError> {
+        Ok(recieve_all(&server.sender(), client)?
+            .iter()
+            .filter(|raw| raw.id == packet::play::KeepAlive::CLIENTBOUND_ID)
+            .count())
+    }
+
+    #[test]
+    fn test_broadcast() -> Result<(), ConnectionError> {
+        let mut broadcaster = Broadcaster::new();
+
+        let (server_a, mut client_a) = connection_pair()?;
+        let (server_b, mut client_b) = connection_pair()?;
+        let (server_c, mut client_c) = connection_pair()?;
+        broadcaster.add(&server_a.sender());
+        broadcaster.add(&server_b.sender());
+        broadcaster.add(&server_c.sender());
+        server_c.close();
+        assert_eq!(broadcaster.len(), 3);
+
+        broadcaster.broadcast(&packet::play::KeepAlive { id: 0 })?;
+        assert_eq!(broadcaster.len(), 2);
+        assert_eq!(recieved_keepalives(&server_a, &mut client_a)?, 1);
+        assert_eq!(recieved_keepalives(&server_b, &mut client_b)?, 1);
+        // Nothing more can be sent to the closed connection, so wait for it to close instead.
+        let mut recieved_c = Vec::new();
+        poll_until(|| {
+            while let Some(raw) = client_c.recieve()? {
+                recieved_c.push(raw.id);
+            }
+            Ok::<_, ConnectionError>(client_c.is_closed().then_some(()))
+        })?;
+        assert!(recieved_c.is_empty());
+
+        drop(server_b);
+        broadcaster.broadcast(&packet::play::KeepAlive { id: 1 })?;
+        assert_eq!(broadcaster.len(), 1);
+        assert_eq!(recieved_keepalives(&server_a, &mut client_a)?, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use pkmc_util::packet::FixedBitSet;
+use thiserror::Error;
+
+/// Number of messages the client keeps track of as "last seen".
+pub const LAST_SEEN_WINDOW: usize = 20;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ChatAcknowledgementError {
+    #[error("Chat acknowledgement advanced window by {0} messages, but expected at most {1}")]
+    InvalidOffset(i32, usize),
+    #[error("Chat acknowledgement acknowledged unknown or ignored message at index {0}")]
+    UnknownMessage(usize),
+}
+
+/// Keeps track of which chat messages the client has seen, so that incoming
+/// message counts & acknowledgements can be validated.
+///
+/// https://minecraft.wiki/w/Java_Edition_protocol/Chat#Client_acknowledgement
+#[derive(Debug)]
+pub struct ChatAcknowledgements {
+    /// Messages that are inside or past the client's window, front is the oldest.
+    /// false if the message was ignored by the client (or there never was a message).
+    tracked: VecDeque<bool>,
+    /// Total amount of messages the client has moved its window by.
+    acknowledged_count: u64,
+}
+
+impl Default for ChatAcknowledgements {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatAcknowledgements {
+    pub fn new() -> Self {
+        Self {
+            tracked: std::iter::repeat_n(false, LAST_SEEN_WINDOW).collect(),
+            acknowledged_count: 0,
+        }
+    }
+
+    /// Call this whenever a signed chat message gets sent to the client.
+    pub fn add_pending(&mut self) {
+        self.tracked.push_back(true);
+    }
+
+    /// Number of messages sent to the client that haven't been moved past yet.
+    pub fn pending(&self) -> usize {
+        self.tracked.len() - LAST_SEEN_WINDOW
+    }
+
+    pub fn acknowledged_count(&self) -> u64 {
+        self.acknowledged_count
+    }
+
+    /// Move the window forward by message_count, as sent in chat & chat acknowledgement packets.
+    pub fn apply_offset(&mut self, message_count: i32) -> Result<(), ChatAcknowledgementError> {
+        let pending = self.pending();
+        if message_count < 0 || message_count as usize > pending {
+            return Err(ChatAcknowledgementError::InvalidOffset(
+                message_count,
+                pending,
+            ));
+        }
+        self.tracked.drain(..message_count as usize);
+        self.acknowledged_count += message_count as u64;
+        Ok(())
+    }
+
+    /// Validate & apply a full update, as sent in chat packets.
+    pub fn apply_update(
+        &mut self,
+        message_count: i32,
+        acknowledged: &FixedBitSet<LAST_SEEN_WINDOW>,
+    ) -> Result<(), ChatAcknowledgementError> {
+        self.apply_offset(message_count)?;
+        (0..LAST_SEEN_WINDOW).try_for_each(|i| {
+            if acknowledged.get(i).unwrap() {
+                if !self.tracked[i] {
+                    return Err(ChatAcknowledgementError::UnknownMessage(i));
+                }
+            } else {
+                self.tracked[i] = false;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pkmc_util::packet::FixedBitSet;
+
+    use crate::chat::{ChatAcknowledgementError, ChatAcknowledgements, LAST_SEEN_WINDOW};
+
+    #[test]
+    fn test_chat_acknowledgements() {
+        let mut acks = ChatAcknowledgements::new();
+        let none = FixedBitSet::<LAST_SEEN_WINDOW>::new();
+
+        // Nothing sent yet, so nothing to acknowledge.
+        assert_eq!(acks.apply_update(0, &none), Ok(()));
+        assert_eq!(
+            acks.apply_update(1, &none),
+            Err(ChatAcknowledgementError::InvalidOffset(1, 0))
+        );
+
+        acks.add_pending();
+        acks.add_pending();
+        acks.add_pending();
+
+        // Client saw 2 messages, the newest 2 in the window.
+        let mut seen = FixedBitSet::<LAST_SEEN_WINDOW>::new();
+        seen.set(LAST_SEEN_WINDOW - 2, true);
+        seen.set(LAST_SEEN_WINDOW - 1, true);
+        assert_eq!(acks.apply_update(2, &seen), Ok(()));
+        assert_eq!(acks.acknowledged_count(), 2);
+        assert_eq!(acks.pending(), 1);
+
+        // Count going backwards.
+        assert_eq!(
+            acks.apply_update(-1, &seen),
+            Err(ChatAcknowledgementError::InvalidOffset(-1, 1))
+        );
+        // Acknowledging a slot that never had a message.
+        let mut unknown = FixedBitSet::<LAST_SEEN_WINDOW>::new();
+        unknown.set(0, true);
+        assert_eq!(
+            acks.apply_update(0, &unknown),
+            Err(ChatAcknowledgementError::UnknownMessage(0))
+        );
+
+        assert_eq!(acks.apply_update(1, &none), Ok(()));
+        assert_eq!(acks.acknowledged_count(), 3);
+        assert_eq!(acks.pending(), 0);
+    }
+}
@@ -1,14 +1,22 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use pkmc_defs::{packet, registry::Registries};
 use pkmc_util::{
     nbt::{NBTError, NBT},
     packet::{
         handler::{PacketHandler, ZlibPacketHandler},
-        Connection, ConnectionError, ServerboundPacket,
+        Connection, ConnectionError, DisconnectCause, KeepAliveError, KeepAliveTracker,
+        ServerboundPacket,
     },
     IdTable, UUID,
 };
+use rand::Rng as _;
 use thiserror::Error;
 
 const PROTOCOL_VERSION: i32 = 769;
@@ -20,6 +28,7 @@ const CONFIGURATION_TIMEOUT: std::time::Duration = std::time::Duration::from_mil
     #[cfg(not(debug_assertions))]
     1000,
 );
+const KEEPALIVE_PING_TIME: std::time::Duration = std::time::Duration::from_millis(10000);
 
 #[derive(Error, Debug)]
 pub enum ClientHandlerError {
@@ -33,6 +42,70 @@ pub enum ClientHandlerError {
     InvalidLoginPlayer,
     #[error("Invalid configuration finalization")]
     InvalidConfigurationFinalization,
+    #[error(transparent)]
+    KeepAliveError(#[from] KeepAliveError),
+}
+
+/// Status descriptions (MOTDs) to pick from for each status request.
+///
+/// Clones share the round robin position.
+#[derive(Debug, Clone)]
+pub struct StatusDescriptions {
+    descriptions: Arc<[String]>,
+    round_robin: Option<Arc<AtomicUsize>>,
+}
+
+impl StatusDescriptions {
+    /// Picks a random description each time.
+    pub fn random(descriptions: impl Into<Arc<[String]>>) -> Self {
+        Self {
+            descriptions: descriptions.into(),
+            round_robin: None,
+        }
+    }
+
+    /// Cycles through the descriptions in order.
+    pub fn round_robin(descriptions: impl Into<Arc<[String]>>) -> Self {
+        Self {
+            descriptions: descriptions.into(),
+            round_robin: Some(Arc::new(AtomicUsize::new(0))),
+        }
+    }
+
+    pub fn pick(&self) -> Option<&str> {
+        if self.descriptions.is_empty() {
+            return None;
+        }
+        let index = match &self.round_robin {
+            Some(next) => next.fetch_add(1, Ordering::Relaxed) % self.descriptions.len(),
+            None => rand::thread_rng().gen_range(0..self.descriptions.len()),
+        };
+        Some(&self.descriptions[index])
+    }
+}
+
+/// Which part of connecting a [`ClientHandler`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionPhase {
+    Handshake,
+    Status,
+    Login,
+    Configuration,
+    Play,
+    Closed,
+}
+
+impl std::fmt::Display for ConnectionPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionPhase::Handshake => write!(f, "handshake"),
+            ConnectionPhase::Status => write!(f, "status"),
+            ConnectionPhase::Login => write!(f, "login"),
+            ConnectionPhase::Configuration => write!(f, "configuration"),
+            ConnectionPhase::Play => write!(f, "play"),
+            ConnectionPhase::Closed => write!(f, "closed"),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -49,6 +122,7 @@ enum ClientHandlerState {
         last_packet_time: std::time::Instant,
         can_finalize: bool,
         sent_finalize_packet: bool,
+        keep_alive: KeepAliveTracker,
     },
     Play {
         player: (UUID, String),
@@ -60,6 +134,14 @@ pub struct ClientHandlerPlay {
     pub connection: Connection,
     pub player_id: UUID,
     pub player_name: String,
+    /// Packs that both the server & client know.
+    pub known_packs: Vec<packet::configuration::KnownPack>,
+    /// Brand the client sent, "vanilla" for unmodded clients.
+    pub client_brand: Option<String>,
+    /// Vanilla defaults if the client didn't send any.
+    pub client_information: packet::configuration::ClientInformation,
+    /// Whether the client was transferred here, it may have cookies from the previous server.
+    pub transferred: bool,
 }
 
 #[derive(Debug)]
@@ -69,10 +151,18 @@ pub struct ClientHandler {
 
     brand: Option<String>,
     compression: Option<(usize, u32)>,
-    status_description: Option<String>,
+    status_descriptions: Option<StatusDescriptions>,
     status_favicon: Option<String>,
     registries: Option<Registries>,
     tags: Option<HashMap<String, IdTable<String>>>,
+    known_packs: Vec<packet::configuration::KnownPack>,
+    enabled_features: packet::configuration::UpdateEnabledFeatures,
+    client_brand: Option<String>,
+    client_information: Option<packet::configuration::ClientInformation>,
+    transferred: bool,
+
+    closed_during: Option<ConnectionPhase>,
+    last_error: Option<String>,
 }
 
 impl ClientHandler {
@@ -82,10 +172,17 @@ impl ClientHandler {
             state: ClientHandlerState::Handshake,
             brand: None,
             compression: None,
-            status_description: None,
+            status_descriptions: None,
             status_favicon: None,
             registries: None,
             tags: None,
+            known_packs: Vec::new(),
+            enabled_features: packet::configuration::UpdateEnabledFeatures::default(),
+            client_brand: None,
+            client_information: None,
+            transferred: false,
+            closed_during: None,
+            last_error: None,
         }
     }
 
@@ -104,7 +201,13 @@ impl ClientHandler {
     }
 
     pub fn with_status_description(mut self, description: impl Into<String>) -> Self {
-        self.status_description = Some(description.into());
+        self.status_descriptions = Some(StatusDescriptions::random([description.into()]));
+        self
+    }
+
+    /// Picks one of the descriptions for each status request.
+    pub fn with_status_descriptions(mut self, descriptions: StatusDescriptions) -> Self {
+        self.status_descriptions = Some(descriptions);
         self
     }
 
@@ -130,11 +233,56 @@ impl ClientHandler {
         self
     }
 
+    /// Feature flags to enable, defaults to only "minecraft:vanilla".
+    pub fn with_enabled_features<S: Into<String>>(
+        mut self,
+        features: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.enabled_features.features = features.into_iter().map(|f| f.into()).collect();
+        self
+    }
+
     pub fn into_connection(self) -> Connection {
         self.connection
     }
 
+    pub fn phase(&self) -> ConnectionPhase {
+        match self.state {
+            ClientHandlerState::Closed => ConnectionPhase::Closed,
+            ClientHandlerState::Handshake => ConnectionPhase::Handshake,
+            ClientHandlerState::Status => ConnectionPhase::Status,
+            ClientHandlerState::Login { .. } => ConnectionPhase::Login,
+            ClientHandlerState::Configuration { .. } => ConnectionPhase::Configuration,
+            ClientHandlerState::Play { .. } => ConnectionPhase::Play,
+        }
+    }
+
+    /// The phase the connection was in when it closed.
+    pub fn closed_during(&self) -> Option<ConnectionPhase> {
+        self.closed_during
+    }
+
+    /// The last error from [`ClientHandler::update`].
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Errors close the connection, as the client can't continue connecting after one.
     pub fn update(&mut self) -> Result<(), ClientHandlerError> {
+        let phase = self.phase();
+        let result = self.update_state();
+        if let Err(err) = &result {
+            self.last_error = Some(err.to_string());
+            self.connection.close_with(DisconnectCause::ProtocolError);
+            self.state = ClientHandlerState::Closed;
+        }
+        if phase != ConnectionPhase::Closed && self.phase() == ConnectionPhase::Closed {
+            self.closed_during = Some(phase);
+        }
+        result
+    }
+
+    fn update_state(&mut self) -> Result<(), ClientHandlerError> {
         if self.connection.is_closed() {
             self.state = ClientHandlerState::Closed;
             return Ok(());
@@ -162,7 +310,11 @@ impl ClientHandler {
                     packet::handshake::IntentionNextState::Login => {
                         self.state = ClientHandlerState::Login { player: None };
                     }
-                    packet::handshake::IntentionNextState::Transfer => unimplemented!(),
+                    // Transferred clients log in like any other.
+                    packet::handshake::IntentionNextState::Transfer => {
+                        self.transferred = true;
+                        self.state = ClientHandlerState::Login { player: None };
+                    }
                 }
             }
             ClientHandlerState::Status => {
@@ -179,9 +331,12 @@ impl ClientHandler {
                                 },
                                 players: None,
                                 description: self
-                                    .status_description
-                                    .take()
-                                    .map(|s| packet::status::ResponseDescription { text: s }),
+                                    .status_descriptions
+                                    .as_ref()
+                                    .and_then(|d| d.pick())
+                                    .map(|s| packet::status::ResponseDescription {
+                                        text: s.to_owned(),
+                                    }),
                                 favicon: self.status_favicon.take(),
                                 enforces_secure_chat: false,
                             })?;
@@ -227,6 +382,7 @@ impl ClientHandler {
                                 last_packet_time: std::time::Instant::now(),
                                 can_finalize: false,
                                 sent_finalize_packet: false,
+                                keep_alive: KeepAliveTracker::new(KEEPALIVE_PING_TIME),
                             };
                         }
                     }
@@ -238,7 +394,17 @@ impl ClientHandler {
                 ref mut last_packet_time,
                 ref mut can_finalize,
                 ref mut sent_finalize_packet,
+                ref mut keep_alive,
             } => {
+                let now = std::time::Instant::now();
+                if keep_alive.is_timed_out(now) {
+                    return Err(KeepAliveError.into());
+                }
+                if let Some(id) = keep_alive.should_send(now) {
+                    self.connection
+                        .send_urgent(&packet::configuration::KeepAlive { id })?;
+                }
+
                 if !*sent_finalize_packet {
                     if !*sent_initial_configuration_packets {
                         *sent_initial_configuration_packets = true;
@@ -248,13 +414,13 @@ impl ClientHandler {
                                 .send(&packet::configuration::CustomPayload::Brand(brand))?;
                         }
 
+                        self.connection.send(&self.enabled_features)?;
+
+                        // Registry data is only sent once the client responds with the packs
+                        // that it knows, so that entries without data refer to a known pack.
                         self.connection
                             .send(&packet::configuration::SelectKnownPacks {
-                                packs: vec![packet::configuration::KnownPack {
-                                    namespace: "minecraft:core".to_owned(),
-                                    id: "".to_owned(),
-                                    version: "1.21".to_owned(),
-                                }],
+                                packs: vec![packet::configuration::KnownPack::vanilla()],
                             })?;
                     }
 
@@ -264,14 +430,26 @@ impl ClientHandler {
                     {
                         match packet {
                             packet::configuration::ConfigurationPacket::CustomPayload(
-                                _custom_payload,
+                                packet::configuration::CustomPayload::Brand(brand),
+                            ) => self.client_brand = Some(brand),
+                            packet::configuration::ConfigurationPacket::CustomPayload(
+                                packet::configuration::CustomPayload::Unknown { .. },
                             ) => {}
                             packet::configuration::ConfigurationPacket::ClientInformation(
-                                _client_information,
-                            ) => {}
+                                client_information,
+                            ) => self.client_information = Some(client_information),
                             packet::configuration::ConfigurationPacket::SelectKnownPacks(
-                                _select_known_packs,
+                                select_known_packs,
                             ) => {
+                                // Client may only respond with packs that we sent.
+                                self.known_packs = select_known_packs
+                                    .packs
+                                    .into_iter()
+                                    .filter(|pack| {
+                                        *pack == packet::configuration::KnownPack::vanilla()
+                                    })
+                                    .collect();
+
                                 // NOTE: This is very very bad and ugly, somehow uglier than myself.
                                 // Once registries are actually properly implemented, pretty much all
                                 // of this will not be needed.
@@ -325,6 +503,9 @@ impl ClientHandler {
                             ) => {
                                 return Err(ClientHandlerError::InvalidConfigurationFinalization);
                             }
+                            packet::configuration::ConfigurationPacket::KeepAlive(keepalive) => {
+                                keep_alive.acknowledge(keepalive.id)?;
+                            }
                         }
 
                         *last_packet_time = std::time::Instant::now();
@@ -342,16 +523,18 @@ impl ClientHandler {
                     self.connection
                         .recieve_into::<packet::configuration::ConfigurationPacket>()?
                 {
-                    if !matches!(
-                        packet,
-                        packet::configuration::ConfigurationPacket::FinishConfiguration(..)
-                    ) {
-                        return Err(ClientHandlerError::InvalidConfigurationFinalization);
+                    match packet {
+                        packet::configuration::ConfigurationPacket::FinishConfiguration(..) => {
+                            self.state = ClientHandlerState::Play {
+                                player: player.clone(),
+                            };
+                        }
+                        // May still be responding to a keep alive sent before finalizing.
+                        packet::configuration::ConfigurationPacket::KeepAlive(keepalive) => {
+                            keep_alive.acknowledge(keepalive.id)?;
+                        }
+                        _ => return Err(ClientHandlerError::InvalidConfigurationFinalization),
                     }
-
-                    self.state = ClientHandlerState::Play {
-                        player: player.clone(),
-                    };
                 }
             }
             ClientHandlerState::Play { .. } => {}
@@ -374,8 +557,345 @@ impl ClientHandler {
                 connection: self.connection,
                 player_id,
                 player_name,
+                known_packs: self.known_packs,
+                client_brand: self.client_brand,
+                client_information: self.client_information.unwrap_or_default(),
+                transferred: self.transferred,
             }),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::Write as _;
+
+    use pkmc_defs::{generated::generated, packet, registry::Registries};
+    use pkmc_util::{
+        packet::{Connection, ConnectionError, RawPacket, ReadExtPacket as _, WriteExtPacket as _},
+        test_util::{connection_pair, poll_until},
+        UUID,
+    };
+
+    use crate::client_handler::{
+        ClientHandler, ClientHandlerError, ClientHandlerPlay, ConnectionPhase, StatusDescriptions,
+    };
+
+    fn client_send(
+        client: &Connection,
+        id: i32,
+        write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>,
+    ) -> Result<(), ConnectionError> {
+        let mut data = Vec::new();
+        write(&mut data)?;
+        client
+            .sender()
+            .send_raw(RawPacket::new(id, data.into_boxed_slice()))
+    }
+
+    /// Update the handler until done returns true, and return the packet ids that the client
+    /// recieved.
+    fn pump_until(
+        handler: &mut ClientHandler,
+        client: &mut Connection,
+        done: impl Fn(&ClientHandler, &[i32]) -> bool,
+    ) -> Result<Vec<i32>, ClientHandlerError> {
+        let mut ids = Vec::new();
+        poll_until(|| {
+            handler.update()?;
+            while let Some(raw) = client.recieve()? {
+                ids.push(raw.id);
+            }
+            Ok::<_, ClientHandlerError>(done(handler, &ids).then_some(()))
+        })?;
+        Ok(ids)
+    }
+
+    /// Update the handler until the client recieves a packet with the id.
+    fn pump_until_id(
+        handler: &mut ClientHandler,
+        client: &mut Connection,
+        id: i32,
+    ) -> Result<Vec<i32>, ClientHandlerError> {
+        pump_until(handler, client, |_, ids| ids.contains(&id))
+    }
+
+    /// Handshake & login, returning the ids recieved when entering configuration.
+    fn enter_configuration(
+        handler: &mut ClientHandler,
+        client: &mut Connection,
+    ) -> Result<Vec<i32>, ClientHandlerError> {
+        client_send(
+            client,
+            generated::packet::handshake::SERVERBOUND_MINECRAFT_INTENTION,
+            |w| {
+                w.write_varint(769)?;
+                w.write_string("localhost")?;
+                w.write_all(&25565u16.to_be_bytes())?;
+                w.write_varint(2)
+            },
+        )?;
+        pump_until(handler, client, |handler, _| {
+            handler.phase() != ConnectionPhase::Handshake
+        })?;
+        client_send(
+            client,
+            generated::packet::login::SERVERBOUND_MINECRAFT_HELLO,
+            |w| {
+                w.write_string("Test")?;
+                w.write_uuid(&UUID::new_v7())
+            },
+        )?;
+        pump_until_id(
+            handler,
+            client,
+            generated::packet::login::CLIENTBOUND_MINECRAFT_LOGIN_FINISHED,
+        )?;
+        client_send(
+            client,
+            generated::packet::login::SERVERBOUND_MINECRAFT_LOGIN_ACKNOWLEDGED,
+            |_| Ok(()),
+        )?;
+        pump_until_id(
+            handler,
+            client,
+            generated::packet::configuration::CLIENTBOUND_MINECRAFT_SELECT_KNOWN_PACKS,
+        )
+    }
+
+    #[test]
+    fn test_known_packs_before_registries() -> Result<(), ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let registries: Registries = serde_json::from_str(
+            r#"{ "minecraft:painting_variant": { "minecraft:earth": { "asset_id": "minecraft:earth", "height": 2, "width": 2 } } }"#,
+        )
+        .unwrap();
+        let mut handler = ClientHandler::new(server).with_registies(registries);
+
+        let ids = enter_configuration(&mut handler, &mut client)?;
+        assert!(ids.contains(
+            &generated::packet::configuration::CLIENTBOUND_MINECRAFT_UPDATE_ENABLED_FEATURES
+        ));
+        assert!(ids
+            .contains(&generated::packet::configuration::CLIENTBOUND_MINECRAFT_SELECT_KNOWN_PACKS));
+        assert!(
+            !ids.contains(&generated::packet::configuration::CLIENTBOUND_MINECRAFT_REGISTRY_DATA)
+        );
+
+        let vanilla = packet::configuration::KnownPack::vanilla();
+        client_send(
+            &client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_SELECT_KNOWN_PACKS,
+            |w| {
+                w.write_varint(1)?;
+                w.write_string(&vanilla.namespace)?;
+                w.write_string(&vanilla.id)?;
+                w.write_string(&vanilla.version)
+            },
+        )?;
+        pump_until_id(
+            &mut handler,
+            &mut client,
+            generated::packet::configuration::CLIENTBOUND_MINECRAFT_REGISTRY_DATA,
+        )?;
+        assert_eq!(handler.known_packs, vec![vanilla]);
+
+        Ok(())
+    }
+
+    /// Respond to known packs & finish configuration, returning the play state.
+    fn finish_configuration(
+        mut handler: ClientHandler,
+        client: &mut Connection,
+    ) -> Result<ClientHandlerPlay, ClientHandlerError> {
+        client_send(
+            client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_SELECT_KNOWN_PACKS,
+            |w| w.write_varint(0),
+        )?;
+        pump_until_id(
+            &mut handler,
+            client,
+            generated::packet::configuration::CLIENTBOUND_MINECRAFT_FINISH_CONFIGURATION,
+        )?;
+        client_send(
+            client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_FINISH_CONFIGURATION,
+            |_| Ok(()),
+        )?;
+        pump_until(&mut handler, client, |handler, _| handler.is_finalized())?;
+
+        assert!(handler.is_finalized());
+        Ok(handler.finalized_play_state().unwrap())
+    }
+
+    #[test]
+    fn test_client_brand() -> Result<(), ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server);
+        enter_configuration(&mut handler, &mut client)?;
+
+        client_send(
+            &client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD,
+            |w| {
+                w.write_string("example:unknown")?;
+                w.write_all(&[1, 2, 3])
+            },
+        )?;
+        client_send(
+            &client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD,
+            |w| {
+                w.write_string("minecraft:brand")?;
+                w.write_string("fabric")
+            },
+        )?;
+        let play = finish_configuration(handler, &mut client)?;
+        assert_eq!(play.client_brand.as_deref(), Some("fabric"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_information() -> Result<(), ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server);
+        enter_configuration(&mut handler, &mut client)?;
+        let play = finish_configuration(handler, &mut client)?;
+        assert_eq!(
+            play.client_information,
+            packet::configuration::ClientInformation::default()
+        );
+        assert_eq!(play.client_information.displayed_skin_parts, 0x7F);
+        assert_eq!(play.client_information.view_distance, 10);
+
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server);
+        enter_configuration(&mut handler, &mut client)?;
+        client_send(
+            &client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_CLIENT_INFORMATION,
+            |w| {
+                w.write_string("nl_nl")?;
+                w.write_all(&4i8.to_be_bytes())?;
+                w.write_varint(0)?;
+                w.write_bool(true)?;
+                w.write_all(&[0x01])?;
+                w.write_varint(1)?;
+                w.write_bool(false)?;
+                w.write_bool(true)
+            },
+        )?;
+        let play = finish_configuration(handler, &mut client)?;
+        assert_eq!(play.client_information.locale, "nl_nl");
+        assert_eq!(play.client_information.view_distance, 4);
+        assert_eq!(play.client_information.displayed_skin_parts, 0x01);
+
+        Ok(())
+    }
+
+    /// Description of a status request on a new connection.
+    fn status_description(descriptions: &StatusDescriptions) -> Result<String, ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server).with_status_descriptions(descriptions.clone());
+        client_send(
+            &client,
+            generated::packet::handshake::SERVERBOUND_MINECRAFT_INTENTION,
+            |w| {
+                w.write_varint(769)?;
+                w.write_string("localhost")?;
+                w.write_all(&25565u16.to_be_bytes())?;
+                w.write_varint(1)
+            },
+        )?;
+        client_send(
+            &client,
+            generated::packet::status::SERVERBOUND_MINECRAFT_STATUS_REQUEST,
+            |_| Ok(()),
+        )?;
+        let raw = poll_until(|| {
+            handler.update()?;
+            Ok::<_, ClientHandlerError>(client.recieve()?)
+        })?;
+        let mut reader = std::io::Cursor::new(&raw.data);
+        let response: serde_json::Value =
+            serde_json::from_str(&reader.read_string().map_err(ConnectionError::from)?).unwrap();
+        Ok(response["description"]["text"].as_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn test_status_descriptions() -> Result<(), ClientHandlerError> {
+        let motds = ["First".to_owned(), "Second".to_owned()];
+
+        let round_robin = StatusDescriptions::round_robin(motds.clone());
+        let picked = (0..4)
+            .map(|_| status_description(&round_robin))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(picked, vec!["First", "Second", "First", "Second"]);
+
+        let random = StatusDescriptions::random(motds.clone());
+        for _ in 0..16 {
+            assert!(motds.contains(&status_description(&random)?));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_intention() -> Result<(), ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server);
+
+        client_send(
+            &client,
+            generated::packet::handshake::SERVERBOUND_MINECRAFT_INTENTION,
+            |w| {
+                w.write_varint(769)?;
+                w.write_string("localhost")?;
+                w.write_all(&25565u16.to_be_bytes())?;
+                w.write_varint(3)
+            },
+        )?;
+        pump_until(&mut handler, &mut client, |handler, _| {
+            handler.phase() != ConnectionPhase::Handshake
+        })?;
+        assert_eq!(handler.phase(), ConnectionPhase::Login);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_phase() -> Result<(), ClientHandlerError> {
+        let (server, mut client) = connection_pair()?;
+        let mut handler = ClientHandler::new(server);
+        assert_eq!(handler.phase(), ConnectionPhase::Handshake);
+
+        enter_configuration(&mut handler, &mut client)?;
+        assert_eq!(handler.phase(), ConnectionPhase::Configuration);
+        assert_eq!(handler.closed_during(), None);
+
+        // Finishing before the client knows the registries is invalid.
+        client_send(
+            &client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_FINISH_CONFIGURATION,
+            |_| Ok(()),
+        )?;
+        assert!(matches!(
+            pump_until(&mut handler, &mut client, |_, _| false),
+            Err(ClientHandlerError::InvalidConfigurationFinalization)
+        ));
+        assert_eq!(handler.phase(), ConnectionPhase::Closed);
+        assert_eq!(
+            handler.closed_during(),
+            Some(ConnectionPhase::Configuration)
+        );
+        assert_eq!(
+            handler.last_error(),
+            Some("Invalid configuration finalization")
+        );
+
+        Ok(())
+    }
+}
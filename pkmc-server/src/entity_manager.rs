@@ -1,19 +1,27 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet},
     fmt::Debug,
     sync::{atomic::AtomicI32, Arc, Mutex, Weak},
 };
 
-use pkmc_defs::packet;
+use pkmc_defs::packet::{
+    self,
+    play::{EntityMetadata, EntityMetadataBundle},
+};
 use pkmc_util::{
     packet::{ConnectionError, ConnectionSender},
-    UUID,
+    Vec3, UUID,
 };
 
 pub trait Entity: Debug {
     fn r#type(&self) -> i32;
 }
 
+/// Handle to a spawned entity.
+///
+/// The [`EntityManager`] only weakly references entities, so once the last handle (& any
+/// [`EntityHandler`] references) is dropped the entity is despawned, and viewers are sent
+/// RemoveEntities on the next [`EntityManager::update_viewers`].
 #[derive(Debug)]
 pub struct EntityBase<T: Entity + ?Sized> {
     pub inner: Box<T>,
@@ -29,8 +37,7 @@ pub fn new_entity_id() -> i32 {
 }
 
 impl<T: Entity> EntityBase<T> {
-    fn new(inner: T, uuid: UUID) -> Self {
-        let id = new_entity_id();
+    fn new(inner: T, id: i32, uuid: UUID) -> Self {
         Self {
             handler: Arc::new(Mutex::new(EntityHandler::new(id, uuid, inner.r#type()))),
             inner: Box::new(inner),
@@ -50,6 +57,9 @@ impl<T: Entity> EntityBase<T> {
     pub fn handler(&self) -> &Arc<Mutex<EntityHandler>> {
         &self.handler
     }
+
+    /// Same as dropping the handle, just more explicit.
+    pub fn despawn(self) {}
 }
 
 #[derive(Debug)]
@@ -57,18 +67,96 @@ pub struct EntityHandler {
     id: i32,
     uuid: UUID,
     r#type: i32,
+    position: Vec3<f64>,
+    yaw: f32,
+    pitch: f32,
+    /// Position at the last [`EntityManager::update_viewers`].
+    last_position: Vec3<f64>,
+    /// If the position or rotation changed since the last update.
+    moved: bool,
+    /// Starts out as the client side defaults for the entity type.
+    metadata: EntityMetadataBundle,
+    /// Metadata indices changed since the last update.
+    dirty_metadata: BTreeSet<u8>,
 }
 
 impl EntityHandler {
     fn new(id: i32, uuid: UUID, r#type: i32) -> Self {
-        Self { id, uuid, r#type }
+        Self {
+            id,
+            uuid,
+            r#type,
+            position: Vec3::new(0.0, 100.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            last_position: Vec3::new(0.0, 100.0, 0.0),
+            moved: false,
+            metadata: EntityMetadataBundle::default_for_type(r#type),
+            dirty_metadata: BTreeSet::new(),
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn uuid(&self) -> &UUID {
+        &self.uuid
+    }
+
+    pub fn r#type(&self) -> i32 {
+        self.r#type
+    }
+
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec3<f64>) {
+        self.position = position;
+        self.moved = true;
+    }
+
+    /// Change in position since the last update, in blocks per tick.
+    pub fn velocity(&self) -> Vec3<f64> {
+        self.position - self.last_position
+    }
+
+    pub fn rotation(&self) -> (f32, f32) {
+        (self.yaw, self.pitch)
+    }
+
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.moved = true;
+    }
+
+    pub fn metadata(&self) -> &EntityMetadataBundle {
+        &self.metadata
+    }
+
+    /// Sets a metadata value, only changed values are sent to viewers on the next update.
+    pub fn set_metadata(&mut self, index: u8, value: EntityMetadata) {
+        if self.metadata.0.get(&index) != Some(&value) {
+            self.metadata.0.insert(index, value);
+            self.dirty_metadata.insert(index);
+        }
     }
 }
 
+/// Angles are sent as 1/256ths of a full turn.
+fn angle_to_u8(angle: f32) -> u8 {
+    ((angle / 360.0 * 256.0) as i32).rem_euclid(256) as u8
+}
+
 #[derive(Debug)]
 pub struct EntityViewer {
     connection: ConnectionSender,
     viewing: HashSet<i32>,
+    pub position: Vec3<f64>,
+    /// Entities further away than this are not shown to the viewer.
+    pub radius: f64,
 }
 
 impl EntityViewer {
@@ -76,14 +164,30 @@ impl EntityViewer {
         Self {
             connection,
             viewing: HashSet::new(),
+            position: Vec3::new(0.0, 100.0, 0.0),
+            radius: 64.0,
         }
     }
+
+    pub fn is_viewing(&self, id: i32) -> bool {
+        self.viewing.contains(&id)
+    }
+}
+
+fn in_range(center: Vec3<f64>, radius: f64, position: Vec3<f64>) -> bool {
+    let delta = position - center;
+    delta.x * delta.x + delta.y * delta.y + delta.z * delta.z <= radius * radius
 }
 
 #[derive(Debug, Default)]
 pub struct EntityManager {
-    entities: Vec<Weak<Mutex<EntityHandler>>>,
+    /// Entities by id, the id is kept so it can be freed once the entity is dropped.
+    entities: Vec<(i32, Weak<Mutex<EntityHandler>>)>,
     viewers: Vec<Weak<Mutex<EntityViewer>>>,
+    /// Ids of dropped entities that viewers may still be viewing.
+    despawned_ids: HashSet<i32>,
+    /// Ids that no viewer references anymore, reused before allocating new ones.
+    free_ids: BTreeSet<i32>,
 }
 
 impl EntityManager {
@@ -102,23 +206,54 @@ impl EntityManager {
             .flat_map(|v| v.upgrade())
             .collect::<Vec<_>>();
 
-        self.entities.retain(|e| e.strong_count() > 0);
+        self.entities.retain(|(id, e)| {
+            if e.strong_count() > 0 {
+                return true;
+            }
+            self.despawned_ids.insert(*id);
+            false
+        });
 
         let entities = self
             .entities
             .iter()
-            .flat_map(|e| e.upgrade())
+            .flat_map(|(_, e)| e.upgrade())
             .collect::<Vec<_>>();
 
         viewers
             .iter()
             .map(|v| v.lock().unwrap())
             .try_for_each(|mut viewer| {
+                let mut visible = HashSet::new();
+                let (position, radius) = (viewer.position, viewer.radius);
                 entities
                     .iter()
                     .map(|e| e.lock().unwrap())
+                    .filter(|entity| in_range(position, radius, entity.position))
                     .try_for_each(|entity| {
+                        visible.insert(entity.id);
                         if viewer.viewing.contains(&entity.id) {
+                            if entity.moved {
+                                let velocity = entity.velocity();
+                                viewer.connection.send(&packet::play::EntityPositionSync {
+                                    id: entity.id,
+                                    x: entity.position.x,
+                                    y: entity.position.y,
+                                    z: entity.position.z,
+                                    velocity_x: velocity.x,
+                                    velocity_y: velocity.y,
+                                    velocity_z: velocity.z,
+                                    yaw: entity.yaw,
+                                    pitch: entity.pitch,
+                                    on_ground: false,
+                                })?;
+                            }
+                            if !entity.dirty_metadata.is_empty() {
+                                viewer.connection.send(&packet::play::SetEntityMetadata {
+                                    entity_id: entity.id,
+                                    metadata: entity.metadata.subset(&entity.dirty_metadata),
+                                })?;
+                            }
                             return Ok(());
                         }
                         viewer.viewing.insert(entity.id);
@@ -126,27 +261,390 @@ impl EntityManager {
                             id: entity.id,
                             uuid: entity.uuid,
                             r#type: entity.r#type,
-                            x: 0.0,
-                            y: 100.0,
-                            z: 0.0,
-                            pitch: 0,
-                            yaw: 0,
-                            head_yaw: 0,
+                            x: entity.position.x,
+                            y: entity.position.y,
+                            z: entity.position.z,
+                            pitch: angle_to_u8(entity.pitch),
+                            yaw: angle_to_u8(entity.yaw),
+                            head_yaw: angle_to_u8(entity.yaw),
                             data: 0,
                             velocity_x: 0,
                             velocity_y: 0,
                             velocity_z: 0,
                         })?;
+                        // The client already has the defaults.
+                        let metadata = entity
+                            .metadata
+                            .changed_from(&EntityMetadataBundle::default_for_type(entity.r#type));
+                        if !metadata.is_empty() {
+                            viewer.connection.send(&packet::play::SetEntityMetadata {
+                                entity_id: entity.id,
+                                metadata,
+                            })?;
+                        }
                         Ok::<_, ConnectionError>(())
-                    })
+                    })?;
+
+                // Entities that went out of range or were removed, all in a single packet.
+                let removed = viewer
+                    .viewing
+                    .difference(&visible)
+                    .copied()
+                    .collect::<HashSet<_>>();
+                if !removed.is_empty() {
+                    viewer.viewing.retain(|id| !removed.contains(id));
+                    viewer
+                        .connection
+                        .send(&packet::play::RemoveEntities(removed))?;
+                }
+
+                Ok::<_, ConnectionError>(())
             })?;
 
+        entities
+            .iter()
+            .map(|e| e.lock().unwrap())
+            .for_each(|mut entity| {
+                entity.last_position = entity.position;
+                entity.moved = false;
+                entity.dirty_metadata.clear();
+            });
+
+        // Ids can be reused once every viewer was sent the entity's removal.
+        let viewers = viewers
+            .iter()
+            .map(|v| v.lock().unwrap())
+            .collect::<Vec<_>>();
+        let freed = self
+            .despawned_ids
+            .iter()
+            .filter(|id| viewers.iter().all(|viewer| !viewer.is_viewing(**id)))
+            .copied()
+            .collect::<Vec<_>>();
+        freed.into_iter().for_each(|id| {
+            self.despawned_ids.remove(&id);
+            self.free_ids.insert(id);
+        });
+
         Ok(())
     }
 
+    pub fn get_entity(&self, id: i32) -> Option<Arc<Mutex<EntityHandler>>> {
+        self.entities
+            .iter()
+            .flat_map(|(_, e)| e.upgrade())
+            .find(|e| e.lock().unwrap().id == id)
+    }
+
+    pub fn entity_ids(&self) -> Vec<i32> {
+        self.entities
+            .iter()
+            .filter(|(_, e)| e.strong_count() > 0)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Removes the entity even while handles to it are still held, returns if there was an
+    /// entity with the id.
+    ///
+    /// The id isn't reused, as the handles still have it.
+    pub fn remove_entity(&mut self, id: i32) -> bool {
+        let mut removed = false;
+        self.entities.retain(|(entity_id, e)| {
+            if *entity_id == id && e.strong_count() > 0 {
+                removed = true;
+                return false;
+            }
+            true
+        });
+        removed
+    }
+
+    /// Reuses the lowest freed id, or allocates a new one with [`new_entity_id`].
+    fn allocate_id(&mut self) -> i32 {
+        self.free_ids.pop_first().unwrap_or_else(new_entity_id)
+    }
+
     pub fn add_entity<T: Entity>(&mut self, entity: T, uuid: UUID) -> EntityBase<T> {
-        let entity = EntityBase::new(entity, uuid);
-        self.entities.push(Arc::downgrade(&entity.handler));
+        let entity = EntityBase::new(entity, self.allocate_id(), uuid);
+        self.entities
+            .push((entity.id, Arc::downgrade(&entity.handler)));
         entity
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pkmc_defs::{
+        entity::entity_type_id,
+        packet::{
+            self,
+            play::{EntityMetadata, EntityMetadataBundle},
+        },
+    };
+    use pkmc_util::{
+        packet::{ClientboundPacket as _, Connection, ConnectionError, ReadExtPacket as _},
+        test_util::{connection_pair, recieve_all},
+        ReadExt as _, Vec3, UUID,
+    };
+
+    use crate::entity_manager::{Entity, EntityManager};
+
+    #[derive(Debug)]
+    struct TestEntity;
+
+    impl Entity for TestEntity {
+        fn r#type(&self) -> i32 {
+            0
+        }
+    }
+
+    /// All RemoveEntities packets that the client recieved.
+    fn recieved_removes(
+        server: &Connection,
+        client: &mut Connection,
+    ) -> Result<Vec<HashSet<i32>>, ConnectionError> {
+        let mut removes = Vec::new();
+        for raw in recieve_all(&server.sender(), client)? {
+            if raw.id != packet::play::RemoveEntities::CLIENTBOUND_ID {
+                continue;
+            }
+            let mut reader = std::io::Cursor::new(&raw.data);
+            removes.push(
+                (0..reader.read_varint()?)
+                    .map(|_| reader.read_varint())
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+        Ok(removes)
+    }
+
+    #[test]
+    fn test_remove_entities_batched() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let viewer = manager.add_viewer(server.sender());
+        viewer.lock().unwrap().position = Vec3::new(0.0, 0.0, 0.0);
+        viewer.lock().unwrap().radius = 16.0;
+
+        let entities = (0..3)
+            .map(|i| {
+                let entity = manager.add_entity(TestEntity, UUID::new_v7());
+                entity
+                    .handler()
+                    .lock()
+                    .unwrap()
+                    .set_position(Vec3::new(i as f64, 0.0, 0.0));
+                entity
+            })
+            .collect::<Vec<_>>();
+        let far = manager.add_entity(TestEntity, UUID::new_v7());
+        far.handler()
+            .lock()
+            .unwrap()
+            .set_position(Vec3::new(100.0, 0.0, 0.0));
+
+        manager.update_viewers()?;
+        assert!(entities
+            .iter()
+            .all(|e| viewer.lock().unwrap().is_viewing(e.id())));
+        assert!(!viewer.lock().unwrap().is_viewing(far.id()));
+        assert_eq!(recieved_removes(&server, &mut client)?.len(), 0);
+
+        viewer.lock().unwrap().position = Vec3::new(100.0, 0.0, 0.0);
+        manager.update_viewers()?;
+        assert_eq!(
+            recieved_removes(&server, &mut client)?,
+            vec![entities.iter().map(|e| e.id()).collect::<HashSet<_>>()]
+        );
+        assert!(viewer.lock().unwrap().is_viewing(far.id()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_despawn_on_drop() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let viewer = manager.add_viewer(server.sender());
+
+        let kept = manager.add_entity(TestEntity, UUID::new_v7());
+        let dropped = manager.add_entity(TestEntity, UUID::new_v7());
+        let despawned = manager.add_entity(TestEntity, UUID::new_v7());
+        let (dropped_id, despawned_id) = (dropped.id(), despawned.id());
+        manager.update_viewers()?;
+        assert!(viewer.lock().unwrap().is_viewing(dropped_id));
+        assert_eq!(recieved_removes(&server, &mut client)?.len(), 0);
+
+        drop(dropped);
+        despawned.despawn();
+        assert!(manager.get_entity(dropped_id).is_none());
+        manager.update_viewers()?;
+        assert_eq!(
+            recieved_removes(&server, &mut client)?,
+            vec![HashSet::from([dropped_id, despawned_id])]
+        );
+        assert!(viewer.lock().unwrap().is_viewing(kept.id()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_position_sync_velocity() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let _viewer = manager.add_viewer(server.sender());
+        let entity = manager.add_entity(TestEntity, UUID::new_v7());
+        let still = manager.add_entity(TestEntity, UUID::new_v7());
+        manager.update_viewers()?;
+
+        (1..=3).try_for_each(|tick| {
+            entity.handler().lock().unwrap().set_position(Vec3::new(
+                0.5 * tick as f64,
+                100.0,
+                -0.25 * tick as f64,
+            ));
+            manager.update_viewers()
+        })?;
+
+        let mut syncs = Vec::new();
+        for raw in recieve_all(&server.sender(), &mut client)? {
+            if raw.id != packet::play::EntityPositionSync::CLIENTBOUND_ID {
+                continue;
+            }
+            let mut reader = std::io::Cursor::new(&raw.data);
+            let id = reader.read_varint()?;
+            let [x, _y, _z, vx, vy, vz] =
+                std::array::from_fn(|_| f64::from_be_bytes(reader.read_const().unwrap()));
+            syncs.push((id, x, vx, vy, vz));
+        }
+        assert_eq!(
+            syncs,
+            vec![
+                (entity.id(), 0.5, 0.5, 0.0, -0.25),
+                (entity.id(), 1.0, 0.5, 0.0, -0.25),
+                (entity.id(), 1.5, 0.5, 0.0, -0.25),
+            ]
+        );
+        assert!(syncs.iter().all(|s| s.0 != still.id()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_only_changed() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let _viewer = manager.add_viewer(server.sender());
+        let entity = manager.add_entity(TestEntity, UUID::new_v7());
+        let mut handler = entity.handler().lock().unwrap();
+        handler.set_metadata(0, EntityMetadata::Byte(0x20));
+        handler.set_metadata(16, EntityMetadata::VarInt(1));
+        drop(handler);
+        manager.update_viewers()?;
+
+        // Setting an unchanged value doesn't resend it.
+        let mut handler = entity.handler().lock().unwrap();
+        handler.set_metadata(0, EntityMetadata::Byte(0x20));
+        handler.set_metadata(16, EntityMetadata::VarInt(2));
+        drop(handler);
+        manager.update_viewers()?;
+        manager.update_viewers()?;
+
+        let mut metadata = Vec::new();
+        for raw in recieve_all(&server.sender(), &mut client)? {
+            if raw.id == packet::play::SetEntityMetadata::CLIENTBOUND_ID {
+                metadata.push(raw.data);
+            }
+        }
+        assert_eq!(
+            metadata,
+            vec![
+                vec![entity.id() as u8, 0, 0, 0x20, 16, 1, 1, 0xFF].into_boxed_slice(),
+                vec![entity.id() as u8, 16, 1, 2, 0xFF].into_boxed_slice(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct TypedEntity(i32);
+
+    impl Entity for TypedEntity {
+        fn r#type(&self) -> i32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_default_metadata() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let _viewer = manager.add_viewer(server.sender());
+
+        let item = manager.add_entity(TypedEntity(entity_type_id("item").unwrap()), UUID::new_v7());
+        let player = manager.add_entity(
+            TypedEntity(entity_type_id("player").unwrap()),
+            UUID::new_v7(),
+        );
+        let item_metadata = item.handler().lock().unwrap().metadata().clone();
+        assert_eq!(item_metadata, EntityMetadataBundle::entity_default());
+        assert!(!item_metadata.0.contains_key(&9));
+        assert_eq!(
+            *player.handler().lock().unwrap().metadata(),
+            EntityMetadataBundle::player_default()
+        );
+        assert_eq!(
+            player.handler().lock().unwrap().metadata().0.get(&9),
+            Some(&EntityMetadata::Float(1.0))
+        );
+
+        // Defaults aren't sent, and setting a default value isn't a change.
+        item.handler()
+            .lock()
+            .unwrap()
+            .set_metadata(0, EntityMetadata::Byte(0));
+        manager.update_viewers()?;
+        let mut spawned = 0;
+        for raw in recieve_all(&server.sender(), &mut client)? {
+            assert_ne!(raw.id, packet::play::SetEntityMetadata::CLIENTBOUND_ID);
+            if raw.id == packet::play::AddEntity::CLIENTBOUND_ID {
+                spawned += 1;
+            }
+        }
+        assert_eq!(spawned, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reuse_ids() -> Result<(), ConnectionError> {
+        let (server, mut client) = connection_pair()?;
+        let mut manager = EntityManager::default();
+        let viewer = manager.add_viewer(server.sender());
+        viewer.lock().unwrap().position = Vec3::new(0.0, 100.0, 0.0);
+
+        let entity = manager.add_entity(TestEntity, UUID::new_v7());
+        let id = entity.id();
+        manager.update_viewers()?;
+        assert!(viewer.lock().unwrap().is_viewing(id));
+
+        // Not reused until the removal was sent.
+        entity.despawn();
+        let other = manager.add_entity(TestEntity, UUID::new_v7());
+        assert_ne!(other.id(), id);
+
+        manager.update_viewers()?;
+        assert_eq!(
+            recieved_removes(&server, &mut client)?,
+            vec![HashSet::from([id])]
+        );
+        let respawned = manager.add_entity(TestEntity, UUID::new_v7());
+        assert_eq!(respawned.id(), id);
+
+        Ok(())
+    }
+}
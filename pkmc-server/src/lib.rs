@@ -1,5 +1,10 @@
+pub mod broadcaster;
+pub mod chat;
 pub mod client_handler;
 pub mod entity_manager;
+pub mod player_registry;
+pub mod tick_timings;
 pub mod world;
+pub mod world_border;
 
 pub use client_handler::*;
@@ -0,0 +1,66 @@
+use pkmc_util::UUID;
+
+/// Players that are currently online, in the order they joined.
+#[derive(Debug, Default)]
+pub struct PlayerRegistry {
+    players: Vec<(UUID, String)>,
+}
+
+impl PlayerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names are unique, so this replaces any entry with the same uuid or name (offline mode
+    /// gives reconnecting players a new uuid).
+    pub fn add(&mut self, uuid: UUID, name: &str) {
+        self.players
+            .retain(|(player_uuid, player_name)| *player_uuid != uuid && player_name != name);
+        self.players.push((uuid, name.to_owned()));
+    }
+
+    pub fn remove(&mut self, uuid: &UUID) {
+        self.players.retain(|(player_uuid, _)| player_uuid != uuid);
+    }
+
+    pub fn contains(&self, uuid: &UUID) -> bool {
+        self.players
+            .iter()
+            .any(|(player_uuid, _)| player_uuid == uuid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.players.iter().map(|(_, name)| name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pkmc_util::UUID;
+
+    use crate::player_registry::PlayerRegistry;
+
+    #[test]
+    fn test_reconnect_replaces_entry() {
+        let mut registry = PlayerRegistry::new();
+        let (first, reconnected, other) = (UUID::new_v7(), UUID::new_v7(), UUID::new_v7());
+        registry.add(first, "Steve");
+        registry.add(other, "Alex");
+        registry.add(reconnected, "Steve");
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["Alex", "Steve"]);
+        assert!(!registry.contains(&first));
+
+        // Previous session being cleaned up afterwards doesn't remove the new one.
+        registry.remove(&first);
+        assert!(registry.contains(&reconnected));
+        assert_eq!(registry.len(), 2);
+    }
+}
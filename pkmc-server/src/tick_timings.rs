@@ -0,0 +1,72 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// Ticks that take longer than this mean the server can't keep up.
+pub const TICK_DURATION: Duration = Duration::from_millis(50);
+pub const TICKS_PER_SECOND: f64 = 20.0;
+
+/// Number of ticks that are averaged.
+const TICK_TIMINGS_WINDOW: usize = 100;
+
+/// Rolling measurement of how long each tick takes.
+#[derive(Debug, Default)]
+pub struct TickTimings {
+    durations: VecDeque<Duration>,
+}
+
+impl TickTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns if the tick took longer than [`TICK_DURATION`].
+    pub fn record(&mut self, duration: Duration) -> bool {
+        if self.durations.len() >= TICK_TIMINGS_WINDOW {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+        duration > TICK_DURATION
+    }
+
+    /// Average milliseconds per tick.
+    pub fn mspt(&self) -> f64 {
+        if self.durations.is_empty() {
+            return 0.0;
+        }
+        self.durations
+            .iter()
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .sum::<f64>()
+            / self.durations.len() as f64
+    }
+
+    /// Ticks per second that the server manages to keep up with, at most [`TICKS_PER_SECOND`].
+    pub fn tps(&self) -> f64 {
+        let mspt = self.mspt();
+        if mspt <= TICK_DURATION.as_secs_f64() * 1000.0 {
+            return TICKS_PER_SECOND;
+        }
+        1000.0 / mspt
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::tick_timings::{TickTimings, TICKS_PER_SECOND};
+
+    #[test]
+    fn test_slow_ticks() {
+        let mut timings = TickTimings::new();
+        assert_eq!(timings.tps(), TICKS_PER_SECOND);
+
+        (0..100).for_each(|_| assert!(!timings.record(Duration::from_millis(10))));
+        assert_eq!(timings.tps(), TICKS_PER_SECOND);
+        assert!((timings.mspt() - 10.0).abs() < 0.001);
+
+        // Slow ticks push out the fast ones.
+        (0..100).for_each(|_| assert!(timings.record(Duration::from_millis(100))));
+        assert!((timings.tps() - 10.0).abs() < 0.001);
+        assert!((timings.mspt() - 100.0).abs() < 0.001);
+    }
+}
@@ -1,11 +1,11 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     fs::File,
     hash::Hash,
     io::{Seek, Write},
     path::PathBuf,
-    sync::{Arc, Mutex, Weak},
+    sync::{Arc, Mutex},
 };
 
 use itertools::Itertools;
@@ -20,9 +20,9 @@ use pkmc_defs::{
 };
 use pkmc_util::{
     nbt::{from_nbt, NBTError, NBT},
-    nbt_compound,
+    nbt_compound, normalize_identifier,
     packet::{to_paletted_data, to_paletted_data_singular, ConnectionError, ConnectionSender},
-    IdTable, PackedArray, Position, ReadExt, Transmutable, Vec3,
+    IdTable, PackedArray, Position, ReadExt, Transmutable,
 };
 use serde::Deserialize;
 use thiserror::Error;
@@ -30,17 +30,27 @@ use thiserror::Error;
 use crate::world::{chunk_loader::ChunkPosition, SECTION_SIZE};
 
 use super::{
-    chunk_loader::ChunkLoader, World, WorldBlock, WorldViewer, CHUNK_SIZE, SECTION_BIOMES,
-    SECTION_BLOCKS,
+    chunk_loader::ChunkLoader, section_block_index_pos, section_pos_biome_index,
+    section_pos_block_index, Dimension, World, WorldBlock, WorldViewer, WorldViewers, CHUNK_SIZE,
+    SECTION_BIOMES, SECTION_BIOMES_SIZE, SECTION_BLOCKS,
 };
 
 pub const REGION_SIZE: usize = 32;
 pub const CHUNKS_PER_REGION: usize = REGION_SIZE * REGION_SIZE;
+/// Regions & chunks kept loaded before the least recently used ones are unloaded, see
+/// [`AnvilWorld::with_capacity`].
+pub const DEFAULT_REGION_CAPACITY: usize = 64;
+pub const DEFAULT_CHUNK_CAPACITY: usize = 8192;
+const REGION_SECTOR_SIZE: usize = 4096;
+/// Chunk locations & timestamps, each take up a sector.
+const REGION_HEADER_SIZE: usize = REGION_SECTOR_SIZE * 2;
 
 // Each time the world updates & sends new data to client, we either send sections or chunks.
 // NOTE: When sending sections, the client calculates lighting instead of server.
 pub const UPDATE_SECTION_CHUNK_SWITCH_NUM_SECTIONS: usize = 4;
 pub const UPDATE_SECTION_CHUNK_SWITCH_NUM_BLOCKS: usize = 1024;
+// Same for block entities, changed block entities are sent individually unless there's too many.
+pub const UPDATE_BLOCK_ENTITY_CHUNK_SWITCH_NUM: usize = 64;
 
 #[derive(Error, Debug)]
 pub enum AnvilError {
@@ -54,6 +64,39 @@ pub enum AnvilError {
     RegionUnsupportedCompression(String),
     #[error(transparent)]
     NBTError(#[from] NBTError),
+    #[error("Chunk is too large to save ({0} bytes)")]
+    ChunkTooLarge(usize),
+    #[error("Chunk uses the pre-1.18 section layout (DataVersion {0}), upgrade the world first")]
+    LegacyChunkLayout(i32),
+}
+
+/// Values that can be stored in [`PalettedData`] & sent to the client as ids.
+trait Paletteable {
+    type Context: ?Sized;
+    /// Bits per entry of the direct palette.
+    const DIRECT: u32;
+
+    fn to_id(&self, context: &Self::Context) -> i32;
+}
+
+impl Paletteable for Block {
+    type Context = ();
+    const DIRECT: u32 = PALETTED_DATA_BLOCKS_DIRECT;
+
+    fn to_id(&self, _context: &()) -> i32 {
+        self.id_with_default_fallback()
+            .unwrap_or_else(|| Block::air().id().unwrap())
+    }
+}
+
+impl Paletteable for Biome {
+    type Context = IdTable<Biome>;
+    const DIRECT: u32 = PALETTED_DATA_BIOMES_DIRECT;
+
+    fn to_id(&self, mapper: &IdTable<Biome>) -> i32 {
+        self.id(mapper)
+            .unwrap_or_else(|| Biome::default().id(mapper).unwrap())
+    }
 }
 
 fn default_paletted_data<T: Default>() -> Box<[T]> {
@@ -68,6 +111,18 @@ struct PalettedData<T: Debug + Default, const N: usize, const I_S: u8, const I_E
     data: Box<[i64]>,
 }
 
+impl<T: Debug + Default, const N: usize, const I_S: u8, const I_E: u8> Default
+    for PalettedData<T, N, I_S, I_E>
+{
+    /// Filled with the default value.
+    fn default() -> Self {
+        Self {
+            palette: default_paletted_data(),
+            data: Box::new([]),
+        }
+    }
+}
+
 impl<T: Debug + Default, const N: usize, const I_S: u8, const I_E: u8>
     PalettedData<T, N, I_S, I_E>
 {
@@ -75,7 +130,8 @@ impl<T: Debug + Default, const N: usize, const I_S: u8, const I_E: u8>
         match palette_count {
             0 => panic!(),
             1 => 0,
-            palette_count => PackedArray::bits_per_entry(palette_count as u64 - 1).clamp(I_S, I_E),
+            // Stored data doesn't have a direct palette, so it isn't capped to the indirect range.
+            palette_count => PackedArray::bits_per_entry(palette_count as u64 - 1).max(I_S),
         }
     }
 
@@ -94,6 +150,46 @@ impl<T: Debug + Default, const N: usize, const I_S: u8, const I_E: u8>
         debug_assert!(palette_index < self.palette.len());
         &self.palette[palette_index]
     }
+
+    fn to_nbt(&self, palette_to_nbt: impl Fn(&T) -> NBT) -> NBT {
+        let mut compound = HashMap::new();
+        compound.insert(
+            "palette".to_owned(),
+            NBT::List(self.palette.iter().map(palette_to_nbt).collect()),
+        );
+        if Self::bpe(self.palette.len()) != 0 {
+            compound.insert("data".to_owned(), NBT::LongArray(self.data.clone()));
+        }
+        NBT::Compound(compound)
+    }
+}
+
+impl<T: Debug + Default + Paletteable, const N: usize, const I_S: u8, const I_E: u8>
+    PalettedData<T, N, I_S, I_E>
+{
+    /// Id of every entry.
+    fn to_ids(&self, context: &T::Context) -> Box<[i32]> {
+        let palette_ids = self
+            .palette
+            .iter()
+            .map(|v| v.to_id(context))
+            .collect::<Box<[_]>>();
+        (0..N).map(|i| palette_ids[self.palette_index(i)]).collect()
+    }
+
+    fn write_ids(ids: &[i32], mut writer: impl Write) -> Result<(), AnvilError> {
+        // FIXME: Why does to_paletted_data_precomputed work only most of the time?
+        // Some sections are just outright missing.
+        // My best guess is that there's 2 palette values that are the exact same, so when
+        // minecraft decodes the data it doesn't know what to do.
+        // For now we'll just do it the slow way.
+        writer.write_all(&to_paletted_data(ids, I_S as u32..=I_E as u32, T::DIRECT)?)?;
+        Ok(())
+    }
+
+    fn write(&self, writer: impl Write, context: &T::Context) -> Result<(), AnvilError> {
+        Self::write_ids(&self.to_ids(context), writer)
+    }
 }
 
 impl<T: Debug + Default + Eq + Clone + Hash, const N: usize, const I_S: u8, const I_E: u8>
@@ -136,12 +232,13 @@ impl<T: Debug + Default + Eq + Clone + Hash, const N: usize, const I_S: u8, cons
                 self.data = Vec::new().into_boxed_slice();
             }
             bpe => {
-                let mut data = PackedArray::new(bpe, N);
-
-                let remaining = data
-                    .consume(parsed.iter().map(|v| *palette.get(v).unwrap() as u64))
-                    .count();
-                debug_assert_eq!(remaining, 0);
+                let data = PackedArray::from_indices(
+                    &parsed
+                        .iter()
+                        .map(|v| *palette.get(v).unwrap() as u64)
+                        .collect::<Vec<_>>(),
+                    bpe,
+                );
 
                 self.palette = palette
                     .into_iter()
@@ -167,56 +264,23 @@ type ChunkSectionBlockStates = PalettedData<
 >;
 
 impl ChunkSectionBlockStates {
-    fn get_block_index(x: u8, y: u8, z: u8) -> usize {
-        debug_assert!((x as usize) < SECTION_SIZE);
-        debug_assert!((y as usize) < SECTION_SIZE);
-        debug_assert!((z as usize) < SECTION_SIZE);
-        (y as usize) * SECTION_SIZE * SECTION_SIZE + (z as usize) * SECTION_SIZE + (x as usize)
-    }
-
     fn get_block(&self, x: u8, y: u8, z: u8) -> &Block {
-        self.get(Self::get_block_index(x, y, z))
+        self.get(section_pos_block_index(x, y, z))
     }
 
     fn set_block(&mut self, x: u8, y: u8, z: u8, block: Block) -> bool {
-        self.set(Self::get_block_index(x, y, z), block)
+        self.set(section_pos_block_index(x, y, z), block)
     }
 
-    fn write(&self, mut writer: impl Write) -> Result<(), AnvilError> {
-        let block_ids = self
-            .palette
+    /// Paletted data prefixed with the number of non-air blocks.
+    fn write_with_block_count(&self, mut writer: impl Write) -> Result<(), AnvilError> {
+        let block_ids = self.to_ids(&());
+        let block_count = block_ids
             .iter()
-            .map(|b| {
-                b.id_with_default_fallback()
-                    .unwrap_or_else(|| Block::air().id().unwrap())
-            })
-            .collect::<Box<[_]>>();
-
-        let block_count = (0..SECTION_BLOCKS)
-            .filter(|i| !generated::block::is_air(block_ids[self.palette_index(*i)]))
+            .filter(|id| !generated::block::is_air(**id))
             .count();
-
         writer.write_all(&(block_count as u16).to_be_bytes())?;
-        // FIXME: Why does this work only most of the time?
-        // Some sections are just outright missing.
-        // My best guess is that there's 2 palette values that are the exact same, so when
-        // minecraft decodes the data it doesn't know what to do.
-        //writer.write_all(&to_paletted_data_precomputed(
-        //    &block_ids,
-        //    &self.data,
-        //    PALETTED_DATA_BLOCKS_INDIRECT,
-        //    PALETTED_DATA_BLOCKS_DIRECT,
-        //)?)?;
-        // For now we'll just do it the slow way.
-        writer.write_all(&to_paletted_data(
-            &(0..SECTION_BLOCKS)
-                .map(|i| block_ids[self.palette_index(i)])
-                .collect::<Box<[_]>>(),
-            PALETTED_DATA_BLOCKS_INDIRECT,
-            PALETTED_DATA_BLOCKS_DIRECT,
-        )?)?;
-
-        Ok(())
+        Self::write_ids(&block_ids, writer)
     }
 }
 
@@ -230,25 +294,8 @@ type ChunkSectionBiomes = PalettedData<
 >;
 
 impl ChunkSectionBiomes {
-    fn write(&self, mut writer: impl Write, mapper: &IdTable<Biome>) -> Result<(), AnvilError> {
-        let biome_ids = self
-            .palette
-            .iter()
-            .map(|b| {
-                b.id(mapper)
-                    .unwrap_or_else(|| Biome::default().id(mapper).unwrap())
-            })
-            .collect::<Box<[_]>>();
-
-        writer.write_all(&to_paletted_data(
-            &(0..SECTION_BIOMES)
-                .map(|i| biome_ids[self.palette_index(i)])
-                .collect::<Box<[_]>>(),
-            PALETTED_DATA_BIOMES_INDIRECT,
-            PALETTED_DATA_BIOMES_DIRECT,
-        )?)?;
-
-        Ok(())
+    fn set_biome(&mut self, x: u8, y: u8, z: u8, biome: Biome) -> bool {
+        self.set(section_pos_biome_index(x, y, z), biome)
     }
 }
 
@@ -260,6 +307,26 @@ struct ChunkSection {
     biomes: Option<ChunkSectionBiomes>,
 }
 
+impl ChunkSection {
+    fn to_nbt(&self) -> NBT {
+        let mut compound = HashMap::new();
+        compound.insert("Y".to_owned(), NBT::Byte(self.y));
+        if let Some(block_states) = &self.block_states {
+            compound.insert(
+                "block_states".to_owned(),
+                block_states.to_nbt(Block::to_nbt),
+            );
+        }
+        if let Some(biomes) = &self.biomes {
+            compound.insert(
+                "biomes".to_owned(),
+                biomes.to_nbt(|biome| NBT::String(biome.name().to_owned())),
+            );
+        }
+        NBT::Compound(compound)
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 struct AnvilBlockEntity {
     id: String,
@@ -273,20 +340,184 @@ struct AnvilBlockEntity {
     data: HashMap<String, serde_json::Value>,
 }
 
+/// Older chunks have their data inside of a `Level` compound, this moves it to the root.
+///
+/// NOTE: Only the wrapper is handled, the section layout inside of it is expected to be the
+/// 1.18+ one, see [`parse_chunk`].
+fn unwrap_level(nbt: NBT) -> NBT {
+    match nbt {
+        NBT::Compound(mut compound) => {
+            if let Some(NBT::Compound(level)) = compound.remove("Level") {
+                level.into_iter().for_each(|(key, value)| {
+                    compound.entry(key).or_insert(value);
+                });
+            }
+            NBT::Compound(compound)
+        }
+        nbt => nbt,
+    }
+}
+
+/// Parses chunk NBT from a region file, chunks with the pre-1.18 section layout (`Sections` with
+/// `Palette` & `BlockStates`) are rejected instead of being parsed without any sections.
+fn parse_chunk(nbt: NBT) -> Result<AnvilChunk, AnvilError> {
+    let nbt = unwrap_level(nbt);
+    if let NBT::Compound(compound) = &nbt {
+        if compound.contains_key("Sections") && !compound.contains_key("sections") {
+            let data_version = match compound.get("DataVersion") {
+                Some(NBT::Int(data_version)) => *data_version,
+                _ => 0,
+            };
+            return Err(AnvilError::LegacyChunkLayout(data_version));
+        }
+    }
+    Ok(from_nbt::<AnvilChunk>(nbt)?)
+}
+
+/// Block light of every section in a chunk, sections are bottom to top & missing sections are
+/// treated as air. Light spreads across sections but not into neighbouring chunks.
+///
+/// Sections without any light are None, same as [`packet::play::LevelLightData`].
+fn compute_block_light(sections: &[Option<&ChunkSectionBlockStates>]) -> Vec<Option<[u8; 2048]>> {
+    let height = sections.len() * SECTION_SIZE;
+    let index = |x: usize, y: usize, z: usize| {
+        (y / SECTION_SIZE) * SECTION_BLOCKS
+            + section_pos_block_index(x as u8, (y % SECTION_SIZE) as u8, z as u8)
+    };
+
+    let mut opacity = vec![0u8; sections.len() * SECTION_BLOCKS];
+    let mut light = vec![0u8; sections.len() * SECTION_BLOCKS];
+    let mut queue = std::collections::VecDeque::new();
+    sections
+        .iter()
+        .enumerate()
+        .filter_map(|(i, block_states)| Some((i, (*block_states)?)))
+        .for_each(|(i, block_states)| {
+            let palette = block_states
+                .palette
+                .iter()
+                .map(|block| (block.light_opacity(), block.light_emission()))
+                .collect::<Vec<_>>();
+            if palette.iter().all(|(o, e)| *o == 0 && *e == 0) {
+                return;
+            }
+            (0..SECTION_BLOCKS).for_each(|section_index| {
+                let (o, e) = palette[block_states.palette_index(section_index)];
+                let (x, y, z) = section_block_index_pos(section_index);
+                let position = (x as usize, i * SECTION_SIZE + y as usize, z as usize);
+                opacity[i * SECTION_BLOCKS + section_index] = o;
+                if e > 0 {
+                    light[i * SECTION_BLOCKS + section_index] = e;
+                    queue.push_back(position);
+                }
+            });
+        });
+
+    while let Some((x, y, z)) = queue.pop_front() {
+        let level = light[index(x, y, z)];
+        [
+            (x.wrapping_sub(1), y, z),
+            (x + 1, y, z),
+            (x, y.wrapping_sub(1), z),
+            (x, y + 1, z),
+            (x, y, z.wrapping_sub(1)),
+            (x, y, z + 1),
+        ]
+        .into_iter()
+        .filter(|(x, y, z)| *x < CHUNK_SIZE && *y < height && *z < CHUNK_SIZE)
+        .for_each(|(x, y, z)| {
+            let i = index(x, y, z);
+            let new_level = level.saturating_sub(opacity[i].max(1));
+            if new_level > light[i] {
+                light[i] = new_level;
+                queue.push_back((x, y, z));
+            }
+        });
+    }
+
+    light
+        .chunks_exact(SECTION_BLOCKS)
+        .map(|section| {
+            if section.iter().all(|level| *level == 0) {
+                return None;
+            }
+            let mut nibbles = [0u8; 2048];
+            section.iter().enumerate().for_each(|(i, level)| {
+                nibbles[i / 2] |= level << ((i % 2) * 4);
+            });
+            Some(nibbles)
+        })
+        .collect()
+}
+
+/// `MOTION_BLOCKING` & `WORLD_SURFACE` heightmaps of a chunk, sections are bottom to top &
+/// missing sections are treated as air.
+///
+/// Heights are the number of blocks from the bottom of the world to the top of the highest
+/// matching block, 0 for columns without any.
+fn compute_heightmaps(sections: &[Option<&ChunkSectionBlockStates>]) -> NBT {
+    let mut motion_blocking = [0u64; CHUNK_SIZE * CHUNK_SIZE];
+    let mut world_surface = [0u64; CHUNK_SIZE * CHUNK_SIZE];
+    sections
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(i, block_states)| Some((i, (*block_states)?)))
+        .for_each(|(i, block_states)| {
+            let palette = block_states
+                .palette
+                .iter()
+                .map(|block| (!block.is_air(), block.is_motion_blocking()))
+                .collect::<Vec<_>>();
+            if palette.iter().all(|(solid, _)| !solid) {
+                return;
+            }
+            (0..CHUNK_SIZE * CHUNK_SIZE).for_each(|column| {
+                let (x, z) = ((column % CHUNK_SIZE) as u8, (column / CHUNK_SIZE) as u8);
+                (0..SECTION_SIZE as u8).rev().for_each(|y| {
+                    let (solid, blocks_motion) =
+                        palette[block_states.palette_index(section_pos_block_index(x, y, z))];
+                    let height = (i * SECTION_SIZE + y as usize + 1) as u64;
+                    if solid && world_surface[column] == 0 {
+                        world_surface[column] = height;
+                    }
+                    if blocks_motion && motion_blocking[column] == 0 {
+                        motion_blocking[column] = height;
+                    }
+                });
+            });
+        });
+
+    let bits_per_entry = PackedArray::bits_per_entry((sections.len() * SECTION_SIZE) as u64);
+    let pack = |heights: &[u64]| {
+        NBT::LongArray(
+            PackedArray::from_indices(heights, bits_per_entry)
+                .into_inner()
+                .into_iter()
+                .map(|v| v as i64)
+                .collect(),
+        )
+    };
+    nbt_compound!(
+        "MOTION_BLOCKING" => pack(&motion_blocking),
+        "WORLD_SURFACE" => pack(&world_surface),
+    )
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AnvilChunk {
-    //#[serde(rename = "DataVersion")]
-    //data_version: i32,
-    //#[serde(rename = "xPos")]
-    //x_pos: i32,
-    //#[serde(rename = "zPos")]
-    //z_pos: i32,
-    //#[serde(rename = "yPos")]
-    //y_pos: i32,
-    //#[serde(rename = "Status")]
-    //status: String,
-    //#[serde(rename = "LastUpdate")]
-    //last_update: i64,
+    #[serde(rename = "DataVersion", default)]
+    data_version: i32,
+    #[serde(rename = "xPos", default)]
+    x_pos: i32,
+    #[serde(rename = "zPos", default)]
+    z_pos: i32,
+    #[serde(rename = "yPos", default)]
+    y_pos: i32,
+    #[serde(rename = "Status", default)]
+    status: String,
+    #[serde(rename = "LastUpdate", default)]
+    last_update: i64,
     sections: Vec<ChunkSection>,
     block_entities: Vec<AnvilBlockEntity>,
     #[serde(skip, default)]
@@ -296,7 +527,7 @@ pub struct AnvilChunk {
 impl AnvilChunk {
     fn initialize(&mut self) {
         // Sometimes sections are unsorted.
-        self.sections.sort_by(|a, b| a.y.cmp(&b.y));
+        self.sections.sort_by_key(|section| section.y);
 
         self.parsed_block_entities = self
             .block_entities
@@ -316,14 +547,51 @@ impl AnvilChunk {
             .collect();
     }
 
+    /// If the chunk has finished generating, proto-chunks may be missing blocks & sections.
+    ///
+    /// Chunks without a status are assumed to be finished.
+    fn is_full(&self) -> bool {
+        self.status.is_empty()
+            || normalize_identifier(&self.status, "minecraft") == "minecraft:full"
+    }
+
     fn get_section(&self, section_y: i8) -> Option<&ChunkSection> {
         self.sections.iter().find(|section| section.y == section_y)
     }
 
-    fn get_section_mut(&mut self, section_y: i8) -> Option<&mut ChunkSection> {
-        self.sections
-            .iter_mut()
-            .find(|section| section.y == section_y)
+    /// Block states of each section in the range, None for sections that don't exist.
+    fn section_block_states(
+        &self,
+        section_y_range: std::ops::RangeInclusive<i8>,
+    ) -> Vec<Option<&ChunkSectionBlockStates>> {
+        section_y_range
+            .map(|section_y| {
+                self.get_section(section_y)
+                    .and_then(|section| section.block_states.as_ref())
+            })
+            .collect()
+    }
+
+    /// Section at the height, sections that don't exist yet are created empty.
+    fn get_section_or_insert(&mut self, section_y: i8) -> &mut ChunkSection {
+        let index = match self
+            .sections
+            .binary_search_by_key(&section_y, |section| section.y)
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.sections.insert(
+                    index,
+                    ChunkSection {
+                        y: section_y,
+                        block_states: None,
+                        biomes: None,
+                    },
+                );
+                index
+            }
+        };
+        &mut self.sections[index]
     }
 
     fn get_tile_block(&self, block_x: u8, block_y: i16, block_z: u8) -> Option<Block> {
@@ -368,13 +636,10 @@ impl AnvilChunk {
             }
         };
 
-        let Some(section) = self.get_section_mut(block_y.div_euclid(SECTION_SIZE as i16) as i8)
-        else {
-            return false;
-        };
-        let Some(block_states) = section.block_states.as_mut() else {
-            return false;
-        };
+        let section = self.get_section_or_insert(block_y.div_euclid(SECTION_SIZE as i16) as i8);
+        let block_states = section
+            .block_states
+            .get_or_insert_with(ChunkSectionBlockStates::default);
 
         block_states.set_block(
             block_x,
@@ -384,9 +649,65 @@ impl AnvilChunk {
         )
     }
 
+    /// Sets the biome of the 4x4x4 cell that contains the block.
+    fn set_biome(&mut self, block_x: u8, block_y: i16, block_z: u8, biome: Biome) -> bool {
+        debug_assert!((block_x as usize) < SECTION_SIZE);
+        debug_assert!((block_z as usize) < SECTION_SIZE);
+
+        let section = self.get_section_or_insert(block_y.div_euclid(SECTION_SIZE as i16) as i8);
+        section
+            .biomes
+            .get_or_insert_with(ChunkSectionBiomes::default)
+            .set_biome(
+                block_x / SECTION_BIOMES_SIZE as u8,
+                (block_y.rem_euclid(SECTION_SIZE as i16)) as u8 / SECTION_BIOMES_SIZE as u8,
+                block_z / SECTION_BIOMES_SIZE as u8,
+                biome,
+            )
+    }
+
     fn block_entities(&self) -> &HashMap<(u8, i16, u8), BlockEntity> {
         &self.parsed_block_entities
     }
+
+    /// Inverse of parsing the chunk, in the same layout as chunks are stored in region files.
+    ///
+    /// NOTE: Fields that are not parsed (lighting, heightmaps, entities, etc.) are not written,
+    /// Minecraft recalculates them when loading the chunk.
+    pub fn to_nbt(&self) -> NBT {
+        nbt_compound![
+            "DataVersion" => NBT::Int(self.data_version),
+            "xPos" => NBT::Int(self.x_pos),
+            "zPos" => NBT::Int(self.z_pos),
+            "yPos" => NBT::Int(self.y_pos),
+            "Status" => NBT::String(self.status.clone()),
+            "LastUpdate" => NBT::Long(self.last_update),
+            "sections" => NBT::List(self.sections.iter().map(ChunkSection::to_nbt).collect()),
+            "block_entities" => NBT::List(
+                self.parsed_block_entities
+                    .iter()
+                    .map(|((x, y, z), block_entity)| {
+                        let mut compound = match &block_entity.data {
+                            NBT::Compound(compound) => compound.clone(),
+                            _ => HashMap::new(),
+                        };
+                        compound.insert("id".to_owned(), NBT::String(block_entity.r#type.clone()));
+                        compound.insert("keepPacked".to_owned(), NBT::Byte(0));
+                        compound.insert(
+                            "x".to_owned(),
+                            NBT::Int(self.x_pos * CHUNK_SIZE as i32 + *x as i32),
+                        );
+                        compound.insert("y".to_owned(), NBT::Int(*y as i32));
+                        compound.insert(
+                            "z".to_owned(),
+                            NBT::Int(self.z_pos * CHUNK_SIZE as i32 + *z as i32),
+                        );
+                        NBT::Compound(compound)
+                    })
+                    .collect()
+            ),
+        ]
+    }
 }
 
 #[derive(Debug)]
@@ -465,14 +786,15 @@ impl Region {
 
         match self
             .read_nbt(chunk_x, chunk_z)?
-            .map(|nbt| from_nbt::<AnvilChunk>(nbt.1))
+            .map(|nbt| parse_chunk(nbt.1))
             .transpose()?
         {
-            Some(mut chunk) => {
+            Some(mut chunk) if chunk.is_full() => {
                 chunk.initialize();
                 self.loaded_chunks.insert((chunk_x, chunk_z), Some(chunk));
             }
-            None => {
+            // Unfinished chunks are treated as if they don't exist, instead of sending holes.
+            _ => {
                 self.loaded_chunks.insert((chunk_x, chunk_z), None);
             }
         }
@@ -480,6 +802,106 @@ impl Region {
         Ok(())
     }
 
+    /// First sector of a free run that's num_sectors long, the header & every chunk's sectors are
+    /// used. If there's no such gap it's after the last used sector.
+    fn find_free_sectors(&self, num_sectors: usize) -> usize {
+        let mut used = vec![true; REGION_HEADER_SIZE / REGION_SECTOR_SIZE];
+        for &(offset, length) in self.locations.iter().filter(|(offset, _)| *offset != 0) {
+            let start = offset as usize / REGION_SECTOR_SIZE;
+            let end = start + (length as usize).div_ceil(REGION_SECTOR_SIZE);
+            if used.len() < end {
+                used.resize(end, false);
+            }
+            used[start..end].fill(true);
+        }
+        let mut start = 0;
+        for (sector, used) in used.iter().enumerate() {
+            if *used {
+                start = sector + 1;
+            } else if sector + 1 - start >= num_sectors {
+                return start;
+            }
+        }
+        start
+    }
+
+    /// Writes the chunk data zlib compressed into free sectors, the location is only updated once
+    /// the data is written, so the previous data stays intact if writing fails. The previous
+    /// sectors are free to reuse afterwards.
+    fn write(&mut self, chunk_x: u8, chunk_z: u8, data: &[u8]) -> Result<(), AnvilError> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let mut sectors = Vec::with_capacity(compressed.len() + 5);
+        sectors.extend(((compressed.len() + 1) as u32).to_be_bytes());
+        sectors.push(2);
+        sectors.extend(compressed);
+        let num_sectors = sectors.len().div_ceil(REGION_SECTOR_SIZE);
+        if num_sectors > u8::MAX as usize {
+            return Err(AnvilError::ChunkTooLarge(sectors.len()));
+        }
+        sectors.resize(num_sectors * REGION_SECTOR_SIZE, 0);
+
+        let index = (chunk_x as usize) + (chunk_z as usize) * REGION_SIZE;
+        let offset = (self.find_free_sectors(num_sectors) * REGION_SECTOR_SIZE) as u32;
+        self.file.seek(std::io::SeekFrom::Start(offset as u64))?;
+        self.file.write_all(&sectors)?;
+
+        self.file
+            .seek(std::io::SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(
+            &(((offset / REGION_SECTOR_SIZE as u32) << 8) | num_sectors as u32).to_be_bytes(),
+        )?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs() as u32)
+            .unwrap_or(0);
+        self.file.seek(std::io::SeekFrom::Start(
+            (REGION_SECTOR_SIZE + index * 4) as u64,
+        ))?;
+        self.file.write_all(&timestamp.to_be_bytes())?;
+        self.locations[index] = (offset, sectors.len() as u32);
+        Ok(())
+    }
+
+    /// Writes the loaded chunk over its original data, keeping anything that [`AnvilChunk`]
+    /// doesn't parse. Chunks that were inside of a `Level` compound are written back inside of
+    /// one, as their DataVersion expects.
+    fn save_chunk(&mut self, chunk_x: u8, chunk_z: u8) -> Result<(), AnvilError> {
+        let original = self.read_nbt(chunk_x, chunk_z)?.map(|nbt| nbt.1);
+        let level_wrapped = matches!(
+            &original,
+            Some(NBT::Compound(compound)) if matches!(compound.get("Level"), Some(NBT::Compound(_)))
+        );
+        let mut compound = match original.map(unwrap_level) {
+            Some(NBT::Compound(compound)) => compound,
+            _ => HashMap::new(),
+        };
+        let Some(chunk) = self.get_chunk(chunk_x, chunk_z) else {
+            return Ok(());
+        };
+        if let NBT::Compound(chunk_compound) = chunk.to_nbt() {
+            compound.extend(chunk_compound);
+        }
+        // These are out of date now, vanilla recalculates them.
+        compound.remove("Heightmaps");
+        compound.insert("isLightOn".to_owned(), NBT::Byte(0));
+        let nbt = if level_wrapped {
+            let mut root = HashMap::new();
+            if let Some(data_version) = compound.remove("DataVersion") {
+                root.insert("DataVersion".to_owned(), data_version);
+            }
+            root.insert("Level".to_owned(), NBT::Compound(compound));
+            NBT::Compound(root)
+        } else {
+            NBT::Compound(compound)
+        };
+        let data = nbt.to_bytes("", false)?;
+        self.write(chunk_x, chunk_z, &data)
+    }
+
     fn get_chunk(&self, chunk_x: u8, chunk_z: u8) -> Option<&AnvilChunk> {
         self.loaded_chunks
             .get(&(chunk_x, chunk_z))
@@ -522,13 +944,29 @@ impl SectionDiff {
 #[derive(Debug)]
 pub struct AnvilWorld {
     root: PathBuf,
-    identifier: String,
+    /// Overrides the vanilla dimension folder inside of root.
+    dimension_root: Option<PathBuf>,
+    dimension: Dimension,
     loaded_regions: HashMap<(i32, i32), Option<Region>>,
     section_y_range: std::ops::RangeInclusive<i8>,
     biome_mapper: IdTable<Biome>,
-    viewers: Vec<Weak<Mutex<WorldViewer>>>,
-    viewers_id: usize,
+    viewers: WorldViewers,
     diffs: HashMap<(i32, i32), HashMap<i16, SectionDiff>>,
+    /// Block entities set since the last update, coalesced per chunk.
+    block_entity_diffs: HashMap<(i32, i32), HashSet<Position>>,
+    /// Chunks that have to be resent as a whole, biomes are only sent with the chunk.
+    reload_chunks: HashSet<ChunkPosition>,
+    /// Chunks that were changed since they were last saved.
+    unsaved_chunks: HashSet<ChunkPosition>,
+    /// Chunks that are never unloaded, see [`World::keep_loaded`].
+    kept_chunks: HashSet<ChunkPosition>,
+    region_capacity: usize,
+    chunk_capacity: usize,
+    /// Incremented every time a chunk is accessed, used as the last access time.
+    access_count: u64,
+    region_access: HashMap<(i32, i32), u64>,
+    /// Every chunk that is loaded, including ones that don't exist.
+    chunk_access: HashMap<ChunkPosition, u64>,
 }
 
 impl AnvilWorld {
@@ -540,18 +978,64 @@ impl AnvilWorld {
     ) -> Self {
         Self {
             root: root.into(),
-            identifier: identifier.to_owned(),
+            dimension_root: None,
+            dimension: Dimension::new(identifier),
             loaded_regions: HashMap::new(),
             section_y_range,
             biome_mapper,
-            viewers: Vec::new(),
-            viewers_id: 0,
+            viewers: WorldViewers::new(),
             diffs: HashMap::new(),
+            block_entity_diffs: HashMap::new(),
+            reload_chunks: HashSet::new(),
+            unsaved_chunks: HashSet::new(),
+            kept_chunks: HashSet::new(),
+            region_capacity: DEFAULT_REGION_CAPACITY,
+            chunk_capacity: DEFAULT_CHUNK_CAPACITY,
+            access_count: 0,
+            region_access: HashMap::new(),
+            chunk_access: HashMap::new(),
+        }
+    }
+
+    /// Load this dimension from an explicit folder (containing "region/"), instead of the
+    /// vanilla layout relative to the world root.
+    pub fn with_dimension_root<P: Into<PathBuf>>(mut self, dimension_root: P) -> Self {
+        self.dimension_root = Some(dimension_root.into());
+        self
+    }
+
+    /// Maximum number of regions & chunks to keep loaded, the least recently used ones are
+    /// unloaded when [`World::update_viewers`] is called. Chunks that are viewed, kept loaded or
+    /// have unsaved changes are never unloaded, and regions only once none of their chunks are
+    /// loaded.
+    pub fn with_capacity(mut self, region_capacity: usize, chunk_capacity: usize) -> Self {
+        self.region_capacity = region_capacity;
+        self.chunk_capacity = chunk_capacity;
+        self
+    }
+
+    /// Folder that contains the "region/" folder for this dimension.
+    pub fn dimension_root(&self) -> PathBuf {
+        if let Some(dimension_root) = &self.dimension_root {
+            return dimension_root.clone();
+        }
+        let mut path = self.root.clone();
+        match self.dimension.name() {
+            "minecraft:overworld" => {}
+            "minecraft:the_nether" => path.push("DIM-1"),
+            "minecraft:the_end" => path.push("DIM1"),
+            name => {
+                let (namespace, name) = name.split_once(':').unwrap();
+                path.push("dimensions");
+                path.push(namespace);
+                path.push(name);
+            }
         }
+        path
     }
 
     pub fn identifier(&self) -> &str {
-        &self.identifier
+        self.dimension.name()
     }
 
     fn prepare_region(&mut self, region_x: i32, region_z: i32) -> Result<(), AnvilError> {
@@ -559,11 +1043,22 @@ impl AnvilWorld {
             return Ok(());
         }
 
-        let mut path = self.root.clone();
+        let mut path = self.dimension_root();
         path.push("region");
         path.push(format!("r.{}.{}.mca", region_x, region_z));
 
-        let file = match std::fs::File::open(path) {
+        // Read only worlds can still be viewed, they just can't be saved.
+        let file = match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+        {
+            Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                std::fs::File::open(&path)
+            }
+            result => result,
+        };
+        let file = match file {
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
                 self.loaded_regions.insert((region_x, region_z), None);
                 return Ok(());
@@ -604,6 +1099,165 @@ impl AnvilWorld {
             )?;
         }
 
+        self.access_count += 1;
+        self.region_access
+            .insert((region_x, region_z), self.access_count);
+        self.chunk_access
+            .insert(ChunkPosition::new(chunk_x, chunk_z), self.access_count);
+
+        Ok(())
+    }
+
+    /// Unloads the least recently used chunks & regions that are over capacity, see
+    /// [`AnvilWorld::with_capacity`].
+    fn evict(&mut self, viewers: &[Arc<Mutex<WorldViewer>>]) {
+        if self.chunk_access.len() > self.chunk_capacity {
+            let viewers = viewers
+                .iter()
+                .map(|viewer| viewer.lock().unwrap())
+                .collect::<Vec<_>>();
+            let evicted = self
+                .chunk_access
+                .iter()
+                .filter(|(chunk, _)| {
+                    !self.kept_chunks.contains(chunk)
+                        && !self.unsaved_chunks.contains(chunk)
+                        && !viewers
+                            .iter()
+                            .any(|viewer| viewer.loader.has_loaded(**chunk))
+                })
+                .sorted_by_key(|(_, access)| **access)
+                .map(|(chunk, _)| *chunk)
+                .take(self.chunk_access.len() - self.chunk_capacity)
+                .collect::<Vec<_>>();
+            drop(viewers);
+            evicted.into_iter().for_each(|chunk| {
+                self.chunk_access.remove(&chunk);
+                if let Some(region) = self.get_region_mut(
+                    chunk.chunk_x.div_euclid(REGION_SIZE as i32),
+                    chunk.chunk_z.div_euclid(REGION_SIZE as i32),
+                ) {
+                    region.loaded_chunks.remove(&(
+                        chunk.chunk_x.wrapping_rem_euclid(REGION_SIZE as i32) as u8,
+                        chunk.chunk_z.wrapping_rem_euclid(REGION_SIZE as i32) as u8,
+                    ));
+                }
+            });
+        }
+
+        if self.loaded_regions.len() > self.region_capacity {
+            let evicted = self
+                .loaded_regions
+                .iter()
+                .filter(|(_, region)| {
+                    region.as_ref().is_none_or(|region| {
+                        region.loaded_chunks.values().all(|chunk| chunk.is_none())
+                    })
+                })
+                .map(|(position, _)| *position)
+                .sorted_by_key(|position| self.region_access.get(position).copied())
+                .take(self.loaded_regions.len() - self.region_capacity)
+                .collect::<Vec<_>>();
+            evicted.into_iter().for_each(|(region_x, region_z)| {
+                self.loaded_regions.remove(&(region_x, region_z));
+                self.region_access.remove(&(region_x, region_z));
+                // Chunks that don't exist may still be loaded.
+                self.chunk_access.retain(|chunk, _| {
+                    chunk.chunk_x.div_euclid(REGION_SIZE as i32) != region_x
+                        || chunk.chunk_z.div_euclid(REGION_SIZE as i32) != region_z
+                });
+            });
+        }
+    }
+
+    /// None if the chunk isn't loaded or doesn't exist.
+    fn chunk_packet(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Result<Option<packet::play::LevelChunkWithLight>, AnvilError> {
+        let Some(chunk) = self.get_chunk(chunk_x, chunk_z) else {
+            return Ok(None);
+        };
+        let section_block_states = chunk.section_block_states(self.section_y_range());
+        Ok(Some(packet::play::LevelChunkWithLight {
+            chunk_x,
+            chunk_z,
+            chunk_data: packet::play::LevelChunkData {
+                heightmaps: compute_heightmaps(&section_block_states),
+                data: {
+                    let mut writer = Vec::new();
+
+                    self.section_y_range().try_for_each(|section_y| {
+                        if let Some(section) = chunk.get_section(section_y) {
+                            if let Some(block_states) = &section.block_states {
+                                block_states.write_with_block_count(&mut writer)?;
+                            } else {
+                                writer.write_all(&0u16.to_be_bytes())?;
+                                writer.write_all(&to_paletted_data_singular(
+                                    Block::air().id().unwrap(),
+                                )?)?;
+                            }
+                            if let Some(biomes) = &section.biomes {
+                                biomes.write(&mut writer, &self.biome_mapper)?;
+                            } else {
+                                writer.write_all(&to_paletted_data_singular(
+                                    Biome::default().id(&self.biome_mapper).unwrap(),
+                                )?)?;
+                            }
+                        } else {
+                            writer.write_all(&0u16.to_be_bytes())?;
+                            writer.write_all(&to_paletted_data_singular(
+                                Block::air().id().unwrap(),
+                            )?)?;
+                            writer.write_all(&to_paletted_data_singular(
+                                Biome::default().id(&self.biome_mapper).unwrap(),
+                            )?)?;
+                        }
+                        Ok::<_, AnvilError>(())
+                    })?;
+
+                    writer.into_boxed_slice()
+                },
+                block_entities: chunk
+                    .block_entities()
+                    .iter()
+                    .map(|((x, y, z), b)| packet::play::BlockEntity {
+                        x: *x,
+                        z: *z,
+                        y: *y,
+                        r#type: b.block_entity_id().unwrap(),
+                        data: b.data.clone(),
+                    })
+                    .collect(),
+            },
+            light_data: {
+                // TODO: Sky light
+                let mut light_data =
+                    packet::play::LevelLightData::full_bright(section_block_states.len());
+                light_data.block_lights_arrays = std::iter::once(None)
+                    .chain(compute_block_light(&section_block_states))
+                    .chain(std::iter::once(None))
+                    .collect();
+                light_data
+            },
+        }))
+    }
+
+    /// Writes the chunk to its region file, chunks that aren't loaded are skipped.
+    pub fn save_chunk(&mut self, chunk_x: i32, chunk_z: i32) -> Result<(), AnvilError> {
+        let Some(region) = self.get_region_mut(
+            chunk_x.div_euclid(REGION_SIZE as i32),
+            chunk_z.div_euclid(REGION_SIZE as i32),
+        ) else {
+            return Ok(());
+        };
+        region.save_chunk(
+            (chunk_x.wrapping_rem_euclid(REGION_SIZE as i32)) as u8,
+            (chunk_z.wrapping_rem_euclid(REGION_SIZE as i32)) as u8,
+        )?;
+        self.unsaved_chunks
+            .remove(&ChunkPosition::new(chunk_x, chunk_z));
         Ok(())
     }
 
@@ -630,35 +1284,25 @@ impl AnvilWorld {
         )?;
         Some(chunk)
     }
-
-    fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
-        self.section_y_range.clone()
-    }
 }
 
 impl World for AnvilWorld {
     type Error = AnvilError;
 
     fn add_viewer(&mut self, connection: ConnectionSender) -> Arc<Mutex<WorldViewer>> {
-        let viewer = Arc::new(Mutex::new(WorldViewer {
-            id: self.viewers_id,
-            connection,
-            loader: ChunkLoader::new(6),
-            position: Vec3::new(0.0, 100.0, 0.0),
-        }));
-        self.viewers_id += 1;
-        self.viewers.push(Arc::downgrade(&viewer));
-        viewer
+        self.viewers.add(connection, ChunkLoader::new(6))
     }
 
     fn update_viewers(&mut self) -> Result<(), Self::Error> {
-        self.viewers.retain(|v| v.strong_count() > 0);
-
-        let viewers = self
-            .viewers
-            .iter()
-            .flat_map(|v| v.upgrade())
-            .collect::<Vec<_>>();
+        let viewers = self.viewers.upgrade();
+
+        let mut reloaded = std::mem::take(&mut self.reload_chunks);
+        reloaded.iter().for_each(|chunk_position| {
+            viewers
+                .iter()
+                .map(|viewer| viewer.lock().unwrap())
+                .for_each(|mut viewer| viewer.loader.force_reload(*chunk_position));
+        });
 
         self.diffs
             .drain()
@@ -669,6 +1313,7 @@ impl World for AnvilWorld {
                         >= UPDATE_SECTION_CHUNK_SWITCH_NUM_BLOCKS
                 {
                     // Just resend the whole chunk
+                    reloaded.insert(chunk_position);
                     viewers
                         .iter()
                         .map(|viewer| viewer.lock().unwrap())
@@ -690,114 +1335,74 @@ impl World for AnvilWorld {
                 }
             })?;
 
+        std::mem::take(&mut self.block_entity_diffs)
+            .into_iter()
+            .filter(|((chunk_x, chunk_z), _)| {
+                // Resent chunks already include their block entities.
+                !reloaded.contains(&ChunkPosition::new(*chunk_x, *chunk_z))
+            })
+            .try_for_each(|((chunk_x, chunk_z), positions)| {
+                let chunk_position = ChunkPosition::new(chunk_x, chunk_z);
+                if positions.len() >= UPDATE_BLOCK_ENTITY_CHUNK_SWITCH_NUM {
+                    viewers
+                        .iter()
+                        .map(|viewer| viewer.lock().unwrap())
+                        .for_each(|mut viewer| viewer.loader.force_reload(chunk_position));
+                    return Ok(());
+                }
+                let Some(chunk) = self.get_chunk(chunk_x, chunk_z) else {
+                    return Ok(());
+                };
+                positions.into_iter().try_for_each(|position| {
+                    // Block entity may have been replaced since it was set.
+                    let Some(block_entity) = chunk.block_entities().get(&(
+                        position.x.rem_euclid(CHUNK_SIZE as i32) as u8,
+                        position.y,
+                        position.z.rem_euclid(CHUNK_SIZE as i32) as u8,
+                    )) else {
+                        return Ok(());
+                    };
+                    let Some(r#type) = block_entity.block_entity_id() else {
+                        return Ok(());
+                    };
+                    let packet = packet::play::BlockEntityData {
+                        location: position,
+                        r#type,
+                        data: block_entity.data.clone(),
+                    };
+                    viewers
+                        .iter()
+                        .map(|viewer| viewer.lock().unwrap())
+                        .filter(|viewer| viewer.loader.has_loaded(chunk_position))
+                        .try_for_each(|viewer| viewer.connection().send(&packet))
+                })
+            })?;
+
         viewers
             .iter()
             .map(|viewer| viewer.lock().unwrap())
             .try_for_each(|mut viewer| {
-                let center = ChunkPosition::new(
-                    (viewer.position.x / 16.0) as i32,
-                    (viewer.position.z / 16.0) as i32,
-                );
-                if viewer.loader.update_center(Some(center)) {
-                    viewer
-                        .connection()
-                        .send(&packet::play::SetChunkCacheCenter {
-                            chunk_x: center.chunk_x,
-                            chunk_z: center.chunk_z,
-                        })?;
-                }
-
-                while let Some(to_unload) = viewer.loader.next_to_unload() {
-                    viewer.connection().send(&packet::play::ForgetLevelChunk {
-                        chunk_x: to_unload.chunk_x,
-                        chunk_z: to_unload.chunk_z,
-                    })?;
-                }
+                viewer.update_center()?;
 
                 if let Some(to_load) = viewer.loader.next_to_load() {
                     self.prepare_chunk(to_load.chunk_x, to_load.chunk_z)?;
-                    if let Some(chunk) = self.get_chunk(to_load.chunk_x, to_load.chunk_z) {
-                        viewer
-                            .connection()
-                            .send(&packet::play::LevelChunkWithLight {
-                                chunk_x: to_load.chunk_x,
-                                chunk_z: to_load.chunk_z,
-                                chunk_data: packet::play::LevelChunkData {
-                                    heightmaps: nbt_compound!(),
-                                    data: {
-                                        let mut writer = Vec::new();
-
-                                        self.section_y_range().try_for_each(|section_y| {
-                                            if let Some(section) = chunk.get_section(section_y) {
-                                                if let Some(block_states) = &section.block_states {
-                                                    block_states.write(&mut writer)?;
-                                                } else {
-                                                    writer.write_all(&0u16.to_be_bytes())?;
-                                                    writer.write_all(
-                                                        &to_paletted_data_singular(
-                                                            Block::air().id().unwrap(),
-                                                        )?,
-                                                    )?;
-                                                }
-                                                if let Some(biomes) = &section.biomes {
-                                                    biomes
-                                                        .write(&mut writer, &self.biome_mapper)?;
-                                                } else {
-                                                    writer.write_all(
-                                                        &to_paletted_data_singular(
-                                                            Biome::default()
-                                                                .id(&self.biome_mapper)
-                                                                .unwrap(),
-                                                        )?,
-                                                    )?;
-                                                }
-                                            } else {
-                                                writer.write_all(&0u16.to_be_bytes())?;
-                                                writer.write_all(&to_paletted_data_singular(
-                                                    Block::air().id().unwrap(),
-                                                )?)?;
-                                                writer.write_all(&to_paletted_data_singular(
-                                                    Biome::default()
-                                                        .id(&self.biome_mapper)
-                                                        .unwrap(),
-                                                )?)?;
-                                            }
-                                            Ok::<_, AnvilError>(())
-                                        })?;
-
-                                        writer.into_boxed_slice()
-                                    },
-                                    block_entities: chunk
-                                        .block_entities()
-                                        .iter()
-                                        .map(|((x, y, z), b)| packet::play::BlockEntity {
-                                            x: *x,
-                                            z: *z,
-                                            y: *y,
-                                            r#type: b.block_entity_id().unwrap(),
-                                            data: b.data.clone(),
-                                        })
-                                        .collect(),
-                                },
-                                // TODO: Light data
-                                light_data: packet::play::LevelLightData::full_bright(
-                                    self.section_y_range().count(),
-                                ),
-                            })?;
-                    } else {
-                        viewer.connection().send(
+                    match self.chunk_packet(to_load.chunk_x, to_load.chunk_z)? {
+                        Some(packet) => viewer.connection().send(&packet)?,
+                        None => viewer.connection().send(
                             &packet::play::LevelChunkWithLight::generate_test(
                                 to_load.chunk_x,
                                 to_load.chunk_z,
                                 self.section_y_range().count(),
                             )?,
-                        )?;
+                        )?,
                     }
                 }
 
                 Ok::<(), Self::Error>(())
             })?;
 
+        self.evict(&viewers);
+
         Ok(())
     }
 
@@ -819,6 +1424,13 @@ impl World for AnvilWorld {
     }
 
     fn set_block(&mut self, position: Position, block: WorldBlock) -> Result<(), Self::Error> {
+        // Sections outside of the world would be created otherwise.
+        let section_y = position.y.div_euclid(SECTION_SIZE as i16);
+        if section_y < *self.section_y_range.start() as i16
+            || section_y > *self.section_y_range.end() as i16
+        {
+            return Ok(());
+        }
         let chunk_x = position.x.div_euclid(CHUNK_SIZE as i32);
         let chunk_z = position.z.div_euclid(CHUNK_SIZE as i32);
         self.prepare_chunk(chunk_x, chunk_z)?;
@@ -828,12 +1440,15 @@ impl World for AnvilWorld {
         ) else {
             return Ok(());
         };
+        let is_block_entity = block.as_block_entity().is_some();
         if chunk.set_block(
             (position.x.rem_euclid(CHUNK_SIZE as i32)) as u8,
             position.y,
             (position.z.rem_euclid(CHUNK_SIZE as i32)) as u8,
             block.clone(),
         ) {
+            self.unsaved_chunks
+                .insert(ChunkPosition::new(chunk_x, chunk_z));
             self.diffs
                 .entry((
                     position.x.div_euclid(SECTION_SIZE as i32),
@@ -852,24 +1467,214 @@ impl World for AnvilWorld {
                         .unwrap_or_else(|| Block::air().id().unwrap()),
                 );
         }
+        if is_block_entity {
+            self.unsaved_chunks
+                .insert(ChunkPosition::new(chunk_x, chunk_z));
+            self.block_entity_diffs
+                .entry((chunk_x, chunk_z))
+                .or_default()
+                .insert(position);
+        }
+        Ok(())
+    }
+
+    fn set_biome(&mut self, position: Position, biome: Biome) -> Result<(), Self::Error> {
+        let section_y = position.y.div_euclid(SECTION_SIZE as i16);
+        if section_y < *self.section_y_range.start() as i16
+            || section_y > *self.section_y_range.end() as i16
+        {
+            return Ok(());
+        }
+        let chunk_x = position.x.div_euclid(CHUNK_SIZE as i32);
+        let chunk_z = position.z.div_euclid(CHUNK_SIZE as i32);
+        self.prepare_chunk(chunk_x, chunk_z)?;
+        let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_z) else {
+            return Ok(());
+        };
+        if chunk.set_biome(
+            (position.x.rem_euclid(CHUNK_SIZE as i32)) as u8,
+            position.y,
+            (position.z.rem_euclid(CHUNK_SIZE as i32)) as u8,
+            biome,
+        ) {
+            self.reload_chunks
+                .insert(ChunkPosition::new(chunk_x, chunk_z));
+            self.unsaved_chunks
+                .insert(ChunkPosition::new(chunk_x, chunk_z));
+        }
         Ok(())
     }
+
+    fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+        self.loaded_regions
+            .values()
+            .flatten()
+            .flat_map(|region| {
+                region
+                    .loaded_chunks
+                    .iter()
+                    .filter(|(_, chunk)| chunk.is_some())
+                    .map(|((x, z), _)| {
+                        ChunkPosition::new(
+                            region.region_x * REGION_SIZE as i32 + *x as i32,
+                            region.region_z * REGION_SIZE as i32 + *z as i32,
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn keep_loaded(&mut self, chunks: &[ChunkPosition]) -> Result<(), Self::Error> {
+        self.kept_chunks.extend(chunks.iter().copied());
+        chunks
+            .iter()
+            .try_for_each(|chunk| self.prepare_chunk(chunk.chunk_x, chunk.chunk_z))
+    }
+
+    fn save(&mut self) -> Result<(), Self::Error> {
+        // Chunks are only marked as saved once written, so a failed save doesn't lose edits.
+        self.unsaved_chunks
+            .iter()
+            .copied()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .try_for_each(|chunk| self.save_chunk(chunk.chunk_x, chunk.chunk_z))
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        !self.unsaved_chunks.is_empty()
+    }
+
+    fn dimension(&self) -> &Dimension {
+        &self.dimension
+    }
+
+    fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
+        self.section_y_range.clone()
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use pkmc_defs::block::BLOCKS_TO_IDS;
-    use pkmc_util::Position;
-
-    use crate::world::{anvil::AnvilWorld, World as _};
+    use pkmc_defs::generated::{
+        generated, PALETTED_DATA_BIOMES_DIRECT, PALETTED_DATA_BIOMES_INDIRECT,
+        PALETTED_DATA_BLOCKS_DIRECT, PALETTED_DATA_BLOCKS_INDIRECT,
+    };
+    use pkmc_defs::{
+        biome::Biome,
+        block::{Block, BlockEntity, BLOCKS_TO_IDS},
+        packet,
+    };
+    use pkmc_util::{
+        nbt::{from_nbt, NBT},
+        nbt_compound,
+        packet::{to_paletted_data, ClientboundPacket as _, RawPacket},
+        test_util::{connection_pair, recieve_all},
+        IdTable, Position, Vec3,
+    };
+
+    use crate::world::{
+        anvil::{
+            compute_block_light, compute_heightmaps, parse_chunk, AnvilChunk, AnvilWorld,
+            ChunkSectionBlockStates, Region, REGION_HEADER_SIZE,
+        },
+        chunk_loader::ChunkPosition,
+        section_pos_biome_index, World as _, WorldBlock, CHUNK_SIZE, SECTION_BIOMES,
+        SECTION_BLOCKS,
+    };
 
     use super::AnvilError;
 
+    // 1.21.4 debug world
+    // https://minecraft.wiki/w/Debug_mode
+    const WORLD_PATH: &str = "./src/world/anvil-test-server/world/";
+
+    #[test]
+    fn test_chunk_to_nbt() -> Result<(), AnvilError> {
+        let file = std::fs::File::open(format!("{}region/r.0.0.mca", WORLD_PATH))?;
+        let mut region = Region::load(file, 0, 0)?;
+
+        let (_, nbt) = region.read_nbt(1, 2)?.unwrap();
+        let mut chunk: AnvilChunk = from_nbt(nbt)?;
+        chunk.initialize();
+
+        let bytes = chunk.to_nbt().to_bytes("", false)?;
+        let mut reparsed: AnvilChunk = from_nbt(NBT::from_bytes(&bytes, false)?.1)?;
+        reparsed.initialize();
+
+        assert_eq!((reparsed.x_pos, reparsed.z_pos), (1, 2));
+        assert_eq!(reparsed.y_pos, chunk.y_pos);
+        assert_eq!(reparsed.data_version, chunk.data_version);
+        assert_eq!(reparsed.status, chunk.status);
+        assert_eq!(reparsed.sections.len(), chunk.sections.len());
+
+        for section in chunk.sections.iter() {
+            let y_start = section.y as i16 * 16;
+            for y in y_start..y_start + 16 {
+                for z in 0..CHUNK_SIZE as u8 {
+                    for x in 0..CHUNK_SIZE as u8 {
+                        assert_eq!(chunk.get_block(x, y, z), reparsed.get_block(x, y, z));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_loaded_chunks() -> Result<(), AnvilError> {
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            Default::default(),
+        );
+        assert!(world.loaded_chunks().is_empty());
+
+        let chunks = [
+            ChunkPosition::new(0, 0),
+            ChunkPosition::new(1, 2),
+            ChunkPosition::new(3, 1),
+        ];
+        for chunk in chunks.iter() {
+            world.prepare_chunk(chunk.chunk_x, chunk.chunk_z)?;
+        }
+        // Chunks that don't exist aren't listed.
+        world.prepare_chunk(-1000, -1000)?;
+
+        let loaded = world.loaded_chunks();
+        assert_eq!(loaded.len(), chunks.len());
+        assert!(chunks.iter().all(|chunk| loaded.contains(chunk)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_root() -> Result<(), AnvilError> {
+        let root = std::path::PathBuf::from("./nonexistent-world/");
+        let world = |dimension: &str| {
+            AnvilWorld::new(&root, dimension, -4..=20, Default::default()).dimension_root()
+        };
+        assert_eq!(world("overworld"), root);
+        assert_eq!(world("the_nether"), root.join("DIM-1"));
+        assert_eq!(world("the_end"), root.join("DIM1"));
+        assert_eq!(
+            world("pkmc:void"),
+            root.join("dimensions").join("pkmc").join("void")
+        );
+
+        // Nether stored somewhere else entirely.
+        let mut nether = AnvilWorld::new(&root, "the_nether", -4..=20, Default::default())
+            .with_dimension_root(WORLD_PATH);
+        assert!(nether.get_block(Position::new(0, 70, 0))?.is_some());
+        assert_eq!(nether.loaded_chunks(), vec![ChunkPosition::new(0, 0)]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_debug_mode_world() -> Result<(), AnvilError> {
-        // 1.21.4 debug world
-        // https://minecraft.wiki/w/Debug_mode
-        const WORLD_PATH: &str = "./src/world/anvil-test-server/world/";
         println!(
             "Testing debug world: {:?}",
             std::fs::canonicalize(WORLD_PATH)?
@@ -925,4 +1730,723 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_block_entity_batching() -> Result<(), AnvilError> {
+        // Unknown biomes fall back to the default, which is all that's needed for chunk packets.
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            [(Biome::default(), 0)].into_iter().collect(),
+        );
+
+        let (server, mut client) = connection_pair()?;
+
+        let viewer = world.add_viewer(server.sender());
+        viewer.lock().unwrap().loader.update_radius(0);
+
+        // (BlockEntityData, LevelChunkWithLight) packets recieved.
+        let mut counts = || {
+            let mut counts = (0, 0);
+            for raw in recieve_all(&server.sender(), &mut client)? {
+                if raw.id == packet::play::BlockEntityData::CLIENTBOUND_ID {
+                    counts.0 += 1;
+                } else if raw.id == packet::play::LevelChunkWithLight::CLIENTBOUND_ID {
+                    counts.1 += 1;
+                }
+            }
+            Ok::<_, AnvilError>(counts)
+        };
+        // Load every chunk in view first.
+        world.update_viewers()?;
+        while counts()?.1 > 0 {
+            world.update_viewers()?;
+        }
+
+        let chest = |name: &str| {
+            WorldBlock::BlockEntity(BlockEntity::new(
+                Block::new("minecraft:chest"),
+                "minecraft:chest",
+                nbt_compound!("CustomName" => NBT::String(name.to_owned()),),
+            ))
+        };
+
+        // Setting the same block entity multiple times in a tick only sends it once.
+        for x in 0..3 {
+            world.set_block(Position::new(x, 100, 0), chest("a"))?;
+            world.set_block(Position::new(x, 100, 0), chest("b"))?;
+        }
+        world.update_viewers()?;
+        assert_eq!(counts()?, (3, 0));
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(Position::new(x, 101, z), chest("c"))?;
+            }
+        }
+        world.update_viewers()?;
+        assert_eq!(counts()?, (0, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paletted_data_write() -> Result<(), AnvilError> {
+        let file = std::fs::File::open(format!("{}region/r.0.0.mca", WORLD_PATH))?;
+        let mut region = Region::load(file, 0, 0)?;
+        let (_, nbt) = region.read_nbt(0, 0)?.unwrap();
+        let chunk: AnvilChunk = from_nbt(nbt)?;
+        let mapper: IdTable<Biome> = [(Biome::new("minecraft:plains"), 3), (Biome::default(), 0)]
+            .into_iter()
+            .collect();
+
+        for section in chunk.sections.iter() {
+            if let Some(block_states) = &section.block_states {
+                // Encoded by mapping every entry by hand.
+                let ids = (0..SECTION_BLOCKS)
+                    .map(|i| {
+                        block_states
+                            .get(i)
+                            .id_with_default_fallback()
+                            .unwrap_or_else(|| Block::air().id().unwrap())
+                    })
+                    .collect::<Vec<_>>();
+                let mut expected = Vec::new();
+                expected.extend(
+                    (ids.iter()
+                        .filter(|id| !generated::block::is_air(**id))
+                        .count() as u16)
+                        .to_be_bytes(),
+                );
+                expected.extend(to_paletted_data(
+                    &ids,
+                    PALETTED_DATA_BLOCKS_INDIRECT,
+                    PALETTED_DATA_BLOCKS_DIRECT,
+                )?);
+                let mut written = Vec::new();
+                block_states.write_with_block_count(&mut written)?;
+                assert_eq!(written, expected);
+            }
+            if let Some(biomes) = &section.biomes {
+                let ids = (0..SECTION_BIOMES)
+                    .map(|i| biomes.get(i).id(&mapper).unwrap_or(0))
+                    .collect::<Vec<_>>();
+                let expected = to_paletted_data(
+                    &ids,
+                    PALETTED_DATA_BIOMES_INDIRECT,
+                    PALETTED_DATA_BIOMES_DIRECT,
+                )?;
+                let mut written = Vec::new();
+                biomes.write(&mut written, &mapper)?;
+                assert_eq!(written.into_boxed_slice(), expected);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_and_edit() -> Result<(), AnvilError> {
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            [(Biome::default(), 0)].into_iter().collect(),
+        );
+
+        let (server, mut client) = connection_pair()?;
+
+        let viewer = world.add_viewer(server.sender());
+        viewer.lock().unwrap().loader.update_radius(0);
+        let mut recieved = || {
+            Ok::<_, AnvilError>(
+                recieve_all(&server.sender(), &mut client)?
+                    .into_iter()
+                    .map(|raw| raw.id)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        world.update_viewers()?;
+        let ids = recieved()?;
+        assert!(ids.contains(&packet::play::SetChunkCacheCenter::CLIENTBOUND_ID));
+        assert!(ids.contains(&packet::play::LevelChunkWithLight::CLIENTBOUND_ID));
+        loop {
+            world.update_viewers()?;
+            if !recieved()?.contains(&packet::play::LevelChunkWithLight::CLIENTBOUND_ID) {
+                break;
+            }
+        }
+        assert!(viewer
+            .lock()
+            .unwrap()
+            .loader
+            .has_loaded(ChunkPosition::new(0, 0)));
+
+        let stone = WorldBlock::Block(Block::new("minecraft:stone"));
+        world.set_block(Position::new(3, 100, 3), stone.clone())?;
+        assert_eq!(world.get_block(Position::new(3, 100, 3))?, Some(stone));
+        world.update_viewers()?;
+        assert_eq!(
+            recieved()?,
+            vec![packet::play::UpdateSectionBlocks::CLIENTBOUND_ID]
+        );
+
+        // Moving away forgets the chunk.
+        viewer.lock().unwrap().position = Vec3::new(1000.0, 100.0, 1000.0);
+        world.update_viewers()?;
+        assert!(recieved()?.contains(&packet::play::ForgetLevelChunk::CLIENTBOUND_ID));
+
+        Ok(())
+    }
+
+    /// Write an uncompressed region file containing the chunks.
+    fn write_region(path: &std::path::Path, chunks: &[((u8, u8), NBT)]) -> Result<(), AnvilError> {
+        let mut locations = vec![0u8; 4096];
+        let mut data = Vec::new();
+        chunks.iter().try_for_each(|((x, z), nbt)| {
+            let bytes = nbt.to_bytes("", false)?;
+            let offset = 2 + data.len() / 4096;
+            let mut sector = Vec::new();
+            sector.extend(((bytes.len() + 1) as u32).to_be_bytes());
+            sector.push(3);
+            sector.extend(bytes);
+            sector.resize(sector.len().div_ceil(4096) * 4096, 0);
+            let index = (*x as usize + *z as usize * 32) * 4;
+            locations[index..index + 4].copy_from_slice(
+                &(((offset as u32) << 8) | (sector.len() / 4096) as u32).to_be_bytes(),
+            );
+            data.extend(sector);
+            Ok::<_, AnvilError>(())
+        })?;
+        let mut file = locations;
+        file.extend([0u8; 4096]);
+        file.extend(data);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unfinished_chunk() -> Result<(), AnvilError> {
+        let file = std::fs::File::open(format!("{}region/r.0.0.mca", WORLD_PATH))?;
+        let mut region = Region::load(file, 0, 0)?;
+        let (_, full) = region.read_nbt(1, 2)?.unwrap();
+        let NBT::Compound(mut unfinished) = full.clone() else {
+            panic!();
+        };
+        unfinished.insert(
+            "Status".to_owned(),
+            NBT::String("minecraft:features".to_owned()),
+        );
+
+        let root = std::env::temp_dir().join(format!("pkmc-unfinished-{}", std::process::id()));
+        write_region(
+            &root.join("region").join("r.0.0.mca"),
+            &[((1, 2), full), ((3, 4), NBT::Compound(unfinished))],
+        )?;
+
+        let mut world = AnvilWorld::new(
+            &root,
+            "minecraft:overworld",
+            -4..=20,
+            [(Biome::default(), 0)].into_iter().collect(),
+        );
+        world.prepare_chunk(1, 2)?;
+        world.prepare_chunk(3, 4)?;
+        assert_eq!(world.loaded_chunks(), vec![ChunkPosition::new(1, 2)]);
+        assert!(world.get_block(Position::new(16, 70, 32))?.is_some());
+        assert!(world.get_block(Position::new(48, 70, 64))?.is_none());
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paletted_data_bpe() {
+        let mut block_states = ChunkSectionBlockStates::default();
+        assert_eq!(*block_states.get(0), Block::air());
+
+        // Past the indirect range, which only limits what's sent to the client.
+        for id in 1..=300 {
+            assert!(block_states.set(id as usize * 11, Block::from_id(id).unwrap()));
+            if [1, 15, 16, 255, 256].contains(&id) {
+                (0..=id).for_each(|id| {
+                    let block = block_states.get(id as usize * 11);
+                    assert_eq!(block.id(), Some(id));
+                });
+            }
+        }
+        assert_eq!(block_states.palette.len(), 301);
+        assert_eq!(*block_states.get(1), Block::air());
+    }
+
+    #[test]
+    fn test_keep_loaded() -> Result<(), AnvilError> {
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            Default::default(),
+        );
+
+        let spawn_chunks = ChunkPosition::new(0, 0)
+            .square_around(2)
+            .collect::<Vec<_>>();
+        assert_eq!(spawn_chunks.len(), 25);
+        world.keep_loaded(&spawn_chunks)?;
+        world.update_viewers()?;
+
+        let loaded = world.loaded_chunks();
+        assert!(loaded.contains(&ChunkPosition::new(0, 0)));
+        assert!(loaded.iter().all(|chunk| spawn_chunks.contains(chunk)));
+
+        Ok(())
+    }
+
+    /// Chunk 1, 2 of the debug world, and the same chunk moved inside of a `Level` compound.
+    fn level_wrapped_chunk() -> Result<(NBT, NBT), AnvilError> {
+        let file = std::fs::File::open(format!("{}region/r.0.0.mca", WORLD_PATH))?;
+        let mut region = Region::load(file, 0, 0)?;
+        let (_, nbt) = region.read_nbt(1, 2)?.unwrap();
+
+        let NBT::Compound(mut compound) = nbt.clone() else {
+            panic!("Chunk isn't a compound");
+        };
+        let data_version = compound.remove("DataVersion").unwrap();
+        let wrapped = nbt_compound![
+            "DataVersion" => data_version,
+            "Level" => NBT::Compound(compound),
+        ];
+        Ok((nbt, wrapped))
+    }
+
+    /// Only the wrapper is handled, the chunk inside of it has the 1.18+ layout.
+    #[test]
+    fn test_level_wrapped_chunk() -> Result<(), AnvilError> {
+        let (nbt, wrapped) = level_wrapped_chunk()?;
+
+        let mut chunk = parse_chunk(nbt)?;
+        chunk.initialize();
+        let mut unwrapped = parse_chunk(wrapped)?;
+        unwrapped.initialize();
+        assert_eq!((unwrapped.x_pos, unwrapped.z_pos), (1, 2));
+        assert_eq!(unwrapped.data_version, chunk.data_version);
+        assert_eq!(unwrapped.status, chunk.status);
+        assert_eq!(unwrapped.parsed_block_entities, chunk.parsed_block_entities);
+        assert_eq!(
+            unwrapped
+                .sections
+                .iter()
+                .map(|section| section.to_nbt())
+                .collect::<Vec<_>>(),
+            chunk
+                .sections
+                .iter()
+                .map(|section| section.to_nbt())
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_chunk_layout() {
+        // Layout of a 1.17.1 chunk, with one section of stone.
+        let legacy = nbt_compound![
+            "DataVersion" => NBT::Int(2730),
+            "Level" => nbt_compound![
+                "xPos" => NBT::Int(0),
+                "zPos" => NBT::Int(0),
+                "Status" => NBT::String("full".to_owned()),
+                "Sections" => NBT::List(vec![nbt_compound![
+                    "Y" => NBT::Byte(0),
+                    "Palette" => NBT::List(vec![nbt_compound![
+                        "Name" => NBT::String("minecraft:stone".to_owned()),
+                    ]]),
+                    "BlockStates" => NBT::LongArray(vec![0; 256].into_boxed_slice()),
+                ]]),
+                "TileEntities" => NBT::List(Vec::new()),
+            ],
+        ];
+        assert!(matches!(
+            parse_chunk(legacy),
+            Err(AnvilError::LegacyChunkLayout(2730))
+        ));
+    }
+
+    /// Chunk packet with the heightmaps decoded, compound keys aren't written in any set order.
+    fn decode_heightmaps(raw: &RawPacket) -> Result<(NBT, Box<[u8]>), AnvilError> {
+        let mut data = std::io::Cursor::new(&raw.data[8..]);
+        let heightmaps = NBT::read_network(&mut data)?;
+        let rest = raw.data[..8]
+            .iter()
+            .chain(&data.get_ref()[data.position() as usize..])
+            .copied()
+            .collect();
+        Ok((heightmaps, rest))
+    }
+
+    #[test]
+    fn test_set_biome_resends_chunk() -> Result<(), AnvilError> {
+        let desert = Biome::new("minecraft:desert");
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            [(Biome::default(), 0), (desert.clone(), 1)]
+                .into_iter()
+                .collect(),
+        );
+
+        let (server, mut client) = connection_pair()?;
+
+        let viewer = world.add_viewer(server.sender());
+        viewer.lock().unwrap().loader.update_radius(0);
+        let mut chunk_packets = || {
+            let mut packets = Vec::new();
+            for raw in recieve_all(&server.sender(), &mut client)? {
+                if raw.id == packet::play::LevelChunkWithLight::CLIENTBOUND_ID {
+                    packets.push(raw);
+                }
+            }
+            Ok::<_, AnvilError>(packets)
+        };
+        // Load every chunk in view first.
+        world.update_viewers()?;
+        while !chunk_packets()?.is_empty() {
+            world.update_viewers()?;
+        }
+        let before = decode_heightmaps(&world.chunk_packet(0, 0)?.unwrap().raw_packet()?)?;
+
+        let position = Position::new(5, 70, 9);
+        world.set_biome(position, desert.clone())?;
+        world.update_viewers()?;
+        let after = chunk_packets()?;
+        assert_eq!(after.len(), 1);
+        // Setting the same biome again doesn't resend.
+        world.set_biome(position, desert.clone())?;
+        world.update_viewers()?;
+        assert!(chunk_packets()?.is_empty());
+
+        let chunk = world.get_chunk(0, 0).unwrap();
+        let biomes = chunk.get_section(4).unwrap().biomes.as_ref().unwrap();
+        assert_eq!(biomes.get(section_pos_biome_index(1, 1, 2)), &desert);
+        let after = decode_heightmaps(&after[0])?;
+        assert_ne!(after, before);
+        assert_eq!(
+            after,
+            decode_heightmaps(&world.chunk_packet(0, 0)?.unwrap().raw_packet()?)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_block_light() {
+        let mut bottom = ChunkSectionBlockStates::default();
+        bottom.set_block(8, 15, 8, Block::new("minecraft:glowstone"));
+        bottom.set_block(9, 15, 8, Block::new("minecraft:stone"));
+        let top = ChunkSectionBlockStates::default();
+        // Section in between is missing, it should act as air.
+        let light = compute_block_light(&[Some(&bottom), None, None, Some(&top)]);
+        assert_eq!(light.len(), 4);
+
+        let level = |x: u8, y: usize, z: u8| {
+            let Some(section) = &light[y / 16] else {
+                return 0;
+            };
+            let index = (y % 16) * 256 + z as usize * 16 + x as usize;
+            (section[index / 2] >> ((index % 2) * 4)) & 0xF
+        };
+        assert_eq!(level(8, 15, 8), 15);
+        assert_eq!(level(8, 14, 8), 14);
+        assert_eq!(level(8, 16, 8), 14);
+        assert_eq!(level(8, 15, 12), 11);
+        assert_eq!(level(8, 25, 8), 5);
+        assert_eq!(level(0, 15, 0), 0);
+        // Stone blocks the light, so it has to go around it.
+        assert_eq!(level(9, 15, 8), 0);
+        assert_eq!(level(10, 15, 8), 11);
+
+        assert!(light[1].is_some());
+        assert!(light[2].is_none());
+        assert!(light[3].is_none());
+    }
+
+    #[test]
+    fn test_compute_heightmaps() -> Result<(), AnvilError> {
+        let file = std::fs::File::open(format!("{}region/r.0.0.mca", WORLD_PATH))?;
+        let mut region = Region::load(file, 0, 0)?;
+
+        for (chunk_x, chunk_z) in [(0, 0), (1, 2), (5, 5)] {
+            let (_, nbt) = region.read_nbt(chunk_x, chunk_z)?.unwrap();
+            // Heightmaps that vanilla saved with the chunk.
+            let NBT::Compound(mut compound) = nbt.clone() else {
+                panic!();
+            };
+            let Some(NBT::Compound(mut expected)) = compound.remove("Heightmaps") else {
+                panic!();
+            };
+            expected.retain(|name, _| name == "MOTION_BLOCKING" || name == "WORLD_SURFACE");
+
+            let mut chunk: AnvilChunk = from_nbt(nbt)?;
+            chunk.initialize();
+            let heightmaps = compute_heightmaps(&chunk.section_block_states(-4..=19));
+            assert_eq!(heightmaps, NBT::Compound(expected));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save() -> Result<(), AnvilError> {
+        let root = std::env::temp_dir().join(format!("pkmc-save-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("region"))?;
+        std::fs::copy(
+            format!("{}region/r.0.0.mca", WORLD_PATH),
+            root.join("region").join("r.0.0.mca"),
+        )?;
+        let new_world = || {
+            AnvilWorld::new(
+                &root,
+                "minecraft:overworld",
+                -4..=20,
+                [(Biome::default(), 0)].into_iter().collect(),
+            )
+        };
+
+        let mut world = new_world();
+        let original = world.get_block(Position::new(17, 70, 33))?;
+        let position = Position::new(19, 80, 37);
+        let stone = WorldBlock::Block(Block::new("minecraft:stone"));
+        world.set_block(position, stone.clone())?;
+        assert!(world.has_unsaved_changes());
+        world.save()?;
+        assert!(!world.has_unsaved_changes());
+
+        let mut reloaded = new_world();
+        assert_eq!(reloaded.get_block(position)?, Some(stone));
+        assert_eq!(reloaded.get_block(Position::new(17, 70, 33))?, original);
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_failed() -> Result<(), AnvilError> {
+        let root = std::env::temp_dir().join(format!("pkmc-save-failed-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("region"))?;
+        for region in ["r.0.0.mca", "r.1.0.mca"] {
+            std::fs::copy(
+                format!("{}region/r.0.0.mca", WORLD_PATH),
+                root.join("region").join(region),
+            )?;
+        }
+        let new_world = || {
+            AnvilWorld::new(
+                &root,
+                "minecraft:overworld",
+                -4..=20,
+                [(Biome::default(), 0)].into_iter().collect(),
+            )
+        };
+
+        let mut world = new_world();
+        let stone = WorldBlock::Block(Block::new("minecraft:stone"));
+        let positions = [
+            Position::new(19, 80, 37),
+            Position::new(40, 80, 37),
+            Position::new(19 + 512, 80, 37),
+        ];
+        positions
+            .iter()
+            .try_for_each(|position| world.set_block(*position, stone.clone()))?;
+
+        // Writing to region 0 0 fails.
+        let region = world.get_region_mut(0, 0).unwrap();
+        region.file = std::fs::File::open(root.join("region").join("r.0.0.mca"))?;
+        assert!(world.save().is_err());
+        assert!(world.unsaved_chunks.contains(&ChunkPosition::new(1, 2)));
+        assert!(world.unsaved_chunks.contains(&ChunkPosition::new(2, 2)));
+
+        let region = world.get_region_mut(0, 0).unwrap();
+        region.file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(root.join("region").join("r.0.0.mca"))?;
+        world.save()?;
+        assert!(!world.has_unsaved_changes());
+
+        let mut reloaded = new_world();
+        for position in positions {
+            assert_eq!(reloaded.get_block(position)?, Some(stone.clone()));
+        }
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_region_write() -> Result<(), AnvilError> {
+        let path = std::env::temp_dir().join(format!("pkmc-region-write-{}", std::process::id()));
+        write_region(
+            &path,
+            &[((0, 0), nbt_compound!()), ((1, 0), nbt_compound!())],
+        )?;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        let mut region = Region::load(file, 0, 0)?;
+
+        // Doesn't compress well, so it doesn't fit in the original sector.
+        let mut seed = 1u64;
+        let data = (0..10000)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (seed >> 56) as u8
+            })
+            .collect::<Vec<_>>();
+        region.write(0, 0, &data)?;
+        region.write(2, 0, &[1, 2, 3])?;
+
+        let file = std::fs::File::open(&path)?;
+        let mut region = Region::load(file, 0, 0)?;
+        assert_eq!(region.read(0, 0)?.as_deref(), Some(data.as_slice()));
+        assert_eq!(region.read(2, 0)?.as_deref(), Some([1, 2, 3].as_slice()));
+        assert!(region.read_nbt(1, 0)?.is_some());
+        assert!(region.locations[0].0 >= region.locations[1].0 + 4096);
+        // Takes the sector that chunk 0, 0 moved out of.
+        assert_eq!(region.locations[2].0, REGION_HEADER_SIZE as u32);
+
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_level_wrapped_chunk() -> Result<(), AnvilError> {
+        let path =
+            std::env::temp_dir().join(format!("pkmc-save-level-wrapped-{}", std::process::id()));
+        let (nbt, wrapped) = level_wrapped_chunk()?;
+        write_region(&path, &[((1, 2), wrapped)])?;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        let mut region = Region::load(file, 0, 0)?;
+        region.prepare_chunk(1, 2)?;
+        region.save_chunk(1, 2)?;
+
+        let NBT::Compound(mut saved) = region.read_nbt(1, 2)?.unwrap().1 else {
+            panic!("Chunk isn't a compound");
+        };
+        let NBT::Compound(original) = nbt else {
+            panic!("Chunk isn't a compound");
+        };
+        assert_eq!(saved.len(), 2);
+        assert_eq!(saved.get("DataVersion"), original.get("DataVersion"));
+        let Some(NBT::Compound(level)) = saved.remove("Level") else {
+            panic!("Chunk isn't inside of Level");
+        };
+        assert!(!level.contains_key("DataVersion"));
+        assert_eq!(level.get("xPos"), Some(&NBT::Int(1)));
+        assert_eq!(level.get("zPos"), Some(&NBT::Int(2)));
+        assert!(matches!(level.get("sections"), Some(NBT::List(sections)) if !sections.is_empty()));
+
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_region_write_not_in_place() -> Result<(), AnvilError> {
+        let path = std::env::temp_dir().join(format!(
+            "pkmc-region-write-not-in-place-{}",
+            std::process::id()
+        ));
+        write_region(
+            &path,
+            &[((0, 0), nbt_compound!()), ((1, 0), nbt_compound!())],
+        )?;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        let mut region = Region::load(file, 0, 0)?;
+        let original = region.locations[0];
+
+        // Still fits in its own sector, but that's the only copy, so it's written elsewhere.
+        region.write(0, 0, &[1, 2, 3])?;
+        assert_ne!(region.locations[0].0, original.0);
+        let moved = region.locations[0];
+
+        // The sector it moved out of is free again, instead of appending forever.
+        region.write(0, 0, &[4, 5, 6])?;
+        assert_eq!(region.locations[0], original);
+        (0..8).try_for_each(|i| region.write(0, 0, &[i]))?;
+        assert!([original, moved].contains(&region.locations[0]));
+        assert_eq!(std::fs::metadata(&path)?.len(), (moved.0 + moved.1) as u64);
+
+        let file = std::fs::File::open(&path)?;
+        let mut region = Region::load(file, 0, 0)?;
+        assert_eq!(region.read(0, 0)?.as_deref(), Some([7].as_slice()));
+        assert!(region.read_nbt(1, 0)?.is_some());
+
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_eviction() -> Result<(), AnvilError> {
+        let mut world = AnvilWorld::new(
+            WORLD_PATH,
+            "minecraft:overworld",
+            -4..=20,
+            Default::default(),
+        )
+        .with_capacity(1, 4);
+        world.keep_loaded(&[ChunkPosition::new(0, 0)])?;
+        (1..=5).try_for_each(|x| world.prepare_chunk(x, 0))?;
+        // Chunks in a region that doesn't exist.
+        world.prepare_chunk(-1, 0)?;
+        world.update_viewers()?;
+
+        let mut loaded = world.loaded_chunks();
+        loaded.sort_by_key(|chunk| chunk.chunk_x);
+        assert_eq!(
+            loaded,
+            vec![
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(4, 0),
+                ChunkPosition::new(5, 0),
+            ]
+        );
+        assert_eq!(world.loaded_regions.len(), 1);
+
+        // Accessing an old chunk again makes it the most recently used.
+        world.prepare_chunk(4, 0)?;
+        world.prepare_chunk(6, 0)?;
+        world.prepare_chunk(7, 0)?;
+        world.update_viewers()?;
+        let mut loaded = world.loaded_chunks();
+        loaded.sort_by_key(|chunk| chunk.chunk_x);
+        assert_eq!(
+            loaded,
+            vec![
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(4, 0),
+                ChunkPosition::new(6, 0),
+                ChunkPosition::new(7, 0),
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashSet;
 
 use itertools::Itertools;
-use pkmc_util::IterRetain as _;
+use pkmc_util::{IterRetain as _, Vec3};
+
+use crate::world::CHUNK_SIZE;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct ChunkPosition {
@@ -14,17 +16,57 @@ impl ChunkPosition {
         Self { chunk_x, chunk_z }
     }
 
+    /// The chunk that contains the world position.
+    pub fn from_world_position(position: Vec3<f64>) -> Self {
+        Self::new(
+            (position.x / CHUNK_SIZE as f64).floor() as i32,
+            (position.z / CHUNK_SIZE as f64).floor() as i32,
+        )
+    }
+
     pub fn distance(&self, other: &ChunkPosition) -> f32 {
         let dx = (other.chunk_x - self.chunk_x) as f32;
         let dz = (other.chunk_z - self.chunk_z) as f32;
         (dx * dx + dz * dz).sqrt()
     }
+
+    /// All chunks in a square of `radius` chunks around this one, including this one.
+    pub fn square_around(self, radius: i32) -> impl Iterator<Item = ChunkPosition> {
+        (-radius..=radius)
+            .cartesian_product(-radius..=radius)
+            .map(move |(dx, dz)| ChunkPosition::new(self.chunk_x + dx, self.chunk_z + dz))
+    }
+}
+
+/// The region of chunks around the center that get loaded.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum LoadShape {
+    /// Same as vanilla.
+    #[default]
+    Square,
+    /// Loads less chunks on larger radii, with no visible difference.
+    Circle,
+}
+
+impl LoadShape {
+    fn contains(&self, center: &ChunkPosition, chunk: &ChunkPosition, radius: i32) -> bool {
+        match self {
+            LoadShape::Square => {
+                (chunk.chunk_x - center.chunk_x)
+                    .abs()
+                    .max((chunk.chunk_z - center.chunk_z).abs())
+                    < radius
+            }
+            LoadShape::Circle => center.distance(chunk) < radius as f32,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ChunkLoader {
     center: Option<ChunkPosition>,
     radius: i32,
+    shape: LoadShape,
     to_load: HashSet<ChunkPosition>,
     loaded: HashSet<ChunkPosition>,
     to_unload: Vec<ChunkPosition>,
@@ -38,22 +80,34 @@ impl ChunkLoader {
         Self {
             center: None,
             radius,
+            shape: LoadShape::default(),
             to_load: HashSet::new(),
             loaded: HashSet::new(),
             to_unload: Vec::new(),
         }
     }
 
+    pub fn with_shape(mut self, shape: LoadShape) -> Self {
+        self.shape = shape;
+        self.force_update();
+        self
+    }
+
+    pub fn shape(&self) -> LoadShape {
+        self.shape
+    }
+
     fn iter_radius(&self) -> impl Iterator<Item = ChunkPosition> {
         let center = self.center.unwrap();
         let radius = self.radius + EXTRA_RADIUS;
+        let shape = self.shape;
         (-radius..=radius)
             .flat_map(move |dx| (-radius..=radius).map(move |dz| (dx, dz)))
             .map(move |(dx, dz)| ChunkPosition {
                 chunk_x: center.chunk_x + dx,
                 chunk_z: center.chunk_z + dz,
             })
-            .filter(move |chunk| center.distance(chunk) < radius as f32)
+            .filter(move |chunk| shape.contains(&center, chunk, radius))
     }
 
     fn force_update(&mut self) {
@@ -63,12 +117,13 @@ impl ChunkLoader {
             return;
         };
 
+        let (shape, radius) = (self.shape, self.radius + EXTRA_RADIUS);
         self.to_load
-            .retain(|chunk| center.distance(chunk) < (self.radius + EXTRA_RADIUS) as f32);
+            .retain(|chunk| shape.contains(&center, chunk, radius));
         self.to_unload.append(
-            &mut self.loaded.retain_returned(|chunk| {
-                center.distance(chunk) < (self.radius + EXTRA_RADIUS) as f32
-            }),
+            &mut self
+                .loaded
+                .retain_returned(|chunk| shape.contains(&center, chunk, radius)),
         );
         self.iter_radius().for_each(|chunk| {
             if self.to_load.contains(&chunk) || self.loaded.contains(&chunk) {
@@ -136,7 +191,92 @@ impl ChunkLoader {
         }
     }
 
+    pub fn force_reload_many(&mut self, positions: impl IntoIterator<Item = ChunkPosition>) {
+        positions
+            .into_iter()
+            .for_each(|position| self.force_reload(position));
+    }
+
+    pub fn center(&self) -> Option<ChunkPosition> {
+        self.center
+    }
+
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    /// Number of chunks queued to be loaded, loaded, and queued to be unloaded.
+    pub fn queue_sizes(&self) -> (usize, usize, usize) {
+        (self.to_load.len(), self.loaded.len(), self.to_unload.len())
+    }
+
     pub fn has_loaded(&self, position: ChunkPosition) -> bool {
         self.loaded.contains(&position) || self.to_unload.iter().contains(&position)
     }
+
+    /// Every chunk that [`ChunkLoader::has_loaded`].
+    pub fn loaded(&self) -> impl Iterator<Item = ChunkPosition> + '_ {
+        self.loaded.iter().chain(self.to_unload.iter()).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::world::chunk_loader::{ChunkLoader, ChunkPosition, LoadShape, EXTRA_RADIUS};
+
+    fn queued_mut(loader: &mut ChunkLoader) -> Vec<ChunkPosition> {
+        std::iter::from_fn(|| loader.next_to_load()).collect()
+    }
+
+    fn queued(mut loader: ChunkLoader) -> Vec<ChunkPosition> {
+        queued_mut(&mut loader)
+    }
+
+    #[test]
+    fn test_force_reload_many() {
+        let mut loader = ChunkLoader::new(2);
+        loader.update_center(Some(ChunkPosition::new(0, 0)));
+        let loaded = queued_mut(&mut loader);
+        assert!(loader.next_to_load().is_none());
+        assert_eq!(loader.loaded().count(), loaded.len());
+
+        let reload = [
+            ChunkPosition::new(0, 0),
+            ChunkPosition::new(1, 0),
+            ChunkPosition::new(-1, 1),
+        ];
+        assert!(reload.iter().all(|chunk| loaded.contains(chunk)));
+        // Chunks that aren't loaded are ignored.
+        loader.force_reload_many(reload.into_iter().chain([ChunkPosition::new(100, 100)]));
+
+        let mut reloaded = queued_mut(&mut loader);
+        reloaded.sort_by_key(|chunk| (chunk.chunk_x, chunk.chunk_z));
+        let mut expected = reload.to_vec();
+        expected.sort_by_key(|chunk| (chunk.chunk_x, chunk.chunk_z));
+        assert_eq!(reloaded, expected);
+    }
+
+    #[test]
+    fn test_circle_load_shape() {
+        let center = ChunkPosition::new(5, -3);
+
+        let mut square = ChunkLoader::new(8);
+        assert_eq!(square.shape(), LoadShape::Square);
+        square.update_center(Some(center));
+        let square = queued(square);
+
+        let mut circle = ChunkLoader::new(8).with_shape(LoadShape::Circle);
+        circle.update_center(Some(center));
+        let circle = queued(circle);
+
+        assert!(circle.len() < square.len());
+        assert!(circle.iter().all(|chunk| square.contains(chunk)));
+
+        let corner = 8 + EXTRA_RADIUS - 1;
+        let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)].map(|(x, z)| {
+            ChunkPosition::new(center.chunk_x + x * corner, center.chunk_z + z * corner)
+        });
+        assert!(corners.iter().all(|chunk| square.contains(chunk)));
+        assert!(corners.iter().all(|chunk| !circle.contains(chunk)));
+    }
 }
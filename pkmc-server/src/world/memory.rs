@@ -0,0 +1,354 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use pkmc_defs::{
+    biome::Biome,
+    block::Block,
+    generated::{
+        generated, PALETTED_DATA_BIOMES_DIRECT, PALETTED_DATA_BIOMES_INDIRECT,
+        PALETTED_DATA_BLOCKS_DIRECT, PALETTED_DATA_BLOCKS_INDIRECT,
+    },
+    packet,
+};
+use pkmc_util::{
+    nbt_compound,
+    packet::{to_paletted_data, ConnectionError, ConnectionSender},
+    IdTable, Position,
+};
+use thiserror::Error;
+
+use super::{
+    chunk_loader::{ChunkLoader, ChunkPosition},
+    section_pos_biome_index, section_pos_block_index, Dimension, World, WorldBlock, WorldViewer,
+    WorldViewers, CHUNK_SIZE, SECTION_BIOMES, SECTION_BIOMES_SIZE, SECTION_BLOCKS, SECTION_SIZE,
+};
+
+#[derive(Error, Debug)]
+pub enum MemoryWorldError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    ConnectionError(#[from] ConnectionError),
+    #[error("Block {0:?} doesn't have an id")]
+    UnknownBlock(Block),
+    #[error("Biome {0:?} doesn't have an id")]
+    UnknownBiome(Biome),
+}
+
+#[derive(Debug)]
+struct MemoryChunk {
+    /// Block ids for each section, bottom to top.
+    sections: Box<[Box<[i32]>]>,
+    /// Biome ids for each section, bottom to top.
+    biomes: Box<[Box<[i32]>]>,
+}
+
+impl MemoryChunk {
+    fn new(num_sections: usize, biome: i32) -> Self {
+        Self {
+            sections: (0..num_sections)
+                .map(|_| vec![Block::air().id().unwrap(); SECTION_BLOCKS].into_boxed_slice())
+                .collect(),
+            biomes: (0..num_sections)
+                .map(|_| vec![biome; SECTION_BIOMES].into_boxed_slice())
+                .collect(),
+        }
+    }
+
+    fn write(&self, mut writer: impl Write) -> Result<(), MemoryWorldError> {
+        self.sections
+            .iter()
+            .zip(self.biomes.iter())
+            .try_for_each(|(section, biomes)| {
+                let block_count = section
+                    .iter()
+                    .filter(|id| !generated::block::is_air(**id))
+                    .count();
+                writer.write_all(&(block_count as u16).to_be_bytes())?;
+                writer.write_all(&to_paletted_data(
+                    section,
+                    PALETTED_DATA_BLOCKS_INDIRECT,
+                    PALETTED_DATA_BLOCKS_DIRECT,
+                )?)?;
+                writer.write_all(&to_paletted_data(
+                    biomes,
+                    PALETTED_DATA_BIOMES_INDIRECT,
+                    PALETTED_DATA_BIOMES_DIRECT,
+                )?)?;
+                Ok(())
+            })
+    }
+}
+
+/// World that is only stored in memory, every chunk starts out as air.
+///
+/// Useful for tests & temporary worlds, block entities are stored as just their block.
+#[derive(Debug)]
+pub struct MemoryWorld {
+    dimension: Dimension,
+    section_y_range: std::ops::RangeInclusive<i8>,
+    chunks: HashMap<ChunkPosition, MemoryChunk>,
+    changed: HashSet<ChunkPosition>,
+    viewers: WorldViewers,
+    biome_mapper: IdTable<Biome>,
+}
+
+impl MemoryWorld {
+    pub fn new(identifier: &str, section_y_range: std::ops::RangeInclusive<i8>) -> Self {
+        Self {
+            dimension: Dimension::new(identifier),
+            section_y_range,
+            chunks: HashMap::new(),
+            changed: HashSet::new(),
+            viewers: WorldViewers::new(),
+            biome_mapper: [(Biome::default(), 0)].into_iter().collect(),
+        }
+    }
+
+    /// Biome ids used in chunk packets, must include [`Biome::default`] which chunks start out
+    /// as.
+    pub fn with_biome_mapper(mut self, biome_mapper: IdTable<Biome>) -> Self {
+        self.biome_mapper = biome_mapper;
+        self
+    }
+
+    fn new_chunk(&self) -> MemoryChunk {
+        MemoryChunk::new(
+            self.num_sections(),
+            Biome::default().id(&self.biome_mapper).unwrap_or(0),
+        )
+    }
+
+    fn num_sections(&self) -> usize {
+        self.section_y_range.clone().count()
+    }
+
+    /// Chunk position & index of section, or None if out of the world's height.
+    fn locate(&self, position: Position) -> Option<(ChunkPosition, usize, (u8, u8, u8))> {
+        let section_y = position.y.div_euclid(SECTION_SIZE as i16);
+        if section_y < *self.section_y_range.start() as i16
+            || section_y > *self.section_y_range.end() as i16
+        {
+            return None;
+        }
+        Some((
+            ChunkPosition::new(
+                position.x.div_euclid(CHUNK_SIZE as i32),
+                position.z.div_euclid(CHUNK_SIZE as i32),
+            ),
+            (section_y - *self.section_y_range.start() as i16) as usize,
+            (
+                position.x.rem_euclid(CHUNK_SIZE as i32) as u8,
+                position.y.rem_euclid(SECTION_SIZE as i16) as u8,
+                position.z.rem_euclid(CHUNK_SIZE as i32) as u8,
+            ),
+        ))
+    }
+
+    fn chunk_packet(
+        &self,
+        position: ChunkPosition,
+    ) -> Result<packet::play::LevelChunkWithLight, MemoryWorldError> {
+        let mut data = Vec::new();
+        match self.chunks.get(&position) {
+            Some(chunk) => chunk.write(&mut data)?,
+            None => self.new_chunk().write(&mut data)?,
+        }
+        Ok(packet::play::LevelChunkWithLight {
+            chunk_x: position.chunk_x,
+            chunk_z: position.chunk_z,
+            chunk_data: packet::play::LevelChunkData {
+                heightmaps: nbt_compound!(),
+                data: data.into_boxed_slice(),
+                block_entities: Vec::new(),
+            },
+            light_data: packet::play::LevelLightData::full_bright(self.num_sections()),
+        })
+    }
+}
+
+impl World for MemoryWorld {
+    type Error = MemoryWorldError;
+
+    fn add_viewer(&mut self, connection: ConnectionSender) -> Arc<Mutex<WorldViewer>> {
+        self.viewers.add(connection, ChunkLoader::new(6))
+    }
+
+    fn update_viewers(&mut self) -> Result<(), Self::Error> {
+        let viewers = self.viewers.upgrade();
+
+        // Changes just resend the whole chunk.
+        self.changed.drain().for_each(|chunk_position| {
+            viewers
+                .iter()
+                .map(|viewer| viewer.lock().unwrap())
+                .for_each(|mut viewer| viewer.loader.force_reload(chunk_position));
+        });
+
+        viewers
+            .iter()
+            .map(|viewer| viewer.lock().unwrap())
+            .try_for_each(|mut viewer| {
+                viewer.update_center()?;
+
+                if let Some(to_load) = viewer.loader.next_to_load() {
+                    viewer.connection().send(&self.chunk_packet(to_load)?)?;
+                }
+
+                Ok::<(), Self::Error>(())
+            })?;
+
+        Ok(())
+    }
+
+    fn get_block(&mut self, position: Position) -> Result<Option<WorldBlock>, Self::Error> {
+        let Some((chunk_position, section, (x, y, z))) = self.locate(position) else {
+            return Ok(None);
+        };
+        let Some(chunk) = self.chunks.get(&chunk_position) else {
+            return Ok(Some(WorldBlock::Block(Block::air())));
+        };
+        Ok(
+            Block::from_id(chunk.sections[section][section_pos_block_index(x, y, z)])
+                .map(WorldBlock::Block),
+        )
+    }
+
+    fn set_block(&mut self, position: Position, block: WorldBlock) -> Result<(), Self::Error> {
+        let Some((chunk_position, section, (x, y, z))) = self.locate(position) else {
+            return Ok(());
+        };
+        let block = block.into_block();
+        let Some(id) = block.id_with_default_fallback() else {
+            return Err(MemoryWorldError::UnknownBlock(block));
+        };
+        let new_chunk = self.new_chunk();
+        let chunk = self.chunks.entry(chunk_position).or_insert(new_chunk);
+        let current = &mut chunk.sections[section][section_pos_block_index(x, y, z)];
+        if *current != id {
+            *current = id;
+            self.changed.insert(chunk_position);
+        }
+        Ok(())
+    }
+
+    fn set_biome(&mut self, position: Position, biome: Biome) -> Result<(), Self::Error> {
+        let Some((chunk_position, section, (x, y, z))) = self.locate(position) else {
+            return Ok(());
+        };
+        let Some(id) = biome.id(&self.biome_mapper) else {
+            return Err(MemoryWorldError::UnknownBiome(biome));
+        };
+        let new_chunk = self.new_chunk();
+        let chunk = self.chunks.entry(chunk_position).or_insert(new_chunk);
+        let current = &mut chunk.biomes[section][section_pos_biome_index(
+            x / SECTION_BIOMES_SIZE as u8,
+            y / SECTION_BIOMES_SIZE as u8,
+            z / SECTION_BIOMES_SIZE as u8,
+        )];
+        if *current != id {
+            *current = id;
+            self.changed.insert(chunk_position);
+        }
+        Ok(())
+    }
+
+    fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+        self.chunks.keys().copied().collect()
+    }
+
+    fn keep_loaded(&mut self, _chunks: &[ChunkPosition]) -> Result<(), Self::Error> {
+        // Every chunk is always in memory.
+        Ok(())
+    }
+
+    fn dimension(&self) -> &Dimension {
+        &self.dimension
+    }
+
+    fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
+        self.section_y_range.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pkmc_defs::{biome::Biome, block::Block, packet};
+    use pkmc_util::{
+        packet::ClientboundPacket as _,
+        test_util::{connection_pair, recieve_all},
+        Position, Vec3,
+    };
+
+    use crate::world::{
+        chunk_loader::ChunkPosition,
+        memory::{MemoryWorld, MemoryWorldError},
+        World as _, WorldBlock,
+    };
+
+    #[test]
+    fn test_memory_world() -> Result<(), MemoryWorldError> {
+        let mut world = MemoryWorld::new("overworld", -4..=19);
+
+        let stone = WorldBlock::Block(Block::new("minecraft:stone"));
+        assert_eq!(
+            world.get_block(Position::new(3, 10, -20))?,
+            Some(WorldBlock::Block(Block::air()))
+        );
+        world.set_block(Position::new(3, 10, -20), stone.clone())?;
+        assert_eq!(world.get_block(Position::new(3, 10, -20))?, Some(stone));
+        assert_eq!(
+            world.get_block(Position::new(3, 11, -20))?,
+            Some(WorldBlock::Block(Block::air()))
+        );
+        assert_eq!(world.loaded_chunks(), vec![ChunkPosition::new(0, -2)]);
+
+        // Outside of the world's height.
+        assert_eq!(world.get_block(Position::new(0, 1000, 0))?, None);
+        assert!(world
+            .set_block(
+                Position::new(0, 0, 0),
+                WorldBlock::Block(Block::new("minecraft:not_a_block"))
+            )
+            .is_err());
+
+        let (server, mut client) = connection_pair()?;
+
+        let viewer = world.add_viewer(server.sender());
+        viewer.lock().unwrap().position = Vec3::new(3.0, 10.0, -20.0);
+        world.update_viewers()?;
+
+        let chunks = recieve_all(&server.sender(), &mut client)?
+            .iter()
+            .filter(|raw| raw.id == packet::play::LevelChunkWithLight::CLIENTBOUND_ID)
+            .count();
+        assert_eq!(chunks, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_world_biomes() -> Result<(), MemoryWorldError> {
+        let plains = Biome::new("minecraft:plains");
+        let mut world = MemoryWorld::new("overworld", -4..=19);
+        assert!(world
+            .set_biome(Position::new(0, 0, 0), plains.clone())
+            .is_err());
+
+        let mut world = MemoryWorld::new("overworld", -4..=19).with_biome_mapper(
+            [(Biome::default(), 0), (plains.clone(), 1)]
+                .into_iter()
+                .collect(),
+        );
+        world.set_biome(Position::new(0, 0, 0), plains.clone())?;
+        // Outside of the world.
+        world.set_biome(Position::new(0, 1000, 0), plains)?;
+        assert_eq!(world.loaded_chunks(), vec![ChunkPosition::new(0, 0)]);
+        assert_eq!(world.chunks[&ChunkPosition::new(0, 0)].biomes[4][0], 1);
+
+        Ok(())
+    }
+}
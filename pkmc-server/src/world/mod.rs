@@ -1,14 +1,24 @@
 use std::{
     fmt::Debug,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
 };
 
-use chunk_loader::ChunkLoader;
-use pkmc_defs::block::{Block, BlockEntity};
-use pkmc_util::{packet::ConnectionSender, Position, Vec3};
+use chunk_loader::{ChunkLoader, ChunkPosition};
+use pkmc_defs::{
+    biome::Biome,
+    block::{Block, BlockEntity},
+    packet,
+};
+use pkmc_util::{
+    normalize_identifier,
+    packet::{ConnectionError, ConnectionSender},
+    Position, Vec3,
+};
+use thiserror::Error;
 
 pub mod anvil;
 pub mod chunk_loader;
+pub mod memory;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const SECTION_SIZE: usize = 16;
@@ -16,6 +26,70 @@ pub const SECTION_BLOCKS: usize = 4096;
 pub const SECTION_BIOMES_SIZE: usize = 4;
 pub const SECTION_BIOMES: usize = 64;
 
+/// Index into a section's blocks, YZX order.
+pub fn section_pos_block_index(x: u8, y: u8, z: u8) -> usize {
+    debug_assert!((x as usize) < SECTION_SIZE);
+    debug_assert!((y as usize) < SECTION_SIZE);
+    debug_assert!((z as usize) < SECTION_SIZE);
+    (y as usize) * SECTION_SIZE * SECTION_SIZE + (z as usize) * SECTION_SIZE + (x as usize)
+}
+
+/// Inverse of [`section_pos_block_index`].
+pub fn section_block_index_pos(index: usize) -> (u8, u8, u8) {
+    debug_assert!(index < SECTION_BLOCKS);
+    (
+        (index % SECTION_SIZE) as u8,
+        (index / (SECTION_SIZE * SECTION_SIZE)) as u8,
+        ((index / SECTION_SIZE) % SECTION_SIZE) as u8,
+    )
+}
+
+/// Index into a section's biomes, biomes are stored in 4x4x4 cells, YZX order.
+pub fn section_pos_biome_index(x: u8, y: u8, z: u8) -> usize {
+    debug_assert!((x as usize) < SECTION_BIOMES_SIZE);
+    debug_assert!((y as usize) < SECTION_BIOMES_SIZE);
+    debug_assert!((z as usize) < SECTION_BIOMES_SIZE);
+    (y as usize) * SECTION_BIOMES_SIZE * SECTION_BIOMES_SIZE
+        + (z as usize) * SECTION_BIOMES_SIZE
+        + (x as usize)
+}
+
+/// Inverse of [`section_pos_biome_index`].
+pub fn section_biome_index_pos(index: usize) -> (u8, u8, u8) {
+    debug_assert!(index < SECTION_BIOMES);
+    (
+        (index % SECTION_BIOMES_SIZE) as u8,
+        (index / (SECTION_BIOMES_SIZE * SECTION_BIOMES_SIZE)) as u8,
+        ((index / SECTION_BIOMES_SIZE) % SECTION_BIOMES_SIZE) as u8,
+    )
+}
+
+/// Dimension identifier, "minecraft:" namespace is optional.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dimension(String);
+
+impl Dimension {
+    pub fn new(name: &str) -> Self {
+        Self(normalize_identifier(name, "minecraft"))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Dimension {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct WorldViewer {
     id: usize,
@@ -25,6 +99,15 @@ pub struct WorldViewer {
 }
 
 impl WorldViewer {
+    pub fn new(id: usize, connection: ConnectionSender, loader: ChunkLoader) -> Self {
+        Self {
+            id,
+            connection,
+            loader,
+            position: Vec3::new(0.0, 100.0, 0.0),
+        }
+    }
+
     pub fn id(&self) -> usize {
         self.id
     }
@@ -32,6 +115,59 @@ impl WorldViewer {
     pub fn connection(&self) -> &ConnectionSender {
         &self.connection
     }
+
+    /// Sends the new chunk cache center if the viewer moved to another chunk, and forgets
+    /// chunks that are now out of range.
+    pub fn update_center(&mut self) -> Result<(), ConnectionError> {
+        let center = ChunkPosition::from_world_position(self.position);
+        if self.loader.update_center(Some(center)) {
+            self.connection.send(&packet::play::SetChunkCacheCenter {
+                chunk_x: center.chunk_x,
+                chunk_z: center.chunk_z,
+            })?;
+        }
+        while let Some(to_unload) = self.loader.next_to_unload() {
+            self.connection.send(&packet::play::ForgetLevelChunk {
+                chunk_x: to_unload.chunk_x,
+                chunk_z: to_unload.chunk_z,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Viewers of a [`World`], only weakly referenced so that dropped viewers are removed.
+#[derive(Debug, Default)]
+pub struct WorldViewers {
+    viewers: Vec<Weak<Mutex<WorldViewer>>>,
+    next_id: usize,
+}
+
+impl WorldViewers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(
+        &mut self,
+        connection: ConnectionSender,
+        loader: ChunkLoader,
+    ) -> Arc<Mutex<WorldViewer>> {
+        let viewer = Arc::new(Mutex::new(WorldViewer::new(
+            self.next_id,
+            connection,
+            loader,
+        )));
+        self.next_id += 1;
+        self.viewers.push(Arc::downgrade(&viewer));
+        viewer
+    }
+
+    /// Viewers that still exist.
+    pub fn upgrade(&mut self) -> Vec<Arc<Mutex<WorldViewer>>> {
+        self.viewers.retain(|v| v.strong_count() > 0);
+        self.viewers.iter().flat_map(|v| v.upgrade()).collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -71,4 +207,184 @@ pub trait World: Debug {
 
     fn get_block(&mut self, position: Position) -> Result<Option<WorldBlock>, Self::Error>;
     fn set_block(&mut self, position: Position, block: WorldBlock) -> Result<(), Self::Error>;
+    /// Sets the biome of the 4x4x4 cell that contains the position, viewers get the whole chunk
+    /// resent as there's no packet to update just biomes.
+    fn set_biome(&mut self, position: Position, biome: Biome) -> Result<(), Self::Error>;
+
+    /// Positions of all chunks that are currently loaded & exist.
+    fn loaded_chunks(&self) -> Vec<ChunkPosition>;
+    /// Loads chunks & keeps them loaded, even if nobody is viewing them.
+    fn keep_loaded(&mut self, chunks: &[ChunkPosition]) -> Result<(), Self::Error>;
+
+    /// Writes all changed chunks to disk, worlds that don't persist anything do nothing.
+    fn save(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// If there's any changes that [`World::save`] would write.
+    fn has_unsaved_changes(&self) -> bool {
+        false
+    }
+
+    fn dimension(&self) -> &Dimension;
+
+    /// Sections that blocks can be in, from bottom to top.
+    fn section_y_range(&self) -> std::ops::RangeInclusive<i8>;
+}
+
+/// Error of a [`DynWorld`], the original error can be retrieved with [`DynWorldError::into_inner`].
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct DynWorldError(Box<dyn std::error::Error + Send + Sync>);
+
+impl DynWorldError {
+    pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync> {
+        self.0
+    }
+}
+
+/// Any [`World`] implementation, so that different kinds of worlds may be used interchangeably.
+pub type DynWorld = Box<dyn World<Error = DynWorldError> + Send>;
+
+/// Adapter that erases the error type of a [`World`], see [`ErasedWorld::boxed`].
+#[derive(Debug)]
+pub struct ErasedWorld<W: World>(pub W);
+
+impl<W> ErasedWorld<W>
+where
+    W: World + Send + 'static,
+    W::Error: Send + Sync + 'static,
+{
+    pub fn boxed(world: W) -> DynWorld {
+        Box::new(Self(world))
+    }
+}
+
+impl<W> World for ErasedWorld<W>
+where
+    W: World,
+    W::Error: Send + Sync + 'static,
+{
+    type Error = DynWorldError;
+
+    fn add_viewer(&mut self, connection: ConnectionSender) -> Arc<Mutex<WorldViewer>> {
+        self.0.add_viewer(connection)
+    }
+
+    fn update_viewers(&mut self) -> Result<(), Self::Error> {
+        self.0
+            .update_viewers()
+            .map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn get_block(&mut self, position: Position) -> Result<Option<WorldBlock>, Self::Error> {
+        self.0
+            .get_block(position)
+            .map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn set_block(&mut self, position: Position, block: WorldBlock) -> Result<(), Self::Error> {
+        self.0
+            .set_block(position, block)
+            .map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn set_biome(&mut self, position: Position, biome: Biome) -> Result<(), Self::Error> {
+        self.0
+            .set_biome(position, biome)
+            .map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+        self.0.loaded_chunks()
+    }
+
+    fn keep_loaded(&mut self, chunks: &[ChunkPosition]) -> Result<(), Self::Error> {
+        self.0
+            .keep_loaded(chunks)
+            .map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn save(&mut self) -> Result<(), Self::Error> {
+        self.0.save().map_err(|e| DynWorldError(Box::new(e)))
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        self.0.has_unsaved_changes()
+    }
+
+    fn dimension(&self) -> &Dimension {
+        self.0.dimension()
+    }
+
+    fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
+        self.0.section_y_range()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::world::{
+        anvil::AnvilWorld, section_biome_index_pos, section_block_index_pos,
+        section_pos_biome_index, section_pos_block_index, Dimension, World as _, SECTION_BIOMES,
+        SECTION_BIOMES_SIZE, SECTION_BLOCKS, SECTION_SIZE,
+    };
+
+    #[test]
+    fn test_dimension_normalized() {
+        assert_eq!(
+            Dimension::new("overworld"),
+            Dimension::new("minecraft:overworld")
+        );
+        assert_ne!(Dimension::new("overworld"), Dimension::new("the_nether"));
+
+        let mut worlds = HashMap::new();
+        worlds.insert(
+            Dimension::new("overworld"),
+            AnvilWorld::new(
+                "./nonexistent-world/",
+                "overworld",
+                -4..=19,
+                Default::default(),
+            ),
+        );
+        let world = worlds.get(&Dimension::new("minecraft:overworld")).unwrap();
+        assert_eq!(world.identifier(), "minecraft:overworld");
+        assert_eq!(world.dimension(), &Dimension::new("overworld"));
+    }
+
+    #[test]
+    fn test_section_index_round_trip() {
+        for index in 0..SECTION_BLOCKS {
+            let (x, y, z) = section_block_index_pos(index);
+            assert_eq!(section_pos_block_index(x, y, z), index);
+        }
+        for y in 0..SECTION_SIZE as u8 {
+            for z in 0..SECTION_SIZE as u8 {
+                for x in 0..SECTION_SIZE as u8 {
+                    let index = section_pos_block_index(x, y, z);
+                    assert!(index < SECTION_BLOCKS);
+                    assert_eq!(section_block_index_pos(index), (x, y, z));
+                }
+            }
+        }
+
+        for index in 0..SECTION_BIOMES {
+            let (x, y, z) = section_biome_index_pos(index);
+            assert_eq!(section_pos_biome_index(x, y, z), index);
+        }
+        for y in 0..SECTION_BIOMES_SIZE as u8 {
+            for z in 0..SECTION_BIOMES_SIZE as u8 {
+                for x in 0..SECTION_BIOMES_SIZE as u8 {
+                    let index = section_pos_biome_index(x, y, z);
+                    assert!(index < SECTION_BIOMES);
+                    assert_eq!(section_biome_index_pos(index), (x, y, z));
+                }
+            }
+        }
+
+        assert_eq!(section_pos_block_index(1, 2, 3), 2 * 256 + 3 * 16 + 1);
+        assert_eq!(section_pos_biome_index(1, 2, 3), 2 * 16 + 3 * 4 + 1);
+    }
 }
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use pkmc_defs::packet;
+
+/// Largest diameter the world border can have.
+pub const MAX_WORLD_BORDER_SIZE: f64 = 59_999_968.0;
+const PORTAL_TELEPORT_BOUNDARY: i32 = 29_999_984;
+
+/// Server side world border, [`WorldBorder::size`] follows the same lerp as the client does.
+#[derive(Debug)]
+pub struct WorldBorder {
+    center_x: f64,
+    center_z: f64,
+    old_size: f64,
+    new_size: f64,
+    lerp_start: Instant,
+    lerp_duration: Duration,
+    /// In blocks.
+    warning_distance: i32,
+    /// In seconds.
+    warning_time: i32,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center_x: 0.0,
+            center_z: 0.0,
+            old_size: MAX_WORLD_BORDER_SIZE,
+            new_size: MAX_WORLD_BORDER_SIZE,
+            lerp_start: Instant::now(),
+            lerp_duration: Duration::ZERO,
+            warning_distance: 5,
+            warning_time: 15,
+        }
+    }
+}
+
+impl WorldBorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.center_x, self.center_z)
+    }
+
+    pub fn set_center(&mut self, x: f64, z: f64) {
+        self.center_x = x;
+        self.center_z = z;
+    }
+
+    fn remaining(&self) -> Duration {
+        self.lerp_duration.saturating_sub(self.lerp_start.elapsed())
+    }
+
+    /// Current diameter, this is in between sizes while the border is moving.
+    pub fn size(&self) -> f64 {
+        if self.remaining().is_zero() {
+            return self.new_size;
+        }
+        let t = self.lerp_start.elapsed().as_secs_f64() / self.lerp_duration.as_secs_f64();
+        self.old_size + (self.new_size - self.old_size) * t
+    }
+
+    /// Diameter the border is moving towards.
+    pub fn target_size(&self) -> f64 {
+        self.new_size
+    }
+
+    /// Moves the border from its current size to `size` over `duration`.
+    pub fn set_size(&mut self, size: f64, duration: Duration) {
+        self.old_size = self.size();
+        self.new_size = size;
+        self.lerp_start = Instant::now();
+        self.lerp_duration = duration;
+    }
+
+    pub fn warning_distance(&self) -> i32 {
+        self.warning_distance
+    }
+
+    pub fn set_warning_distance(&mut self, blocks: i32) {
+        self.warning_distance = blocks;
+    }
+
+    pub fn warning_time(&self) -> i32 {
+        self.warning_time
+    }
+
+    pub fn set_warning_time(&mut self, seconds: i32) {
+        self.warning_time = seconds;
+    }
+
+    /// Packet with the full border state, for players that are joining.
+    pub fn init_packet(&self) -> packet::play::InitializeBorder {
+        packet::play::InitializeBorder {
+            x: self.center_x,
+            z: self.center_z,
+            old_diameter: self.size(),
+            new_diameter: self.new_size,
+            speed: self.remaining().as_millis() as i64,
+            portal_teleport_boundary: PORTAL_TELEPORT_BOUNDARY,
+            warning_distance: self.warning_distance,
+            warning_time: self.warning_time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::world_border::{WorldBorder, MAX_WORLD_BORDER_SIZE};
+
+    #[test]
+    fn test_size_lerp() {
+        let mut border = WorldBorder::new();
+        assert_eq!(border.size(), MAX_WORLD_BORDER_SIZE);
+
+        border.set_size(100.0, Duration::ZERO);
+        assert_eq!(border.size(), 100.0);
+        assert_eq!(border.init_packet().speed, 0);
+
+        border.set_size(200.0, Duration::from_secs(100));
+        assert!(border.size() >= 100.0 && border.size() < 200.0);
+        assert_eq!(border.target_size(), 200.0);
+        let init = border.init_packet();
+        assert_eq!(init.new_diameter, 200.0);
+        assert!(init.speed > 99_000 && init.speed <= 100_000);
+    }
+}
@@ -0,0 +1,80 @@
+//! Run with `cargo bench -p pkmc-util`.
+
+use std::hint::black_box;
+
+use pkmc_util::{
+    packet::{to_paletted_data, to_paletted_data_precomputed},
+    PackedArray,
+};
+
+const SECTION_BLOCKS: usize = 4096;
+const BLOCKS_INDIRECT: std::ops::RangeInclusive<u32> = 4..=8;
+const BLOCKS_DIRECT: u32 = 15;
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    // Warmup
+    (0..iterations / 10 + 1).for_each(|_| f());
+    let start = std::time::Instant::now();
+    (0..iterations).for_each(|_| f());
+    let elapsed = start.elapsed();
+    println!(
+        "{:<48} {:>10.2}us/iter ({} iterations)",
+        name,
+        elapsed.as_secs_f64() * 1_000_000.0 / iterations as f64,
+        iterations
+    );
+}
+
+/// Section of `palette_size` different values, in a repeating pattern.
+fn section(palette_size: usize) -> Vec<i32> {
+    (0..SECTION_BLOCKS)
+        .map(|i| ((i * 7) % palette_size) as i32 * 3)
+        .collect()
+}
+
+fn main() {
+    for palette_size in [1, 2, 16, 200] {
+        let values = section(palette_size);
+        bench(
+            &format!("to_paletted_data ({} values)", palette_size),
+            2000,
+            || {
+                black_box(
+                    to_paletted_data(black_box(&values), BLOCKS_INDIRECT, BLOCKS_DIRECT).unwrap(),
+                );
+            },
+        );
+    }
+
+    for palette_size in [2, 16, 200] {
+        let values = section(palette_size);
+        let palette = (0..palette_size as i32).map(|i| i * 3).collect::<Vec<_>>();
+        let bpe = PackedArray::bits_per_entry(palette_size as u64 - 1).max(4);
+        let indices = values.iter().map(|v| (*v / 3) as u64).collect::<Vec<_>>();
+        let packed = PackedArray::from_indices(&indices, bpe)
+            .into_inner()
+            .iter()
+            .map(|l| *l as i64)
+            .collect::<Vec<_>>();
+        bench(
+            &format!("to_paletted_data_precomputed ({} values)", palette_size),
+            2000,
+            || {
+                black_box(
+                    to_paletted_data_precomputed(
+                        black_box(&palette),
+                        black_box(&packed),
+                        BLOCKS_INDIRECT,
+                        BLOCKS_DIRECT,
+                    )
+                    .unwrap(),
+                );
+            },
+        );
+    }
+
+    let mut packed = PackedArray::new(8, SECTION_BLOCKS);
+    bench("PackedArray::set (whole section)", 2000, || {
+        (0..SECTION_BLOCKS).for_each(|i| packed.set(i, black_box(i as u64 & 0xFF)));
+    });
+}
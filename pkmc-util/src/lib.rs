@@ -6,6 +6,8 @@ mod packed_array;
 pub mod packet;
 mod position;
 mod read_ext;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 mod transmutable;
 mod uuid;
 mod vec3;
@@ -26,3 +28,36 @@ pub fn normalize_identifier(identifier: &str, default_namespace: &str) -> String
     }
     format!("{}:{}", default_namespace, identifier)
 }
+
+/// If the identifier is a valid "namespace:path", namespace can only contain [a-z0-9._-], and
+/// path can also contain "/".
+pub fn is_valid_identifier(identifier: &str) -> bool {
+    let Some((namespace, path)) = identifier.split_once(':') else {
+        return false;
+    };
+    let valid = |c: char, extra: &str| {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c) || extra.contains(c)
+    };
+    !namespace.is_empty()
+        && !path.is_empty()
+        && namespace.chars().all(|c| valid(c, ""))
+        && path.chars().all(|c| valid(c, "/"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::is_valid_identifier;
+
+    #[test]
+    fn test_is_valid_identifier() {
+        assert!(is_valid_identifier("minecraft:brand"));
+        assert!(is_valid_identifier("bungeecord:main"));
+        assert!(is_valid_identifier("my_mod:some/path-1.2"));
+        assert!(!is_valid_identifier("no_namespace"));
+        assert!(!is_valid_identifier("Upper:case"));
+        assert!(!is_valid_identifier("name/space:path"));
+        assert!(!is_valid_identifier(":path"));
+        assert!(!is_valid_identifier("namespace:"));
+        assert!(!is_valid_identifier("a:b:c"));
+    }
+}
@@ -0,0 +1,227 @@
+use super::NBT;
+
+/// Build an [`NBT`] value, using the SNBT type suffixes.
+///
+/// - Numbers: `1b`, `1s`, `1i`/`1`, `1l`, `1.5f`, `1.5d`/`1.5`, and `true`/`false` as bytes.
+/// - Strings: `"text"`.
+/// - Arrays: `[0, 1, 2]b`, `[0, 1, 2]i`, `[0, 1, 2]l`.
+/// - Lists: `[1b, 2b]`, every value must be the same type.
+/// - Compounds: `{ "key": value, ... }`.
+///
+/// Any other expression is converted with [`NBT::from`].
+///
+/// ```
+/// # use pkmc_util::{nbt, nbt::NBT};
+/// let nbt = nbt!({ "name": "Eggbert", "value": 0.5f, "data": [0, 1, 2]i });
+/// ```
+#[macro_export]
+macro_rules! nbt {
+    // Compound entries, munches the value of each key until the next comma.
+    (@compound [$(($key:expr, $value:expr),)*]) => {
+        $crate::nbt::NBT::Compound(
+            vec![$(($key.to_string(), $value),)*]
+                .into_iter()
+                .collect::<std::collections::HashMap<String, $crate::nbt::NBT>>(),
+        )
+    };
+    (@compound [$($done:tt)*] $key:literal : $($rest:tt)*) => {
+        $crate::nbt!(@compound_value [$($done)*] [$key] [] $($rest)*)
+    };
+    (@compound_value [$($done:tt)*] [$key:literal] [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::nbt!(@compound [$($done)* ($key, $crate::nbt!($($value)+)),] $($rest)*)
+    };
+    (@compound_value [$($done:tt)*] [$key:literal] [$($value:tt)+]) => {
+        $crate::nbt!(@compound [$($done)* ($key, $crate::nbt!($($value)+)),])
+    };
+    (@compound_value [$($done:tt)*] [$key:literal] [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::nbt!(@compound_value [$($done)*] [$key] [$($value)* $next] $($rest)*)
+    };
+
+    // List values, same as compound values.
+    (@list [$($done:expr,)*] []) => {
+        $crate::nbt::__nbt_list(vec![$($done,)*])
+    };
+    (@list [$($done:expr,)*] [$($value:tt)+]) => {
+        $crate::nbt::__nbt_list(vec![$($done,)* $crate::nbt!($($value)+)])
+    };
+    (@list [$($done:expr,)*] [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::nbt!(@list [$($done,)* $crate::nbt!($($value)+),] [] $($rest)*)
+    };
+    (@list [$($done:expr,)*] [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::nbt!(@list [$($done,)*] [$($value)* $next] $($rest)*)
+    };
+
+    ({ $($tt:tt)* }) => {
+        $crate::nbt!(@compound [] $($tt)*)
+    };
+    ([ $($tt:tt)* ] b) => {
+        $crate::nbt::NBT::ByteArray(Vec::<i8>::from([$($tt)*]).into_boxed_slice())
+    };
+    ([ $($tt:tt)* ] i) => {
+        $crate::nbt::NBT::IntArray(Vec::<i32>::from([$($tt)*]).into_boxed_slice())
+    };
+    ([ $($tt:tt)* ] l) => {
+        $crate::nbt::NBT::LongArray(Vec::<i64>::from([$($tt)*]).into_boxed_slice())
+    };
+    ([ $($tt:tt)* ]) => {
+        $crate::nbt!(@list [] [] $($tt)*)
+    };
+    (- $value:literal) => {
+        $crate::nbt::__nbt_literal(concat!("-", stringify!($value)))
+    };
+    ($value:literal) => {
+        $crate::nbt::__nbt_literal(stringify!($value))
+    };
+    ($value:expr) => {
+        $crate::nbt::NBT::from($value)
+    };
+}
+
+/// Parses a literal passed to [`nbt!`], panics if it isn't valid.
+#[doc(hidden)]
+pub fn __nbt_literal(literal: &str) -> NBT {
+    match literal {
+        "true" => return NBT::Byte(1),
+        "false" => return NBT::Byte(0),
+        _ => {}
+    }
+    if literal.ends_with('"') {
+        return NBT::String(unescape_string_literal(literal).unwrap_or_else(|| {
+            panic!("nbt!: invalid string literal {}", literal);
+        }));
+    }
+
+    const SUFFIXES: [&str; 18] = [
+        "i8", "i16", "i32", "i64", "f32", "f64", "b", "B", "s", "S", "i", "I", "l", "L", "f", "F",
+        "d", "D",
+    ];
+    let (number, suffix) = SUFFIXES
+        .iter()
+        .find_map(|suffix| Some((literal.strip_suffix(suffix)?, *suffix)))
+        .unwrap_or((literal, ""));
+    let number = number.replace('_', "");
+    let is_float = number.contains(['.', 'e', 'E']);
+    let parsed = match suffix.to_ascii_lowercase().as_str() {
+        "b" | "i8" => number.parse().ok().map(NBT::Byte),
+        "s" | "i16" => number.parse().ok().map(NBT::Short),
+        "i" | "i32" => number.parse().ok().map(NBT::Int),
+        "" if !is_float => number.parse().ok().map(NBT::Int),
+        "l" | "i64" => number.parse().ok().map(NBT::Long),
+        "f" | "f32" => number.parse().ok().map(NBT::Float),
+        "d" | "f64" | "" => number.parse().ok().map(NBT::Double),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| panic!("nbt!: invalid number literal {}", literal))
+}
+
+/// Contents of a (possibly raw) Rust string literal.
+fn unescape_string_literal(literal: &str) -> Option<String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Some(raw.get(hashes + 1..raw.len() - hashes - 1)?.to_owned());
+    }
+    let mut chars = literal.strip_prefix('"')?.strip_suffix('"')?.chars();
+    let mut string = String::new();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            string.push(char);
+            continue;
+        }
+        string.push(match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            char @ ('\\' | '"' | '\'') => char,
+            _ => return None,
+        });
+    }
+    Some(string)
+}
+
+/// List for [`nbt!`], panics if the values aren't all the same type.
+#[doc(hidden)]
+pub fn __nbt_list(values: Vec<NBT>) -> NBT {
+    if let Some(first) = values.first() {
+        if let Some(other) = values.iter().find(|value| value.tag() != first.tag()) {
+            panic!(
+                "nbt!: list values must be the same type, got {:?} & {:?}",
+                first.tag(),
+                other.tag()
+            );
+        }
+    }
+    NBT::List(values)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{nbt::NBT, nbt_compound};
+
+    #[test]
+    fn test_nbt_macro() {
+        assert_eq!(nbt!(1b), NBT::Byte(1));
+        assert_eq!(nbt!(-1b), NBT::Byte(-1));
+        assert_eq!(nbt!(true), NBT::Byte(1));
+        assert_eq!(nbt!(300s), NBT::Short(300));
+        assert_eq!(nbt!(5), NBT::Int(5));
+        assert_eq!(nbt!(5i), NBT::Int(5));
+        assert_eq!(nbt!(5_000_000_000l), NBT::Long(5_000_000_000));
+        assert_eq!(nbt!(0.5f), NBT::Float(0.5));
+        assert_eq!(nbt!(-0.5d), NBT::Double(-0.5));
+        assert_eq!(nbt!(0.5), NBT::Double(0.5));
+        assert_eq!(
+            nbt!("a \"quoted\" string"),
+            NBT::String("a \"quoted\" string".to_owned())
+        );
+        assert_eq!(nbt!([1, -2, 3]b), NBT::ByteArray(Box::new([1, -2, 3])));
+        assert_eq!(nbt!([1, -2, 3]i), NBT::IntArray(Box::new([1, -2, 3])));
+        assert_eq!(nbt!([1, -2, 3]l), NBT::LongArray(Box::new([1, -2, 3])));
+        assert_eq!(
+            nbt!([1b, -2b]),
+            NBT::List(vec![NBT::Byte(1), NBT::Byte(-2)])
+        );
+        assert_eq!(nbt!([]), NBT::List(Vec::new()));
+        assert_eq!(nbt!({}), nbt_compound![]);
+
+        let name = "Hampus".to_owned();
+        assert_eq!(nbt!(name.clone()), NBT::String(name));
+    }
+
+    #[test]
+    fn test_nbt_macro_nested() {
+        assert_eq!(
+            nbt!({
+                "field": "x",
+                "arr": [0, 1, 2]i,
+                "nested": {
+                    "list": [{ "a": 1b }, { "a": -1b, "b": [[1s], [2s, 3s]] }],
+                    "empty": {},
+                },
+            }),
+            nbt_compound![
+                "field" => NBT::String("x".to_owned()),
+                "arr" => NBT::IntArray(Box::new([0, 1, 2])),
+                "nested" => nbt_compound![
+                    "list" => NBT::List(vec![
+                        nbt_compound!["a" => NBT::Byte(1),],
+                        nbt_compound![
+                            "a" => NBT::Byte(-1),
+                            "b" => NBT::List(vec![
+                                NBT::List(vec![NBT::Short(1)]),
+                                NBT::List(vec![NBT::Short(2), NBT::Short(3)]),
+                            ]),
+                        ],
+                    ]),
+                    "empty" => nbt_compound![],
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nbt!: list values must be the same type")]
+    fn test_nbt_macro_mixed_list() {
+        nbt!([1b, 2s]);
+    }
+}
@@ -1,5 +1,11 @@
 mod de;
+mod macros;
+#[allow(clippy::module_inception)]
 mod nbt;
+mod ser;
+mod snbt;
 
 pub use de::from_nbt;
-pub use nbt::{NBTError, NBT};
+#[doc(hidden)]
+pub use macros::{__nbt_list, __nbt_literal};
+pub use nbt::{infer_nbt_number_type, json_number_to_nbt, NBTError, NBTTag, NBT};
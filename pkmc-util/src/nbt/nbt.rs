@@ -29,10 +29,16 @@ pub enum NBTError {
     JsonCouldntConvert,
     #[error("NBT Json cannot convert number array that contains both ints & floats")]
     JsonMixedIntFloatArray,
+    #[error("NBT invalid SNBT at {0}: {1}")]
+    InvalidSnbt(usize, String),
+    #[error("NBT has {0} unread trailing bytes")]
+    TrailingBytes(usize),
+    #[error("NBT unknown compression, starts with byte {0:#04x}")]
+    UnknownCompression(u8),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum NBTTag {
+pub enum NBTTag {
     End,
     Byte,
     Short,
@@ -135,8 +141,6 @@ impl<T: Into<NBT>> From<Vec<T>> for NBT {
     }
 }
 
-// TODO: More macros for creating NBTs
-
 #[macro_export]
 macro_rules! nbt_compound {
     [$($name:expr => $value:expr,)*] => {
@@ -151,7 +155,7 @@ macro_rules! nbt_compound {
 }
 
 impl NBT {
-    fn tag(&self) -> NBTTag {
+    pub fn tag(&self) -> NBTTag {
         match self {
             NBT::Byte(..) => NBTTag::Byte,
             NBT::Short(..) => NBTTag::Short,
@@ -238,17 +242,44 @@ impl NBT {
         ))
     }
 
+    /// Reads gzip (like level.dat), zlib, or uncompressed NBT, detected by the first bytes.
+    pub fn read_auto(mut data: impl Read) -> Result<(String, NBT), NBTError> {
+        let header: [u8; 2] = data.read_const()?;
+        let data = std::io::Cursor::new(header).chain(data);
+        match header {
+            [0x1F, 0x8B] => NBT::read(flate2::read::GzDecoder::new(data), false),
+            [0x78, _] => NBT::read(flate2::read::ZlibDecoder::new(data), false),
+            [tag, _] if NBTTag::try_from(tag).is_ok() => NBT::read(data, false),
+            [byte, _] => Err(NBTError::UnknownCompression(byte)),
+        }
+    }
+
+    /// Network NBT has no root name, used in packets since 1.20.2.
     pub fn read_network(mut data: impl Read) -> Result<NBT, NBTError> {
         let tag = NBTTag::try_from(u8::from_be_bytes(data.read_const()?))?;
         NBT::read_tag(&mut data, tag)
     }
 
+    /// Errors if there are any bytes left over.
+    fn check_trailing(cursor: std::io::Cursor<&[u8]>) -> Result<(), NBTError> {
+        match cursor.get_ref().len() - cursor.position() as usize {
+            0 => Ok(()),
+            trailing => Err(NBTError::TrailingBytes(trailing)),
+        }
+    }
+
     pub fn from_bytes(bytes: &[u8], compressed: bool) -> Result<(String, NBT), NBTError> {
-        NBT::read(std::io::Cursor::new(bytes), compressed)
+        let mut cursor = std::io::Cursor::new(bytes);
+        let nbt = NBT::read(&mut cursor, compressed)?;
+        NBT::check_trailing(cursor)?;
+        Ok(nbt)
     }
 
     pub fn from_bytes_network(bytes: &[u8]) -> Result<NBT, NBTError> {
-        NBT::read_network(std::io::Cursor::new(bytes))
+        let mut cursor = std::io::Cursor::new(bytes);
+        let nbt = NBT::read_network(&mut cursor)?;
+        NBT::check_trailing(cursor)?;
+        Ok(nbt)
     }
 
     fn write_tag(
@@ -277,7 +308,10 @@ impl NBT {
             }
             NBT::List(list) => {
                 let Some(first) = list.first() else {
-                    return Err(NBTError::InvalidList);
+                    // Empty lists have no type.
+                    data.write_all(&u8::from(NBTTag::End).to_be_bytes())?;
+                    data.write_all(&0u32.to_be_bytes())?;
+                    return Ok(());
                 };
                 let tag = first.tag();
                 if list.iter().any(|item| item.tag() != tag) {
@@ -338,6 +372,7 @@ impl NBT {
         self.write_tag(Some(name), true, &mut data)
     }
 
+    /// See [`NBT::read_network`].
     pub fn write_network(&self, mut data: impl Write) -> Result<(), NBTError> {
         self.write_tag(None, true, &mut data)
     }
@@ -353,117 +388,237 @@ impl NBT {
         self.write_network(&mut data)?;
         Ok(data.into_boxed_slice())
     }
+
+    /// Value at a path of dotted compound keys & list indices, like `/data get`
+    /// (e.g. `Level.Sections[0].Y`). Negative indices count from the end of the list.
+    ///
+    /// None if the path is invalid, a value doesn't exist, or a key is used on something that
+    /// isn't a compound & an index on something that isn't a list.
+    pub fn get_path(&self, path: &str) -> Option<&NBT> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |nbt, segment| match (nbt, segment) {
+                (NBT::Compound(compound), NBTPathSegment::Key(key)) => compound.get(key),
+                (NBT::List(list), NBTPathSegment::Index(index)) => {
+                    list.get(list_index(list.len(), index)?)
+                }
+                _ => None,
+            })
+    }
+
+    /// Mutable version of [`NBT::get_path`].
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut NBT> {
+        parse_path(path)?
+            .into_iter()
+            .try_fold(self, |nbt, segment| match (nbt, segment) {
+                (NBT::Compound(compound), NBTPathSegment::Key(key)) => compound.get_mut(key),
+                (NBT::List(list), NBTPathSegment::Index(index)) => {
+                    let index = list_index(list.len(), index)?;
+                    list.get_mut(index)
+                }
+                _ => None,
+            })
+    }
 }
 
-/// https://minecraft.wiki/w/NBT_format#Conversion_from_JSON
-impl TryFrom<serde_json::Value> for NBT {
-    type Error = NBTError;
+#[derive(Debug, PartialEq)]
+enum NBTPathSegment<'a> {
+    Key(&'a str),
+    Index(isize),
+}
 
-    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
-        #[derive(Eq, PartialEq, Clone, Copy, Debug)]
-        enum BestMatchingNumberType {
-            Byte,
-            Short,
-            Int,
-            Long,
-            Float,
-            Double,
+/// Splits a path like `Level.Sections[0].Y` into its segments, None if it's invalid.
+fn parse_path(path: &str) -> Option<Vec<NBTPathSegment<'_>>> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if key.is_empty() && indices.is_empty() {
+            return None;
+        }
+        if !key.is_empty() {
+            segments.push(NBTPathSegment::Key(key));
         }
+        while !indices.is_empty() {
+            let (index, rest) = indices.strip_prefix('[')?.split_once(']')?;
+            segments.push(NBTPathSegment::Index(index.parse().ok()?));
+            indices = rest;
+        }
+    }
+    Some(segments)
+}
 
-        impl From<&serde_json::Number> for BestMatchingNumberType {
-            fn from(value: &serde_json::Number) -> Self {
-                if let Some(number) = value.as_i64() {
-                    if i8::try_from(number).is_ok() {
-                        BestMatchingNumberType::Byte
-                    } else if i16::try_from(number).is_ok() {
-                        BestMatchingNumberType::Short
-                    } else if i32::try_from(number).is_ok() {
-                        BestMatchingNumberType::Int
-                    } else {
-                        BestMatchingNumberType::Long
-                    }
-                } else if let Some(number) = value.as_f64() {
-                    // TODO: Does this actually check if precision is lost?
-                    if ((number as f32) as f64) == number {
-                        BestMatchingNumberType::Float
-                    } else {
-                        BestMatchingNumberType::Double
-                    }
-                } else {
-                    unreachable!()
-                }
+fn list_index(len: usize, index: isize) -> Option<usize> {
+    if index < 0 {
+        len.checked_sub(index.unsigned_abs())
+    } else {
+        Some(index as usize)
+    }
+}
+
+/// Smallest NBT number type that can hold a JSON number.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+enum BestMatchingNumberType {
+    Byte,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+impl From<&serde_json::Number> for BestMatchingNumberType {
+    fn from(value: &serde_json::Number) -> Self {
+        if let Some(number) = value.as_i64() {
+            if i8::try_from(number).is_ok() {
+                BestMatchingNumberType::Byte
+            } else if i16::try_from(number).is_ok() {
+                BestMatchingNumberType::Short
+            } else if i32::try_from(number).is_ok() {
+                BestMatchingNumberType::Int
+            } else {
+                BestMatchingNumberType::Long
             }
+        } else if let Some(number) = value.as_f64() {
+            // TODO: Does this actually check if precision is lost?
+            if ((number as f32) as f64) == number {
+                BestMatchingNumberType::Float
+            } else {
+                BestMatchingNumberType::Double
+            }
+        } else {
+            unreachable!()
         }
+    }
+}
 
-        impl BestMatchingNumberType {
-            fn convert_json_to_nbt(&self, value: &serde_json::Number) -> Result<NBT, NBTError> {
-                fn do_conversion_int<T: TryFrom<i64>>(
-                    value: &serde_json::Number,
-                ) -> Result<T, NBTError> {
-                    value
-                        .as_i64()
-                        .ok_or(NBTError::JsonCouldntConvertNumber)?
-                        .try_into()
-                        .map_err(|_| NBTError::JsonCouldntConvertNumber)
-                }
+impl BestMatchingNumberType {
+    fn convert_json_to_nbt(&self, value: &serde_json::Number) -> Result<NBT, NBTError> {
+        fn do_conversion_int<T: TryFrom<i64>>(value: &serde_json::Number) -> Result<T, NBTError> {
+            value
+                .as_i64()
+                .ok_or(NBTError::JsonCouldntConvertNumber)?
+                .try_into()
+                .map_err(|_| NBTError::JsonCouldntConvertNumber)
+        }
 
-                Ok(match self {
-                    BestMatchingNumberType::Byte => NBT::Byte(do_conversion_int(value)?),
-                    BestMatchingNumberType::Short => NBT::Short(do_conversion_int(value)?),
-                    BestMatchingNumberType::Int => NBT::Int(do_conversion_int(value)?),
-                    BestMatchingNumberType::Long => NBT::Long(do_conversion_int(value)?),
-                    BestMatchingNumberType::Float => {
-                        NBT::Float(value.as_f64().ok_or(NBTError::JsonCouldntConvertNumber)? as f32)
-                    }
-                    BestMatchingNumberType::Double => {
-                        NBT::Double(value.as_f64().ok_or(NBTError::JsonCouldntConvertNumber)?)
-                    }
-                })
+        Ok(match self {
+            BestMatchingNumberType::Byte => NBT::Byte(do_conversion_int(value)?),
+            BestMatchingNumberType::Short => NBT::Short(do_conversion_int(value)?),
+            BestMatchingNumberType::Int => NBT::Int(do_conversion_int(value)?),
+            BestMatchingNumberType::Long => NBT::Long(do_conversion_int(value)?),
+            BestMatchingNumberType::Float => {
+                NBT::Float(value.as_f64().ok_or(NBTError::JsonCouldntConvertNumber)? as f32)
             }
-
-            fn is_int(&self) -> bool {
-                matches!(
-                    self,
-                    BestMatchingNumberType::Byte
-                        | BestMatchingNumberType::Short
-                        | BestMatchingNumberType::Int
-                        | BestMatchingNumberType::Long
-                )
+            BestMatchingNumberType::Double => {
+                NBT::Double(value.as_f64().ok_or(NBTError::JsonCouldntConvertNumber)?)
             }
+        })
+    }
 
-            fn is_float(&self) -> bool {
-                matches!(
-                    self,
-                    BestMatchingNumberType::Float | BestMatchingNumberType::Double
-                )
-            }
+    fn is_int(&self) -> bool {
+        matches!(
+            self,
+            BestMatchingNumberType::Byte
+                | BestMatchingNumberType::Short
+                | BestMatchingNumberType::Int
+                | BestMatchingNumberType::Long
+        )
+    }
 
-            fn value(&self) -> u8 {
-                match self {
-                    BestMatchingNumberType::Byte => 1,
-                    BestMatchingNumberType::Short => 2,
-                    BestMatchingNumberType::Int => 3,
-                    BestMatchingNumberType::Long => 4,
-                    BestMatchingNumberType::Float => 5,
-                    BestMatchingNumberType::Double => 6,
-                }
-            }
+    fn is_float(&self) -> bool {
+        matches!(
+            self,
+            BestMatchingNumberType::Float | BestMatchingNumberType::Double
+        )
+    }
 
-            fn rank(
-                self,
-                other: BestMatchingNumberType,
-            ) -> Result<BestMatchingNumberType, NBTError> {
-                if (self.is_int() ^ other.is_int()) || (self.is_float() ^ other.is_float()) {
-                    return Err(NBTError::JsonCouldntConvertNumber);
-                }
-                if self.value() > other.value() {
-                    Ok(self)
-                } else {
-                    Ok(other)
-                }
-            }
+    fn value(&self) -> u8 {
+        match self {
+            BestMatchingNumberType::Byte => 1,
+            BestMatchingNumberType::Short => 2,
+            BestMatchingNumberType::Int => 3,
+            BestMatchingNumberType::Long => 4,
+            BestMatchingNumberType::Float => 5,
+            BestMatchingNumberType::Double => 6,
+        }
+    }
+
+    fn rank(self, other: BestMatchingNumberType) -> Result<BestMatchingNumberType, NBTError> {
+        if (self.is_int() ^ other.is_int()) || (self.is_float() ^ other.is_float()) {
+            return Err(NBTError::JsonMixedIntFloatArray);
+        }
+        if self.value() > other.value() {
+            Ok(self)
+        } else {
+            Ok(other)
         }
+    }
+}
+
+impl From<BestMatchingNumberType> for NBTTag {
+    fn from(value: BestMatchingNumberType) -> Self {
+        match value {
+            BestMatchingNumberType::Byte => NBTTag::Byte,
+            BestMatchingNumberType::Short => NBTTag::Short,
+            BestMatchingNumberType::Int => NBTTag::Int,
+            BestMatchingNumberType::Long => NBTTag::Long,
+            BestMatchingNumberType::Float => NBTTag::Float,
+            BestMatchingNumberType::Double => NBTTag::Double,
+        }
+    }
+}
+
+impl TryFrom<NBTTag> for BestMatchingNumberType {
+    type Error = NBTError;
 
+    fn try_from(value: NBTTag) -> Result<Self, Self::Error> {
+        match value {
+            NBTTag::Byte => Ok(BestMatchingNumberType::Byte),
+            NBTTag::Short => Ok(BestMatchingNumberType::Short),
+            NBTTag::Int => Ok(BestMatchingNumberType::Int),
+            NBTTag::Long => Ok(BestMatchingNumberType::Long),
+            NBTTag::Float => Ok(BestMatchingNumberType::Float),
+            NBTTag::Double => Ok(BestMatchingNumberType::Double),
+            _ => Err(NBTError::JsonCouldntConvertNumber),
+        }
+    }
+}
+
+fn number_arena(numbers: &[serde_json::Number]) -> Result<BestMatchingNumberType, NBTError> {
+    let Some(first) = numbers.first() else {
+        return Err(NBTError::JsonConversionEmptyArray);
+    };
+    numbers
+        .iter()
+        .try_fold(BestMatchingNumberType::from(first), |arena, number| {
+            arena.rank(BestMatchingNumberType::from(number))
+        })
+}
+
+/// The tag every number in a JSON array gets converted to, the same promotion that's used when
+/// converting JSON to [`NBT`].
+///
+/// Ints & floats can't be mixed, ints promote up to [`NBTTag::Long`] & floats to
+/// [`NBTTag::Double`].
+pub fn infer_nbt_number_type(values: &[serde_json::Value]) -> Result<NBTTag, NBTError> {
+    let numbers = values
+        .iter()
+        .map(|value| value.as_number().cloned())
+        .collect::<Option<Vec<_>>>()
+        .ok_or(NBTError::JsonCouldntConvert)?;
+    Ok(number_arena(&numbers)?.into())
+}
+
+/// Convert a JSON number to a number NBT of the tag, usually from [`infer_nbt_number_type`].
+pub fn json_number_to_nbt(tag: NBTTag, number: &serde_json::Number) -> Result<NBT, NBTError> {
+    BestMatchingNumberType::try_from(tag)?.convert_json_to_nbt(number)
+}
+
+/// https://minecraft.wiki/w/NBT_format#Conversion_from_JSON
+impl TryFrom<serde_json::Value> for NBT {
+    type Error = NBTError;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
         match value {
             serde_json::Value::Bool(bool) => Ok(NBT::Byte(if bool { 1 } else { 0 })),
             serde_json::Value::Number(number) => {
@@ -500,12 +655,7 @@ impl TryFrom<serde_json::Value> for NBT {
                     .map(|value| value.as_number().cloned().unwrap())
                     .collect::<Vec<_>>();
 
-                let arena = array.iter().try_fold(
-                    BestMatchingNumberType::from(array.first().unwrap()),
-                    |arena, number| {
-                        BestMatchingNumberType::rank(arena, BestMatchingNumberType::from(number))
-                    },
-                )?;
+                let arena = number_arena(&array)?;
 
                 let converted = array
                     .iter()
@@ -574,7 +724,12 @@ impl From<NBT> for serde_json::Value {
 
 #[cfg(test)]
 mod test {
-    use super::{NBTError, NBT};
+    use std::io::Write as _;
+
+    use super::{
+        infer_nbt_number_type, json_number_to_nbt, parse_path, NBTError, NBTPathSegment, NBTTag,
+        NBT,
+    };
 
     #[test]
     fn bigtest() -> Result<(), NBTError> {
@@ -633,4 +788,121 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_infer_nbt_number_type() -> Result<(), NBTError> {
+        let ints = serde_json::json!([1, 2, 300, -5]);
+        let ints = ints.as_array().unwrap();
+        assert_eq!(infer_nbt_number_type(ints)?, NBTTag::Short);
+        assert_eq!(
+            json_number_to_nbt(NBTTag::Short, ints[2].as_number().unwrap())?,
+            NBT::Short(300)
+        );
+
+        assert!(matches!(
+            infer_nbt_number_type(serde_json::json!([1, 2.5]).as_array().unwrap()),
+            Err(NBTError::JsonMixedIntFloatArray)
+        ));
+
+        let promoted = serde_json::json!([1, 5_000_000_000_i64]);
+        assert_eq!(
+            infer_nbt_number_type(promoted.as_array().unwrap())?,
+            NBTTag::Long
+        );
+        assert_eq!(
+            NBT::try_from(promoted)?,
+            NBT::List(vec![NBT::Long(1), NBT::Long(5_000_000_000)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_nbt() -> Result<(), NBTError> {
+        let nbt = nbt_compound![
+            "text" => NBT::String("Hello".to_owned()),
+            "bold" => NBT::Byte(1),
+        ];
+
+        let network = nbt.to_bytes_network()?;
+        assert_eq!(NBT::from_bytes_network(&network)?, nbt);
+        assert_eq!(
+            NBT::from_bytes_network(&NBT::String("Hello".to_owned()).to_bytes_network()?)?,
+            NBT::String("Hello".to_owned())
+        );
+
+        let named = nbt.to_bytes("root", false)?;
+        assert!(NBT::from_bytes_network(&named).is_err());
+        assert!(NBT::from_bytes(&network, false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path() -> Result<(), NBTError> {
+        let mut chunk = NBT::from_snbt(include_str!("./path_test.snbt"))?;
+
+        assert_eq!(
+            parse_path("Level.Sections[0][-1].Y"),
+            Some(vec![
+                NBTPathSegment::Key("Level"),
+                NBTPathSegment::Key("Sections"),
+                NBTPathSegment::Index(0),
+                NBTPathSegment::Index(-1),
+                NBTPathSegment::Key("Y"),
+            ])
+        );
+
+        assert_eq!(chunk.get_path("DataVersion"), Some(&NBT::Int(4189)));
+        assert_eq!(chunk.get_path("Level.Sections[0].Y"), Some(&NBT::Byte(-4)));
+        assert_eq!(chunk.get_path("Level.Sections[-1].Y"), Some(&NBT::Byte(-3)));
+        assert_eq!(
+            chunk.get_path("Level.Sections[0].block_states.palette[1].Name"),
+            Some(&NBT::String("minecraft:stone".to_owned()))
+        );
+
+        // Type mismatches, missing values & invalid paths.
+        assert_eq!(chunk.get_path("Level[0]"), None);
+        assert_eq!(chunk.get_path("Level.Sections.Y"), None);
+        assert_eq!(chunk.get_path("Level.Sections[2]"), None);
+        assert_eq!(chunk.get_path("Level.Sections[-3]"), None);
+        assert_eq!(chunk.get_path("Level.Heightmaps.WORLD_SURFACE[0]"), None);
+        assert_eq!(chunk.get_path("Level.Missing"), None);
+        assert_eq!(chunk.get_path("Level..xPos"), None);
+        assert_eq!(chunk.get_path("Level.Sections[a]"), None);
+        assert_eq!(chunk.get_path("Level.Sections[0"), None);
+        assert_eq!(chunk.get_path(""), None);
+
+        *chunk.get_path_mut("Level.Sections[1].Y").unwrap() = NBT::Byte(5);
+        assert_eq!(chunk.get_path("Level.Sections[1].Y"), Some(&NBT::Byte(5)));
+        assert!(chunk.get_path_mut("Level.xPos.y").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_auto() -> Result<(), NBTError> {
+        let nbt = nbt_compound!["Data" => nbt_compound!["LevelName" => NBT::String("world".to_owned()),],];
+        let raw = nbt.to_bytes("", false)?;
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&raw)?;
+        let gzip = gzip.finish()?;
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&raw)?;
+        let zlib = zlib.finish()?;
+
+        for bytes in [&*raw, &gzip, &zlib] {
+            assert_eq!(
+                NBT::read_auto(std::io::Cursor::new(bytes))?,
+                ("".to_owned(), nbt.clone())
+            );
+        }
+        assert!(matches!(
+            NBT::read_auto(std::io::Cursor::new([0xFF, 0x00])),
+            Err(NBTError::UnknownCompression(0xFF))
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,125 @@
+use serde::{ser::SerializeMap as _, Serialize, Serializer};
+
+use super::NBT;
+
+/// Serializes like the JSON conversion, number types & array kinds aren't kept.
+impl Serialize for NBT {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            NBT::Byte(byte) => serializer.serialize_i8(*byte),
+            NBT::Short(short) => serializer.serialize_i16(*short),
+            NBT::Int(int) => serializer.serialize_i32(*int),
+            NBT::Long(long) => serializer.serialize_i64(*long),
+            NBT::Float(float) => serializer.serialize_f32(*float),
+            NBT::Double(double) => serializer.serialize_f64(*double),
+            NBT::String(string) => serializer.serialize_str(string),
+            NBT::List(list) => serializer.collect_seq(list),
+            NBT::Compound(compound) => {
+                let mut map = serializer.serialize_map(Some(compound.len()))?;
+                compound
+                    .iter()
+                    .try_for_each(|(key, value)| map.serialize_entry(key, value))?;
+                map.end()
+            }
+            NBT::ByteArray(byte_array) => serializer.collect_seq(byte_array.iter()),
+            NBT::IntArray(int_array) => serializer.collect_seq(int_array.iter()),
+            NBT::LongArray(long_array) => serializer.collect_seq(long_array.iter()),
+        }
+    }
+}
+
+impl NBT {
+    /// Convert to JSON, see [`NBT`]'s [`Serialize`] implementation.
+    ///
+    /// If annotate, numbers that aren't ints are written as strings with their SNBT type suffix
+    /// (`1b`, `1s`, `1L`, `1.5f`, `1.5d`) so they can be told apart.
+    pub fn to_json_value(&self, annotate: bool) -> serde_json::Value {
+        if !annotate {
+            // Compound keys are always strings, so this can't fail.
+            return serde_json::to_value(self).unwrap();
+        }
+        match self {
+            NBT::Byte(byte) => serde_json::Value::String(format!("{}b", byte)),
+            NBT::Short(short) => serde_json::Value::String(format!("{}s", short)),
+            NBT::Int(int) => serde_json::Value::from(*int),
+            NBT::Long(long) => serde_json::Value::String(format!("{}L", long)),
+            NBT::Float(float) => serde_json::Value::String(format!("{}f", float)),
+            NBT::Double(double) => serde_json::Value::String(format!("{}d", double)),
+            NBT::String(string) => serde_json::Value::String(string.clone()),
+            NBT::List(list) => list.iter().map(|nbt| nbt.to_json_value(true)).collect(),
+            NBT::Compound(compound) => serde_json::Value::Object(
+                compound
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_json_value(true)))
+                    .collect(),
+            ),
+            NBT::ByteArray(byte_array) => byte_array
+                .iter()
+                .map(|v| NBT::Byte(*v).to_json_value(true))
+                .collect(),
+            NBT::IntArray(int_array) => serde_json::Value::from(int_array.to_vec()),
+            NBT::LongArray(long_array) => long_array
+                .iter()
+                .map(|v| NBT::Long(*v).to_json_value(true))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        nbt::{NBTError, NBT},
+        nbt_compound,
+    };
+
+    #[test]
+    fn test_serialize_round_trip() -> Result<(), NBTError> {
+        let nbt = nbt_compound![
+            "name" => NBT::String("Eggbert".to_string()),
+            "value" => NBT::Float(0.5),
+            "count" => NBT::Int(100_000),
+            "list" => NBT::List(vec![NBT::Short(300), NBT::Short(-300)]),
+            "nested" => nbt_compound![
+                "long" => NBT::Long(5_000_000_000),
+            ],
+        ];
+
+        let json = serde_json::to_string(&nbt).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value, nbt.to_json_value(false));
+        assert_eq!(NBT::try_from(value)?, nbt);
+
+        assert_eq!(
+            NBT::Long(5).to_json_value(true),
+            serde_json::Value::String("5L".to_string())
+        );
+        assert_eq!(
+            NBT::List(vec![NBT::Byte(1), NBT::Int(2)]).to_json_value(true),
+            serde_json::json!(["1b", 2])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_pretty() {
+        let nbt = nbt_compound![
+            "bytes" => NBT::ByteArray(Box::new([1, -1])),
+            "ints" => NBT::IntArray(Box::new([])),
+            "longs" => NBT::LongArray(Box::new([5_000_000_000])),
+        ];
+        let pretty = serde_json::to_string_pretty(&nbt).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+            serde_json::json!({
+                "bytes": [1, -1],
+                "ints": [],
+                "longs": [5_000_000_000i64],
+            })
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+
+use itertools::Itertools as _;
+
+use super::{NBTError, NBT};
+
+/// Parser for stringified NBT, the format used by commands & .snbt files.
+///
+/// https://minecraft.wiki/w/NBT_format#SNBT_format
+struct SnbtParser<'a> {
+    string: &'a str,
+    position: usize,
+}
+
+impl<'a> SnbtParser<'a> {
+    fn new(string: &'a str) -> Self {
+        Self {
+            string,
+            position: 0,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> NBTError {
+        NBTError::InvalidSnbt(self.position, message.into())
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.string[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.string.len() - self.rest().trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), NBTError> {
+        match self.peek() {
+            Some(char) if char == expected => {
+                self.position += char.len_utf8();
+                Ok(())
+            }
+            Some(char) => Err(self.error(format!("Expected '{}', got '{}'", expected, char))),
+            None => Err(self.error(format!("Expected '{}', got end", expected))),
+        }
+    }
+
+    /// Skips the char if it's next.
+    fn accept(&mut self, char: char) -> bool {
+        if self.peek() == Some(char) {
+            self.position += char.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_unquoted_char(char: char) -> bool {
+        char.is_ascii_alphanumeric() || matches!(char, '_' | '-' | '.' | '+')
+    }
+
+    fn parse_unquoted(&mut self) -> Result<&'a str, NBTError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let length = rest
+            .find(|char| !Self::is_unquoted_char(char))
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error("Expected a value"));
+        }
+        self.position += length;
+        Ok(&rest[..length])
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, NBTError> {
+        let quote = self.peek().ok_or_else(|| self.error("Expected a string"))?;
+        self.position += 1;
+        let mut string = String::new();
+        let mut chars = self.rest().chars();
+        loop {
+            let Some(char) = chars.next() else {
+                return Err(self.error("Unterminated string"));
+            };
+            self.position += char.len_utf8();
+            match char {
+                '\\' => {
+                    let Some(escaped) = chars.next() else {
+                        return Err(self.error("Unterminated string"));
+                    };
+                    self.position += escaped.len_utf8();
+                    match escaped {
+                        '\\' | '"' | '\'' => string.push(escaped),
+                        'n' => string.push('\n'),
+                        't' => string.push('\t'),
+                        _ => return Err(self.error(format!("Invalid escape '\\{}'", escaped))),
+                    }
+                }
+                char if char == quote => return Ok(string),
+                char => string.push(char),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, NBTError> {
+        match self.peek() {
+            Some('"' | '\'') => self.parse_quoted(),
+            _ => Ok(self.parse_unquoted()?.to_owned()),
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<NBT, NBTError> {
+        self.expect('{')?;
+        let mut compound = HashMap::new();
+        if self.accept('}') {
+            return Ok(NBT::Compound(compound));
+        }
+        loop {
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            compound.insert(key, value);
+            if !self.accept(',') {
+                break;
+            }
+        }
+        self.expect('}')?;
+        Ok(NBT::Compound(compound))
+    }
+
+    /// Values of a typed array, each converted with to_value.
+    fn parse_array<T>(
+        &mut self,
+        to_value: impl Fn(NBT) -> Option<T>,
+    ) -> Result<Box<[T]>, NBTError> {
+        let mut values = Vec::new();
+        if self.accept(']') {
+            return Ok(values.into_boxed_slice());
+        }
+        loop {
+            let value = self.parse_value()?;
+            values.push(to_value(value).ok_or_else(|| self.error("Invalid array value"))?);
+            if !self.accept(',') {
+                break;
+            }
+        }
+        self.expect(']')?;
+        Ok(values.into_boxed_slice())
+    }
+
+    fn parse_list(&mut self) -> Result<NBT, NBTError> {
+        self.expect('[')?;
+
+        // Typed arrays start with their type.
+        let rest = self.rest();
+        if let Some((kind, _)) = rest.split_once(';') {
+            match kind.trim() {
+                "B" | "I" | "L" => {
+                    self.position += kind.len() + 1;
+                    return Ok(match kind.trim() {
+                        "B" => NBT::ByteArray(self.parse_array(|value| match value {
+                            NBT::Byte(byte) => Some(byte),
+                            NBT::Int(int) => int.try_into().ok(),
+                            _ => None,
+                        })?),
+                        "I" => NBT::IntArray(self.parse_array(|value| match value {
+                            NBT::Int(int) => Some(int),
+                            _ => None,
+                        })?),
+                        _ => NBT::LongArray(self.parse_array(|value| match value {
+                            NBT::Long(long) => Some(long),
+                            NBT::Int(int) => Some(int as i64),
+                            _ => None,
+                        })?),
+                    });
+                }
+                kind if kind.chars().all(Self::is_unquoted_char) && !kind.is_empty() => {
+                    return Err(self.error(format!("Invalid array type '{}'", kind)));
+                }
+                _ => {}
+            }
+        }
+
+        let mut list = Vec::new();
+        if self.accept(']') {
+            return Ok(NBT::List(list));
+        }
+        loop {
+            let value = self.parse_value()?;
+            // The first value decides the type of the list.
+            if let Some(first) = list.first() {
+                if first.tag() != value.tag() {
+                    return Err(self.error("List values must be the same type"));
+                }
+            }
+            list.push(value);
+            if !self.accept(',') {
+                break;
+            }
+        }
+        self.expect(']')?;
+        Ok(NBT::List(list))
+    }
+
+    fn parse_value(&mut self) -> Result<NBT, NBTError> {
+        match self.peek() {
+            Some('{') => self.parse_compound(),
+            Some('[') => self.parse_list(),
+            Some('"' | '\'') => Ok(NBT::String(self.parse_quoted()?)),
+            Some(_) => {
+                let unquoted = self.parse_unquoted()?;
+                Ok(parse_unquoted_value(unquoted))
+            }
+            None => Err(self.error("Expected a value, got end")),
+        }
+    }
+}
+
+/// Numbers & booleans, anything else is a string.
+fn parse_unquoted_value(value: &str) -> NBT {
+    match value {
+        "true" => return NBT::Byte(1),
+        "false" => return NBT::Byte(0),
+        _ => {}
+    }
+    let (number, suffix) = match value.char_indices().last() {
+        Some((index, suffix)) if suffix.is_ascii_alphabetic() => (&value[..index], Some(suffix)),
+        _ => (value, None),
+    };
+    let is_float = number.contains(['.', 'e', 'E']);
+    let parsed = match suffix.map(|suffix| suffix.to_ascii_lowercase()) {
+        Some('b') => number.parse().ok().map(NBT::Byte),
+        Some('s') => number.parse().ok().map(NBT::Short),
+        Some('i') => number.parse().ok().map(NBT::Int),
+        Some('l') => number.parse().ok().map(NBT::Long),
+        Some('f') => number.parse().ok().map(NBT::Float),
+        Some('d') => number.parse().ok().map(NBT::Double),
+        None if is_float => number.parse().ok().map(NBT::Double),
+        None => number.parse().ok().map(NBT::Int),
+        Some(_) => None,
+    };
+    parsed.unwrap_or_else(|| NBT::String(value.to_owned()))
+}
+
+/// Quotes with `"`, or `'` if the string contains `"`.
+fn write_quoted(string: &str, snbt: &mut String) {
+    let quote = if string.contains('"') { '\'' } else { '"' };
+    snbt.push(quote);
+    for char in string.chars() {
+        if char == '\\' || char == quote {
+            snbt.push('\\');
+        }
+        snbt.push(char);
+    }
+    snbt.push(quote);
+}
+
+fn write_key(key: &str, snbt: &mut String) {
+    if !key.is_empty() && key.chars().all(SnbtParser::is_unquoted_char) {
+        snbt.push_str(key);
+    } else {
+        write_quoted(key, snbt);
+    }
+}
+
+fn write_values<T>(
+    prefix: &str,
+    values: impl IntoIterator<Item = T>,
+    snbt: &mut String,
+    mut write: impl FnMut(T, &mut String),
+) {
+    snbt.push('[');
+    snbt.push_str(prefix);
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            snbt.push(',');
+        }
+        write(value, snbt);
+    }
+    snbt.push(']');
+}
+
+fn write_value(value: &NBT, snbt: &mut String) {
+    match value {
+        NBT::Byte(byte) => snbt.push_str(&format!("{}b", byte)),
+        NBT::Short(short) => snbt.push_str(&format!("{}s", short)),
+        NBT::Int(int) => snbt.push_str(&int.to_string()),
+        NBT::Long(long) => snbt.push_str(&format!("{}L", long)),
+        NBT::Float(float) => snbt.push_str(&format!("{}f", float)),
+        NBT::Double(double) => snbt.push_str(&format!("{}d", double)),
+        NBT::String(string) => write_quoted(string, snbt),
+        NBT::List(list) => write_values("", list, snbt, write_value),
+        NBT::Compound(compound) => {
+            snbt.push('{');
+            // Sorted so that the output is the same every time.
+            for (i, (key, value)) in compound
+                .iter()
+                .sorted_by_key(|(key, _)| key.as_str())
+                .enumerate()
+            {
+                if i > 0 {
+                    snbt.push(',');
+                }
+                write_key(key, snbt);
+                snbt.push(':');
+                write_value(value, snbt);
+            }
+            snbt.push('}');
+        }
+        NBT::ByteArray(bytes) => write_values("B;", bytes.iter(), snbt, |byte, snbt| {
+            snbt.push_str(&format!("{}b", byte))
+        }),
+        NBT::IntArray(ints) => write_values("I;", ints.iter(), snbt, |int, snbt| {
+            snbt.push_str(&int.to_string())
+        }),
+        NBT::LongArray(longs) => write_values("L;", longs.iter(), snbt, |long, snbt| {
+            snbt.push_str(&format!("{}L", long))
+        }),
+    }
+}
+
+impl NBT {
+    /// Stringified NBT that vanilla accepts, `NBT::from_snbt(&nbt.to_snbt())` is always `nbt`.
+    ///
+    /// Floating point values must be finite.
+    pub fn to_snbt(&self) -> String {
+        let mut snbt = String::new();
+        write_value(self, &mut snbt);
+        snbt
+    }
+
+    /// Parse stringified NBT, like `{name: "Eggbert", value: 0.5f, data: [I; 1, 2, 3]}`.
+    pub fn from_snbt(snbt: &str) -> Result<NBT, NBTError> {
+        let mut parser = SnbtParser::new(snbt);
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if !parser.rest().is_empty() {
+            return Err(parser.error("Unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng as _};
+
+    use crate::{
+        nbt::{NBTError, NBT},
+        nbt_compound,
+    };
+
+    fn random_string(rng: &mut StdRng) -> String {
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '_', '-', '.', '+', ' ', '"', '\'', '\\', ':', ';', 'é',
+        ];
+        (0..rng.gen_range(0..8))
+            .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+            .collect()
+    }
+
+    /// Stop generating lists & compounds when too deep.
+    fn tags(depth: usize) -> u8 {
+        if depth < 4 {
+            12
+        } else {
+            7
+        }
+    }
+
+    fn random_nbt(rng: &mut StdRng, tag: u8, depth: usize) -> NBT {
+        match tag {
+            0 => NBT::Byte(rng.gen()),
+            1 => NBT::Short(rng.gen()),
+            2 => NBT::Int(rng.gen()),
+            3 => NBT::Long(rng.gen()),
+            4 => NBT::Float(rng.gen_range(-1.0e6..1.0e6)),
+            5 => NBT::Double(rng.gen_range(-1.0e12..1.0e12)),
+            6 => NBT::String(random_string(rng)),
+            7 => {
+                let tag = rng.gen_range(0..tags(depth));
+                NBT::List(
+                    (0..rng.gen_range(0..4))
+                        .map(|_| random_nbt(rng, tag, depth + 1))
+                        .collect(),
+                )
+            }
+            8 => NBT::Compound(
+                (0..rng.gen_range(0..4))
+                    .map(|_| {
+                        let tag = rng.gen_range(0..tags(depth));
+                        (random_string(rng), random_nbt(rng, tag, depth + 1))
+                    })
+                    .collect::<HashMap<_, _>>(),
+            ),
+            9 => NBT::ByteArray((0..rng.gen_range(0..4)).map(|_| rng.gen()).collect()),
+            10 => NBT::IntArray((0..rng.gen_range(0..4)).map(|_| rng.gen()).collect()),
+            _ => NBT::LongArray((0..rng.gen_range(0..4)).map(|_| rng.gen()).collect()),
+        }
+    }
+
+    #[test]
+    fn test_snbt_values() -> Result<(), NBTError> {
+        assert_eq!(NBT::from_snbt("1b")?, NBT::Byte(1));
+        assert_eq!(NBT::from_snbt("-300s")?, NBT::Short(-300));
+        assert_eq!(NBT::from_snbt("5")?, NBT::Int(5));
+        assert_eq!(NBT::from_snbt("5000000000L")?, NBT::Long(5_000_000_000));
+        assert_eq!(NBT::from_snbt("0.5f")?, NBT::Float(0.5));
+        assert_eq!(NBT::from_snbt("0.5")?, NBT::Double(0.5));
+        assert_eq!(NBT::from_snbt("true")?, NBT::Byte(1));
+        assert_eq!(NBT::from_snbt("hello")?, NBT::String("hello".to_owned()));
+        assert_eq!(
+            NBT::from_snbt(r#""say \"hi\"""#)?,
+            NBT::String("say \"hi\"".to_owned())
+        );
+        assert_eq!(
+            NBT::from_snbt(r#"'it\'s "fine"'"#)?,
+            NBT::String("it's \"fine\"".to_owned())
+        );
+        assert_eq!(
+            NBT::from_snbt("[B; 1b, -2b]")?,
+            NBT::ByteArray(Box::new([1, -2]))
+        );
+        assert_eq!(
+            NBT::from_snbt("[I;1,2,3]")?,
+            NBT::IntArray(Box::new([1, 2, 3]))
+        );
+        assert_eq!(
+            NBT::from_snbt("[L; 1l, 2]")?,
+            NBT::LongArray(Box::new([1, 2]))
+        );
+        assert_eq!(NBT::from_snbt("[]")?, NBT::List(Vec::new()));
+        assert_eq!(NBT::from_snbt("{}")?, nbt_compound![]);
+
+        assert!(NBT::from_snbt("[1b, 2s]").is_err());
+        assert!(NBT::from_snbt("[I; 1b]").is_err());
+        assert!(NBT::from_snbt("{a: 1").is_err());
+        assert!(NBT::from_snbt("\"unterminated").is_err());
+        assert!(NBT::from_snbt("1 2").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snbt_nested() -> Result<(), NBTError> {
+        let nbt = NBT::from_snbt(
+            r#"
+            {
+                name: "Eggbert",
+                "quoted key": 'value',
+                value: 0.5f,
+                list: [{a: 1b}, {}],
+                nested: { longs: [L; 1L, 2L], empty: {}, shorts: [1s, 2s] }
+            }
+            "#,
+        )?;
+        assert_eq!(
+            nbt,
+            nbt_compound![
+                "name" => NBT::String("Eggbert".to_owned()),
+                "quoted key" => NBT::String("value".to_owned()),
+                "value" => NBT::Float(0.5),
+                "list" => NBT::List(vec![nbt_compound!["a" => NBT::Byte(1),], nbt_compound![]]),
+                "nested" => nbt_compound![
+                    "longs" => NBT::LongArray(Box::new([1, 2])),
+                    "empty" => nbt_compound![],
+                    "shorts" => NBT::List(vec![NBT::Short(1), NBT::Short(2)]),
+                ],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snbt_write() -> Result<(), NBTError> {
+        assert_eq!(
+            nbt_compound![
+                "b" => NBT::Byte(-1),
+                "a key" => NBT::String("it's \"quoted\"".to_owned()),
+                "list" => NBT::List(vec![NBT::Double(0.5), NBT::Double(1.0)]),
+                "longs" => NBT::LongArray(Box::new([1, 2])),
+            ]
+            .to_snbt(),
+            r#"{"a key":'it\'s "quoted"',b:-1b,list:[0.5d,1d],longs:[L;1L,2L]}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_snbt_round_trip() -> Result<(), NBTError> {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let tag = rng.gen_range(0..12);
+            let nbt = random_nbt(&mut rng, tag, 0);
+            let snbt = nbt.to_snbt();
+            assert_eq!(NBT::from_snbt(&snbt)?, nbt, "{}", snbt);
+        }
+        Ok(())
+    }
+}
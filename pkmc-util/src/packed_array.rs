@@ -7,8 +7,18 @@ pub struct PackedArray<T> {
     packed: T,
 }
 
+/// Mask of the low bits_per_entry bits, shifting by 64 would overflow.
+const fn entry_mask(bits_per_entry: u8) -> u64 {
+    match bits_per_entry {
+        0 => 0,
+        bits_per_entry => u64::MAX >> (64 - bits_per_entry as u32),
+    }
+}
+
 impl PackedArray<Vec<u64>> {
-    /// If this returns 0, you should not be using PackedArray
+    /// Bits needed to store every value up to max_value, which is the palette count - 1.
+    ///
+    /// If this returns 0 (a single value), you should not be using PackedArray.
     pub const fn bits_per_entry(max_value: u64) -> u8 {
         match max_value {
             0 => 0,
@@ -18,6 +28,7 @@ impl PackedArray<Vec<u64>> {
     }
 
     pub const fn packed_size(bits_per_entry: u8, num_entries: usize) -> usize {
+        assert!(bits_per_entry > 0 && bits_per_entry <= 64);
         u64::div_ceil(
             num_entries as u64,
             (u64::BITS / bits_per_entry as u32) as u64,
@@ -30,6 +41,24 @@ impl PackedArray<Vec<u64>> {
             num_entries,
         )
     }
+
+    /// Packs palette indices directly.
+    /// Panics if any index doesn't fit in bits_per_entry.
+    pub fn from_indices(indices: &[u64], bits_per_entry: u8) -> Self {
+        assert!(bits_per_entry > 0 && bits_per_entry <= 64);
+        let entries_per_long = 64 / bits_per_entry as usize;
+        let entry_mask = entry_mask(bits_per_entry);
+        let packed = indices
+            .chunks(entries_per_long)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0, |packed, (i, index)| {
+                    assert!(*index <= entry_mask);
+                    packed | (index << (i * bits_per_entry as usize))
+                })
+            })
+            .collect();
+        Self::from_inner(packed, bits_per_entry, indices.len())
+    }
 }
 
 impl<T> PackedArray<T>
@@ -43,7 +72,7 @@ where
             bits_per_entry,
             num_entries,
             entries_per_long: 64 / bits_per_entry,
-            entry_mask: (1 << bits_per_entry) - 1,
+            entry_mask: entry_mask(bits_per_entry),
             packed,
         }
     }
@@ -129,6 +158,17 @@ mod test {
         assert_eq!(packed.into_inner(), longs);
     }
 
+    #[test]
+    fn packed_array_from_indices() {
+        let indices = (0..4096).map(|i| (i * 7) % 23).collect::<Vec<u64>>();
+        let packed = PackedArray::from_indices(&indices, 5);
+        assert_eq!(packed.iter().collect::<Vec<_>>(), indices);
+
+        let mut consumed = PackedArray::new(5, indices.len());
+        assert!(consumed.consume(indices.iter().cloned()).count() == 0);
+        assert_eq!(packed.into_inner(), consumed.into_inner());
+    }
+
     #[test]
     fn packed_array_test() {
         test_packed_array(
@@ -139,4 +179,37 @@ mod test {
             &[0x0020863148418841, 0x01018A7260F68C87],
         );
     }
+
+    #[test]
+    fn packed_array_bits_per_entry_edges() {
+        assert_eq!(PackedArray::bits_per_entry(0), 0);
+        assert_eq!(PackedArray::bits_per_entry(u64::MAX), 64);
+        assert_eq!(PackedArray::bits_per_entry(1 << 63), 64);
+
+        // Palette counts of 1, a power of two, & one past it.
+        for (palette_count, bpe) in [(1, 0), (2, 1), (3, 2), (16, 4), (17, 5), (256, 8), (257, 9)] {
+            assert_eq!(PackedArray::bits_per_entry(palette_count - 1), bpe);
+            if bpe == 0 {
+                continue;
+            }
+            let values = (0..4096).map(|i| i % palette_count).collect::<Vec<u64>>();
+            let mut packed = PackedArray::from_indices(&values, bpe);
+            assert_eq!(packed.iter().collect::<Vec<_>>(), values);
+            packed.set(7, palette_count - 1);
+            assert_eq!(packed.get(7), Some(palette_count - 1));
+            assert_eq!(packed.get(6), Some(6 % palette_count));
+            assert_eq!(packed.get(8), Some(8 % palette_count));
+        }
+
+        // Full 64 bit entries.
+        let values = [u64::MAX, 0, 1 << 63, 12345];
+        let mut packed = PackedArray::from_indices(&values, 64);
+        assert_eq!(packed.iter().collect::<Vec<_>>(), values);
+        packed.set(1, u64::MAX);
+        assert_eq!(packed.get(1), Some(u64::MAX));
+        assert_eq!(
+            packed.into_inner(),
+            vec![u64::MAX, u64::MAX, 1 << 63, 12345]
+        );
+    }
 }
@@ -2,20 +2,185 @@ use std::{
     collections::VecDeque,
     io::{Read, Write},
     net::TcpStream,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
 };
 
-use crate::{packet::try_read_varint_ret_bytes, ReadExt};
+use crate::packet::try_read_varint_ret_bytes;
 
 use super::{
     handler::{PacketHandler, UncompressedPacketHandler},
-    ClientboundPacket, ConnectionError, RawPacket, ReadExtPacket, WriteExtPacket,
+    ClientboundPacket, ConnectionError, RawPacket, WriteExtPacket,
 };
 
+/// Why a connection was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectCause {
+    /// The peer closed the connection.
+    Closed,
+    BrokenPipe,
+    Reset,
+    Timeout,
+    Kicked,
+    ProtocolError,
+    /// The peer didn't read fast enough, and too many bytes were waiting to be sent.
+    SendBufferFull,
+}
+
+impl std::fmt::Display for DisconnectCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectCause::Closed => write!(f, "Closed"),
+            DisconnectCause::BrokenPipe => write!(f, "Broken pipe"),
+            DisconnectCause::Reset => write!(f, "Connection reset"),
+            DisconnectCause::Timeout => write!(f, "Timed out"),
+            DisconnectCause::Kicked => write!(f, "Kicked"),
+            DisconnectCause::ProtocolError => write!(f, "Protocol error"),
+            DisconnectCause::SendBufferFull => write!(f, "Send buffer full"),
+        }
+    }
+}
+
+impl DisconnectCause {
+    /// Cause for IO errors that mean the connection is gone.
+    fn from_io_error(err: &std::io::Error) -> Option<Self> {
+        match err.kind() {
+            std::io::ErrorKind::BrokenPipe => Some(DisconnectCause::BrokenPipe),
+            std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted => {
+                Some(DisconnectCause::Reset)
+            }
+            std::io::ErrorKind::TimedOut => Some(DisconnectCause::Timeout),
+            std::io::ErrorKind::UnexpectedEof => Some(DisconnectCause::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// Default for [`Connection::with_max_outgoing_bytes`].
+pub const DEFAULT_MAX_OUTGOING_BYTES: usize = 64 * 1024 * 1024;
+
+/// How long [`Connection::close_with`] may block to finish writing the last frames.
+const CLOSE_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
 #[derive(Debug)]
 struct ConnectionInner {
     stream: Option<TcpStream>,
+    /// Peer closed its write side, we may still send.
+    read_closed: bool,
     handler: PacketHandler,
+    disconnect_cause: Option<DisconnectCause>,
+    /// Frames waiting to be written & if they're urgent, the first one may be partially written.
+    outgoing: VecDeque<(bool, Box<[u8]>)>,
+    /// Bytes of the first outgoing frame that have been written.
+    outgoing_written: usize,
+    /// Total size of the outgoing frames.
+    outgoing_bytes: usize,
+    max_outgoing_bytes: usize,
+}
+
+impl ConnectionInner {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: Some(stream),
+            read_closed: false,
+            handler: PacketHandler::Uncompressed(UncompressedPacketHandler),
+            disconnect_cause: None,
+            outgoing: VecDeque::new(),
+            outgoing_written: 0,
+            outgoing_bytes: 0,
+            max_outgoing_bytes: DEFAULT_MAX_OUTGOING_BYTES,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.stream.is_none() || self.read_closed
+    }
+
+    fn close(&mut self, cause: DisconnectCause) {
+        self.stream = None;
+        self.clear_outgoing();
+        // Keep the first cause, closing after is just cleanup.
+        self.disconnect_cause.get_or_insert(cause);
+    }
+
+    fn clear_outgoing(&mut self) {
+        self.outgoing.clear();
+        self.outgoing_written = 0;
+        self.outgoing_bytes = 0;
+    }
+
+    /// Drops the frames that haven't started being written, except urgent ones, then blocks for
+    /// a bit to write what's left. Frames that still couldn't be written are dropped on close.
+    fn flush_before_close(&mut self) {
+        let partial = self.outgoing_written > 0;
+        let mut index = 0;
+        self.outgoing.retain(|(urgent, _)| {
+            index += 1;
+            *urgent || (index == 1 && partial)
+        });
+        self.outgoing_bytes = self.outgoing.iter().map(|(_, frame)| frame.len()).sum();
+        if let Some(stream) = self.stream.as_ref() {
+            if stream.set_nonblocking(false).is_err()
+                || stream.set_write_timeout(Some(CLOSE_FLUSH_TIMEOUT)).is_err()
+            {
+                return;
+            }
+        }
+        let _ = self.flush();
+    }
+
+    fn queue(&mut self, frame: Box<[u8]>, urgent: bool) {
+        let index = if urgent {
+            // Urgent frames go after other urgent frames, but a partially written frame can't be
+            // cut into.
+            let start = (self.outgoing_written > 0) as usize;
+            self.outgoing
+                .iter()
+                .enumerate()
+                .skip(start)
+                .find(|(_, (urgent, _))| !urgent)
+                .map(|(index, _)| index)
+                .unwrap_or(self.outgoing.len())
+        } else {
+            self.outgoing.len()
+        };
+        self.outgoing_bytes += frame.len();
+        self.outgoing.insert(index, (urgent, frame));
+    }
+
+    /// Writes as much as possible without blocking, so a large frame never holds the lock for
+    /// long.
+    fn flush(&mut self) -> Result<(), ConnectionError> {
+        while let Some((_, frame)) = self.outgoing.front() {
+            let Some(stream) = self.stream.as_mut() else {
+                self.clear_outgoing();
+                break;
+            };
+            match stream.write(&frame[self.outgoing_written..]) {
+                Ok(0) => {
+                    self.close(DisconnectCause::BrokenPipe);
+                    break;
+                }
+                Ok(n) => {
+                    self.outgoing_written += n;
+                    if self.outgoing_written == frame.len() {
+                        self.outgoing_bytes -= frame.len();
+                        self.outgoing.pop_front();
+                        self.outgoing_written = 0;
+                    }
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => match DisconnectCause::from_io_error(&err) {
+                    Some(cause) => {
+                        self.close(cause);
+                        break;
+                    }
+                    None => return Err(err)?,
+                },
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -25,11 +190,41 @@ pub struct ConnectionSender {
 
 impl ConnectionSender {
     pub fn is_closed(&self) -> bool {
-        self.inner.lock().unwrap().stream.is_none()
+        self.inner.lock().unwrap().is_closed()
+    }
+
+    pub fn disconnect_cause(&self) -> Option<DisconnectCause> {
+        self.inner.lock().unwrap().disconnect_cause
+    }
+
+    pub fn downgrade(&self) -> WeakConnectionSender {
+        WeakConnectionSender {
+            inner: Arc::downgrade(&self.inner),
+        }
     }
 
     pub fn send(&self, packet: &impl ClientboundPacket) -> Result<(), ConnectionError> {
-        let raw: RawPacket = packet.raw_packet()?;
+        self.send_raw(packet.raw_packet()?)
+    }
+
+    pub fn send_raw(&self, raw: RawPacket) -> Result<(), ConnectionError> {
+        self.send_frame(raw, false)
+    }
+
+    /// Sends the packet before any packets that are still waiting to be written, for time
+    /// sensitive packets like keep alives.
+    ///
+    /// NOTE: Only use this for packets that don't depend on the order of other packets.
+    pub fn send_urgent(&self, packet: &impl ClientboundPacket) -> Result<(), ConnectionError> {
+        self.send_frame(packet.raw_packet()?, true)
+    }
+
+    /// Packets that can't be written yet are queued, and written on the next send or recieve.
+    pub fn flush(&self) -> Result<(), ConnectionError> {
+        self.inner.lock().unwrap().flush()
+    }
+
+    fn send_frame(&self, raw: RawPacket, urgent: bool) -> Result<(), ConnectionError> {
         let bytes = raw.into_bytes();
 
         let handler = self.inner.lock().unwrap().handler.clone();
@@ -41,82 +236,147 @@ impl ConnectionSender {
         with_size.write_all(&encoded)?;
 
         let mut inner = self.inner.lock().unwrap();
-        let Some(stream) = inner.stream.as_mut() else {
+        if inner.stream.is_none() {
             return Ok(());
-        };
-        match stream.write_all(&with_size) {
-            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => inner.stream = None,
-            v => v?,
+        }
+        inner.queue(with_size.into_boxed_slice(), urgent);
+        inner.flush()?;
+        if inner.outgoing_bytes > inner.max_outgoing_bytes {
+            inner.close(DisconnectCause::SendBufferFull);
         }
         Ok(())
     }
 }
 
+/// A [`ConnectionSender`] that doesn't keep the connection alive.
+#[derive(Debug, Clone)]
+pub struct WeakConnectionSender {
+    inner: Weak<Mutex<ConnectionInner>>,
+}
+
+impl WeakConnectionSender {
+    pub fn upgrade(&self) -> Option<ConnectionSender> {
+        Some(ConnectionSender {
+            inner: self.inner.upgrade()?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Connection {
     inner: Arc<Mutex<ConnectionInner>>,
     bytes: VecDeque<u8>,
+    /// Copy of the inner handler, so decoding doesn't have to lock.
+    /// Only [`Connection::set_packet_handler`] changes the handler, which keeps this in sync.
+    handler: PacketHandler,
 }
 
 impl Connection {
     pub fn new(stream: TcpStream) -> Result<Self, ConnectionError> {
         stream.set_nonblocking(true)?;
         Ok(Self {
-            inner: Arc::new(Mutex::new(ConnectionInner {
-                stream: Some(stream),
-                handler: PacketHandler::Uncompressed(UncompressedPacketHandler),
-            })),
+            inner: Arc::new(Mutex::new(ConnectionInner::new(stream))),
             bytes: VecDeque::new(),
+            handler: PacketHandler::Uncompressed(UncompressedPacketHandler),
         })
     }
 
+    /// Maximum number of bytes waiting to be written, the connection is closed with
+    /// [`DisconnectCause::SendBufferFull`] if a send goes over it.
+    pub fn with_max_outgoing_bytes(self, max_outgoing_bytes: usize) -> Self {
+        self.inner.lock().unwrap().max_outgoing_bytes = max_outgoing_bytes;
+        self
+    }
+
     pub fn sender(&self) -> ConnectionSender {
         ConnectionSender {
             inner: self.inner.clone(),
         }
     }
 
-    pub fn set_packet_handler(&self, handler: PacketHandler) {
-        self.inner.lock().unwrap().handler = handler;
+    /// Switch the handler for both directions, packets already in the buffer are decoded with the
+    /// new handler.
+    pub fn set_packet_handler(&mut self, handler: PacketHandler) {
+        self.inner.lock().unwrap().handler = handler.clone();
+        self.handler = handler;
     }
 
+    /// If either the connection was dropped, or the peer won't send anything more.
     pub fn is_closed(&self) -> bool {
-        self.inner.lock().unwrap().stream.is_none()
+        self.inner.lock().unwrap().is_closed()
+    }
+
+    /// The peer closed its write side, packets can still be sent until the connection drops.
+    pub fn is_read_closed(&self) -> bool {
+        self.inner.lock().unwrap().read_closed
+    }
+
+    /// Why the connection was closed, None if it's still open or was closed with
+    /// [`Connection::close`].
+    pub fn disconnect_cause(&self) -> Option<DisconnectCause> {
+        self.inner.lock().unwrap().disconnect_cause
     }
 
+    /// Packets that are still queued are written if they can be without blocking, the rest are
+    /// dropped.
     pub fn close(&self) {
-        self.inner.lock().unwrap().stream = None;
+        let mut inner = self.inner.lock().unwrap();
+        let _ = inner.flush();
+        inner.stream = None;
+        inner.clear_outgoing();
+    }
+
+    /// Closes the connection, unlike [`Connection::close`] only urgent packets & the packet
+    /// that's partially written are kept, and those are given a short time to be written. Send
+    /// the last packet (e.g. a disconnect) with [`Connection::send_urgent`] so it isn't dropped.
+    pub fn close_with(&self, cause: DisconnectCause) {
+        let mut inner = self.inner.lock().unwrap();
+        // Set first, so a timeout while flushing doesn't replace the cause.
+        inner.disconnect_cause.get_or_insert(cause);
+        inner.flush_before_close();
+        inner.close(cause);
     }
 
     pub fn send(&self, packet: &impl ClientboundPacket) -> Result<(), ConnectionError> {
         self.sender().send(packet)
     }
 
+    /// See [`ConnectionSender::send_urgent`].
+    pub fn send_urgent(&self, packet: &impl ClientboundPacket) -> Result<(), ConnectionError> {
+        self.sender().send_urgent(packet)
+    }
+
     fn recieve_bytes(&mut self) -> Result<(), ConnectionError> {
         // TODO: What is best size for this?
         let mut buf = [0u8; 1024];
         let mut inner = self.inner.lock().unwrap();
+        inner.flush()?;
+        if inner.read_closed {
+            return Ok(());
+        }
         let Some(stream) = inner.stream.as_mut() else {
             return Ok(());
         };
         loop {
             match stream.read(&mut buf) {
                 Ok(0) => {
-                    inner.stream = None;
+                    inner.read_closed = true;
+                    inner
+                        .disconnect_cause
+                        .get_or_insert(DisconnectCause::Closed);
                     break;
                 }
                 Ok(n) => self.bytes.extend(&buf[..n]),
                 Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
                     break;
                 }
-                Err(err)
-                    if err.kind() == std::io::ErrorKind::BrokenPipe
-                        || err.kind() == std::io::ErrorKind::UnexpectedEof =>
-                {
-                    inner.stream = None;
-                    break;
-                }
-                Err(err) => return Err(err)?,
+                Err(err) => match DisconnectCause::from_io_error(&err) {
+                    Some(cause) => {
+                        inner.close(cause);
+                        break;
+                    }
+                    None => return Err(err)?,
+                },
             }
         }
         Ok(())
@@ -134,17 +394,13 @@ impl Connection {
             return Ok(None);
         }
 
-        self.bytes.drain(..size_bytes);
-        let encoded: Vec<u8> = self.bytes.drain(..size as usize).collect();
-
-        let handler = self.inner.lock().unwrap().handler.clone();
-        let decoded = handler.read(&encoded)?;
+        // Decode directly from the buffer, it's already contiguous from reading the size.
+        let raw = self
+            .handler
+            .read_packet(&self.bytes.as_slices().0[size_bytes..size_bytes + size as usize])?;
+        self.bytes.drain(..size_bytes + size as usize);
 
-        let mut reader = std::io::Cursor::new(&decoded);
-        Ok(Some(RawPacket {
-            id: reader.read_varint()?,
-            data: reader.read_all()?,
-        }))
+        Ok(Some(raw))
     }
 
     pub fn recieve_into<T>(&mut self) -> Result<Option<T>, ConnectionError>
@@ -154,3 +410,196 @@ impl Connection {
         self.recieve().map(|i| i.map(T::try_from).transpose())?
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Read as _,
+        net::{Shutdown, TcpListener, TcpStream},
+    };
+
+    use crate::{
+        packet::{
+            handler::{PacketHandler, ZlibPacketHandler},
+            ClientboundPacket, Connection, ConnectionError, DisconnectCause, RawPacket,
+        },
+        test_util::{connection_pair, poll_until},
+    };
+
+    struct Goodbye;
+
+    impl ClientboundPacket for Goodbye {
+        const CLIENTBOUND_ID: i32 = 0x1D;
+
+        fn packet_write(&self, mut writer: impl std::io::Write) -> Result<(), ConnectionError> {
+            writer.write_all(b"bye")?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_half_close_can_send() -> Result<(), ConnectionError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let mut server = Connection::new(listener.accept()?.0)?;
+
+        client.shutdown(Shutdown::Write)?;
+        poll_until(|| {
+            assert!(server.recieve()?.is_none());
+            Ok::<_, ConnectionError>(server.is_read_closed().then_some(()))
+        })?;
+        assert!(server.is_closed());
+
+        server.send(&Goodbye)?;
+        drop(server);
+
+        let mut bytes = Vec::new();
+        client.read_to_end(&mut bytes)?;
+        let expected = RawPacket::new(0x1D, b"bye".to_vec().into_boxed_slice()).into_bytes();
+        assert_eq!(bytes[0] as usize, expected.len());
+        assert_eq!(&bytes[1..], &*expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_disconnect_cause() -> Result<(), ConnectionError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let client = TcpStream::connect(listener.local_addr()?)?;
+        let mut server = Connection::new(listener.accept()?.0)?;
+
+        // Closing with unread data sends a reset instead of a normal close.
+        server.send(&Goodbye)?;
+        // Blocks until the data arrives.
+        client.peek(&mut [0])?;
+        drop(client);
+        poll_until(|| {
+            assert!(server.recieve()?.is_none());
+            Ok::<_, ConnectionError>(server.is_closed().then_some(()))
+        })?;
+        assert_eq!(server.disconnect_cause(), Some(DisconnectCause::Reset));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_handler() -> Result<(), ConnectionError> {
+        let (mut server, mut client) = connection_pair()?;
+
+        for _ in 0..100 {
+            client.send(&Goodbye)?;
+        }
+        client.set_packet_handler(PacketHandler::Zlib(ZlibPacketHandler::new(256, 6)));
+        for _ in 0..100 {
+            client.send(&Goodbye)?;
+        }
+
+        let expected = RawPacket::new(0x1D, b"bye".to_vec().into_boxed_slice());
+        for _ in 0..100 {
+            assert_eq!(poll_until(|| server.recieve())?, expected);
+        }
+        // The compressed packets may already be buffered, switching still applies to them.
+        server.set_packet_handler(PacketHandler::Zlib(ZlibPacketHandler::new(256, 6)));
+        for _ in 0..100 {
+            assert_eq!(poll_until(|| server.recieve())?, expected);
+        }
+        assert_eq!(server.recieve()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_urgent_not_delayed() -> Result<(), ConnectionError> {
+        const FRAMES: usize = 128;
+
+        let (mut server, mut client) = connection_pair()?;
+
+        // Way more than fits in the socket buffers, so most of it gets queued.
+        let large = RawPacket::new(0x27, vec![0u8; 256 * 1024].into_boxed_slice());
+        let sender = server.sender();
+        std::thread::spawn(move || -> Result<(), ConnectionError> {
+            for _ in 0..FRAMES {
+                sender.send_raw(large.clone())?;
+            }
+            Ok(())
+        })
+        .join()
+        .unwrap()?;
+        server.send_urgent(&Goodbye)?;
+
+        let mut frames_before = 0;
+        poll_until(|| {
+            server.recieve()?;
+            while let Some(packet) = client.recieve()? {
+                if packet.id == 0x1D {
+                    return Ok(Some(()));
+                }
+                frames_before += 1;
+            }
+            Ok::<_, ConnectionError>(None)
+        })?;
+        assert!(
+            frames_before < FRAMES / 2,
+            "{} frames before",
+            frames_before
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_buffer_full() -> Result<(), ConnectionError> {
+        let (server, _client) = connection_pair()?;
+        let server = server.with_max_outgoing_bytes(1024 * 1024);
+
+        // The client never reads, so this piles up once the socket buffers are full.
+        let large = RawPacket::new(0x27, vec![0u8; 256 * 1024].into_boxed_slice());
+        for _ in 0..256 {
+            server.sender().send_raw(large.clone())?;
+            if server.is_closed() {
+                break;
+            }
+        }
+        assert!(server.is_closed());
+        assert_eq!(
+            server.disconnect_cause(),
+            Some(DisconnectCause::SendBufferFull)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_close_with_sends_urgent() -> Result<(), ConnectionError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let server = Connection::new(listener.accept()?.0)?;
+
+        let large = RawPacket::new(0x27, vec![0u8; 256 * 1024].into_boxed_slice());
+        for _ in 0..64 {
+            server.sender().send_raw(large.clone())?;
+        }
+        server.send_urgent(&Goodbye)?;
+
+        let reader = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+            let mut bytes = Vec::new();
+            client.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        });
+        server.close_with(DisconnectCause::Kicked);
+        drop(server);
+        let bytes = reader.join().unwrap()?;
+
+        // Queued frames are dropped, but the urgent one is still written after whatever frame
+        // was partially written.
+        let expected = RawPacket::new(0x1D, b"bye".to_vec().into_boxed_slice()).into_bytes();
+        assert_eq!(
+            bytes[bytes.len() - expected.len() - 1] as usize,
+            expected.len()
+        );
+        assert!(bytes.ends_with(&expected));
+        assert!(bytes.len() < 64 * 256 * 1024);
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ pub mod zlib;
 
 use std::fmt::Debug;
 
-use super::ConnectionError;
+use super::{ConnectionError, RawPacket};
 
 pub use uncompressed::*;
 pub use zlib::*;
@@ -32,4 +32,91 @@ impl PacketHandler {
             PacketHandler::Zlib(zlib_packet_handler) => zlib_packet_handler.read(buf),
         }
     }
+
+    /// Decodes the packet directly, without any intermediate buffers.
+    pub fn read_packet(&self, buf: &[u8]) -> Result<RawPacket, ConnectionError> {
+        match self {
+            PacketHandler::Uncompressed(uncompressed_packet_handler) => {
+                uncompressed_packet_handler.read_packet(buf)
+            }
+            PacketHandler::Zlib(zlib_packet_handler) => zlib_packet_handler.read_packet(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        io::Write as _,
+    };
+
+    use crate::{
+        packet::{
+            handler::{PacketHandler, ZlibPacketHandler},
+            ConnectionError, RawPacket, WriteExtPacket as _,
+        },
+        ReadExt as _,
+    };
+
+    /// Counts allocations on the current thread, so that other tests don't interfere.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|a| a.set(a.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+        let start = ALLOCATIONS.with(|a| a.get());
+        let result = f();
+        (ALLOCATIONS.with(|a| a.get()) - start, result)
+    }
+
+    #[test]
+    fn test_read_packet_allocations() -> Result<(), ConnectionError> {
+        let data = (0..1_000_000u32)
+            .map(|i| (i % 251) as u8)
+            .collect::<Box<[u8]>>();
+        let raw = RawPacket::new(0x27, data.clone());
+
+        let handler = PacketHandler::Zlib(ZlibPacketHandler::new(256, 4));
+        let bytes = raw.clone().into_bytes();
+        let mut encoded = Vec::new();
+        encoded.write_varint(bytes.len() as i32)?;
+        let mut encoder = flate2::write::ZlibEncoder::new(encoded, flate2::Compression::new(4));
+        encoder.write_all(&bytes)?;
+        let encoded = encoder.finish()?;
+
+        let (buffered_allocations, buffered) = count_allocations(|| {
+            let decoded = handler.read(&encoded)?;
+            RawPacket::from_bytes(&std::io::Cursor::new(decoded).read_all()?)
+        });
+        let (direct_allocations, direct) = count_allocations(|| handler.read_packet(&encoded));
+
+        assert_eq!(buffered?, raw);
+        assert_eq!(direct?, raw);
+        assert!(direct_allocations < buffered_allocations);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use crate::packet::ConnectionError;
+use crate::packet::{ConnectionError, RawPacket};
 
 #[derive(Debug, Clone)]
 pub struct UncompressedPacketHandler;
@@ -11,4 +11,8 @@ impl UncompressedPacketHandler {
     pub fn read(&self, buf: &[u8]) -> Result<Box<[u8]>, ConnectionError> {
         Ok(buf.to_owned().into_boxed_slice())
     }
+
+    pub fn read_packet(&self, buf: &[u8]) -> Result<RawPacket, ConnectionError> {
+        RawPacket::from_bytes(buf)
+    }
 }
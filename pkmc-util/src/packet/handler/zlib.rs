@@ -1,7 +1,9 @@
-use std::io::Write as _;
+use std::io::{Read as _, Write as _};
 
 use crate::{
-    packet::{ConnectionError, ReadExtPacket, WriteExtPacket},
+    packet::{
+        reader::read_varint_ret_bytes, ConnectionError, RawPacket, ReadExtPacket, WriteExtPacket,
+    },
     ReadExt,
 };
 
@@ -49,4 +51,16 @@ impl ZlibPacketHandler {
             _uncompressed_size => Ok(flate2::read::ZlibDecoder::new(reader).read_all()?),
         }
     }
+
+    pub fn read_packet(&self, buf: &[u8]) -> Result<RawPacket, ConnectionError> {
+        let (size_bytes, uncompressed_size) = read_varint_ret_bytes(buf)?;
+        match uncompressed_size {
+            0 => RawPacket::from_bytes(&buf[size_bytes..]),
+            uncompressed_size => {
+                let mut decoded = Vec::with_capacity(uncompressed_size as usize);
+                flate2::read::ZlibDecoder::new(&buf[size_bytes..]).read_to_end(&mut decoded)?;
+                RawPacket::from_vec(decoded)
+            }
+        }
+    }
 }
@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng as _;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Client bad keep alive response (No response, wrong id, or responded when not expected)")]
+pub struct KeepAliveError;
+
+/// Keeps track of keep alive packets, for any connection state that has them.
+///
+/// A keep alive is sent every interval, and the client must respond to it before the next one.
+#[derive(Debug)]
+pub struct KeepAliveTracker {
+    interval: Duration,
+    last_sent: Instant,
+    pending: Option<i64>,
+}
+
+impl KeepAliveTracker {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_sent: Instant::now(),
+            pending: None,
+        }
+    }
+
+    /// If the client didn't respond to the last keep alive before the next one was due.
+    pub fn is_timed_out(&self, now: Instant) -> bool {
+        self.pending.is_some() && now.duration_since(self.last_sent) >= self.interval
+    }
+
+    /// Id of a new keep alive to send, if one is due.
+    pub fn should_send(&mut self, now: Instant) -> Option<i64> {
+        if self.pending.is_some() || now.duration_since(self.last_sent) < self.interval {
+            return None;
+        }
+        let id = rand::thread_rng().gen();
+        self.last_sent = now;
+        self.pending = Some(id);
+        Some(id)
+    }
+
+    /// Client responded to a keep alive.
+    pub fn acknowledge(&mut self, id: i64) -> Result<(), KeepAliveError> {
+        match self.pending.take() {
+            Some(pending) if pending == id => Ok(()),
+            _ => Err(KeepAliveError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::packet::{KeepAliveError, KeepAliveTracker};
+
+    const INTERVAL: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_keep_alive_correct_id() {
+        let mut tracker = KeepAliveTracker::new(INTERVAL);
+        let start = Instant::now();
+        assert_eq!(tracker.should_send(start), None);
+        let id = tracker.should_send(start + INTERVAL).unwrap();
+        // Only one keep alive at a time.
+        assert_eq!(tracker.should_send(start + INTERVAL), None);
+        assert_eq!(tracker.acknowledge(id), Ok(()));
+        assert!(!tracker.is_timed_out(start + INTERVAL * 3));
+        assert!(tracker.should_send(start + INTERVAL * 2).is_some());
+    }
+
+    #[test]
+    fn test_keep_alive_wrong_id() {
+        let mut tracker = KeepAliveTracker::new(INTERVAL);
+        let start = Instant::now();
+        // Responded when not expected.
+        assert_eq!(tracker.acknowledge(0), Err(KeepAliveError));
+        let id = tracker.should_send(start + INTERVAL).unwrap();
+        assert_eq!(tracker.acknowledge(id.wrapping_add(1)), Err(KeepAliveError));
+    }
+
+    #[test]
+    fn test_keep_alive_timeout() {
+        let mut tracker = KeepAliveTracker::new(INTERVAL);
+        let start = Instant::now();
+        assert!(tracker.should_send(start + INTERVAL).is_some());
+        assert!(!tracker.is_timed_out(start + INTERVAL + INTERVAL / 2));
+        assert!(tracker.is_timed_out(start + INTERVAL * 2));
+    }
+}
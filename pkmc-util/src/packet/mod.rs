@@ -1,11 +1,14 @@
 mod connection;
 pub mod handler;
+mod keep_alive;
+#[allow(clippy::module_inception)]
 mod packet;
 mod paletted_container;
 mod reader;
 mod writer;
 
 pub use connection::*;
+pub use keep_alive::*;
 pub use packet::*;
 pub use paletted_container::*;
 pub use reader::*;
@@ -23,6 +26,9 @@ pub enum ConnectionError {
     UnsupportedPacket(String, i32),
     #[error("Invalid raw packet ID for parser (expected: {0}, found: {1})")]
     InvalidRawPacketIDForParser(i32, i32),
+    /// A varint that should be one of a set of values was something else.
+    #[error("Invalid value {value} for {packet}")]
+    InvalidEnumValue { packet: &'static str, value: i32 },
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -73,3 +79,63 @@ impl BitSet {
         self.data
     }
 }
+
+/// A bit set with a length known at compile time, encoded without a length prefix.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FixedBitSet<const N: usize> {
+    data: Box<[u8]>,
+}
+
+impl<const N: usize> Default for FixedBitSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FixedBitSet<N> {
+    pub const BYTES: usize = N.div_ceil(8);
+
+    pub fn new() -> Self {
+        Self {
+            data: vec![0; Self::BYTES].into_boxed_slice(),
+        }
+    }
+
+    pub fn from_bytes(bytes: Box<[u8]>) -> Option<Self> {
+        if bytes.len() != Self::BYTES {
+            return None;
+        }
+        Some(Self { data: bytes })
+    }
+
+    pub fn length(&self) -> usize {
+        N
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= N {
+            None
+        } else {
+            Some((self.data[index >> 3] & (1 << (index & 0b111))) != 0)
+        }
+    }
+
+    pub fn set(&mut self, index: usize, set: bool) {
+        if index < N {
+            if set {
+                self.data[index >> 3] |= 1 << (index & 0b111);
+            } else {
+                self.data[index >> 3] &= !(1 << (index & 0b111));
+            }
+        }
+    }
+
+    /// Indices of all set bits, in ascending order.
+    pub fn iter_set(&self) -> impl Iterator<Item = usize> + use<'_, N> {
+        (0..N).filter(|i| self.get(*i).unwrap())
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
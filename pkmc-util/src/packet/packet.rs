@@ -1,6 +1,6 @@
 use std::io::{Read, Write};
 
-use super::{ConnectionError, WriteExtPacket};
+use super::{reader::read_varint_ret_bytes, ConnectionError, WriteExtPacket};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct RawPacket {
@@ -13,6 +13,25 @@ impl RawPacket {
         Self { id, data }
     }
 
+    /// Parses the packet id & copies the rest as data.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ConnectionError> {
+        let (id_bytes, id) = read_varint_ret_bytes(bytes)?;
+        Ok(Self {
+            id,
+            data: bytes[id_bytes..].into(),
+        })
+    }
+
+    /// Parses the packet id & reuses the allocation for data.
+    pub fn from_vec(mut bytes: Vec<u8>) -> Result<Self, ConnectionError> {
+        let (id_bytes, id) = read_varint_ret_bytes(bytes.as_slice())?;
+        bytes.drain(..id_bytes);
+        Ok(Self {
+            id,
+            data: bytes.into_boxed_slice(),
+        })
+    }
+
     pub fn into_bytes(self) -> Box<[u8]> {
         let mut data = Vec::new();
         data.write_varint(self.id).unwrap();
@@ -45,14 +45,15 @@ impl IndirectPalettedContainer<'_> {
                 Ok::<_, std::io::Error>(())
             })?;
 
-        let mut packed = PackedArray::new(self.bpe as u8, self.values.len());
-        let remaining = packed.consume(
-            self.values
+        let packed = PackedArray::from_indices(
+            &self
+                .values
                 .iter()
-                .map(|value| self.palette.get(value).cloned().unwrap() as u64),
-        );
-        debug_assert!(remaining.count() == 0);
-        let packed = packed.into_inner();
+                .map(|value| self.palette.get(value).cloned().unwrap() as u64)
+                .collect::<Vec<_>>(),
+            self.bpe as u8,
+        )
+        .into_inner();
 
         writer.write_varint(packed.len() as i32)?;
         packed.iter().try_for_each(|v| {
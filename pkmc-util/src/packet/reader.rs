@@ -1,6 +1,8 @@
 use std::io::Read;
 
-use crate::{Position, ReadExt as _, UUID};
+use crate::{nbt::NBT, Position, ReadExt as _, UUID};
+
+use super::FixedBitSet;
 
 pub fn read_varint_ret_bytes(mut reader: impl Read) -> std::io::Result<(usize, i32)> {
     let mut bytes = 0;
@@ -41,6 +43,11 @@ pub trait ReadExtPacket {
     fn read_bool(&mut self) -> std::io::Result<bool>;
     fn read_uuid(&mut self) -> std::io::Result<UUID>;
     fn read_position(&mut self) -> std::io::Result<Position>;
+    /// Varint length prefixed array of positions.
+    fn read_position_array(&mut self) -> std::io::Result<Vec<Position>>;
+    fn read_fixed_bitset<const N: usize>(&mut self) -> std::io::Result<FixedBitSet<N>>;
+    /// Network NBT, without the root name.
+    fn read_nbt(&mut self) -> std::io::Result<NBT>;
 }
 
 impl<T: Read> ReadExtPacket for T {
@@ -50,13 +57,8 @@ impl<T: Read> ReadExtPacket for T {
 
     fn read_string(&mut self) -> std::io::Result<String> {
         let length = self.read_varint()?;
-        let buf = self.read_var(
-            length
-                .try_into()
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
-        )?;
-        let str = String::from_utf8(buf.to_vec())
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        let buf = self.read_var(length.try_into().map_err(std::io::Error::other)?)?;
+        let str = String::from_utf8(buf.to_vec()).map_err(std::io::Error::other)?;
         Ok(str)
     }
 
@@ -64,10 +66,7 @@ impl<T: Read> ReadExtPacket for T {
         match u8::from_le_bytes(self.read_const()?) {
             0 => Ok(false),
             1 => Ok(true),
-            _ => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Invalid boolean",
-            )),
+            _ => Err(std::io::Error::other("Invalid boolean")),
         }
     }
 
@@ -83,11 +82,37 @@ impl<T: Read> ReadExtPacket for T {
             z: (v << 26 >> 38) as i32,
         })
     }
+
+    fn read_position_array(&mut self) -> std::io::Result<Vec<Position>> {
+        let length: usize = self
+            .read_varint()?
+            .try_into()
+            .map_err(|_| std::io::Error::other("Negative position array length"))?;
+        // Don't trust the length for preallocating.
+        let mut positions = Vec::with_capacity(length.min(1024));
+        for _ in 0..length {
+            positions.push(self.read_position()?);
+        }
+        Ok(positions)
+    }
+
+    fn read_fixed_bitset<const N: usize>(&mut self) -> std::io::Result<FixedBitSet<N>> {
+        Ok(FixedBitSet::from_bytes(self.read_var(FixedBitSet::<N>::BYTES)?).unwrap())
+    }
+
+    fn read_nbt(&mut self) -> std::io::Result<NBT> {
+        NBT::read_network(self).map_err(std::io::Error::other)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::packet::ReadExtPacket as _;
+    use crate::{
+        nbt::NBT,
+        nbt_compound,
+        packet::{FixedBitSet, ReadExtPacket as _, WriteExtPacket as _},
+        Position,
+    };
 
     fn create_reader(data: &[u8]) -> std::io::Cursor<&[u8]> {
         std::io::Cursor::new(data)
@@ -110,4 +135,66 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn position_array() -> std::io::Result<()> {
+        let positions = vec![
+            Position::new(0, 0, 0),
+            Position::new(-1, -64, -1),
+            Position::new(33554431, 2047, -33554432),
+            Position::new(-1234, 319, 5678),
+        ];
+        let mut data = Vec::new();
+        data.write_position_array(&positions)?;
+        assert_eq!(create_reader(&data).read_position_array()?, positions);
+
+        assert!(create_reader(&[0x00]).read_position_array()?.is_empty());
+        // Negative length
+        assert!(create_reader(&[0xff, 0xff, 0xff, 0xff, 0x0f])
+            .read_position_array()
+            .is_err());
+        // Truncated
+        assert!(create_reader(&[0x02, 0, 0, 0, 0, 0, 0, 0, 0])
+            .read_position_array()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn nbt() -> std::io::Result<()> {
+        let nbt = nbt_compound!["text" => NBT::String("Hello".to_owned()),];
+        let mut data = Vec::new();
+        data.write_nbt(&nbt)?;
+        data.write_bool(true)?;
+
+        let mut reader = create_reader(&data);
+        assert_eq!(reader.read_nbt()?, nbt);
+        assert!(reader.read_bool()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_bitset() -> std::io::Result<()> {
+        let mut bitset = FixedBitSet::<20>::new();
+        bitset.set(0, true);
+        bitset.set(9, true);
+        bitset.set(19, true);
+        bitset.set(20, true);
+        assert_eq!(bitset.get(9), Some(true));
+        assert_eq!(bitset.get(10), Some(false));
+        assert_eq!(bitset.get(20), None);
+        bitset.set(9, false);
+        assert_eq!(bitset.iter_set().collect::<Vec<_>>(), vec![0, 19]);
+
+        let mut data = Vec::new();
+        data.write_fixed_bitset(&bitset)?;
+        assert_eq!(data, [0b00000001, 0b00000000, 0b00001000]);
+        let mut reader = create_reader(&data);
+        assert_eq!(reader.read_fixed_bitset::<20>()?, bitset);
+        assert_eq!(reader.position() as usize, data.len());
+
+        Ok(())
+    }
 }
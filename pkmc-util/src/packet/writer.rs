@@ -2,7 +2,7 @@ use std::io::Write;
 
 use crate::{nbt::NBT, Position, Transmutable, UUID};
 
-use super::BitSet;
+use super::{BitSet, FixedBitSet};
 
 pub fn write_varint(mut writer: impl Write, value: i32) -> std::io::Result<()> {
     let mut value: u32 = value.transmute();
@@ -33,6 +33,13 @@ pub const fn varint_size(mut value: i32) -> i32 {
     }
 }
 
+/// If the position fits in the packed format, x & z as signed 26 bits, y as signed 12 bits.
+fn is_packable_position(position: &Position) -> bool {
+    (-(1 << 25)..(1 << 25)).contains(&position.x)
+        && (-(1 << 11)..(1 << 11)).contains(&position.y)
+        && (-(1 << 25)..(1 << 25)).contains(&position.z)
+}
+
 pub trait WriteExtPacket {
     fn write_varint(&mut self, value: i32) -> std::io::Result<()>;
     fn write_varlong(&mut self, value: i64) -> std::io::Result<()>;
@@ -40,7 +47,14 @@ pub trait WriteExtPacket {
     fn write_bool(&mut self, bool: bool) -> std::io::Result<()>;
     fn write_uuid(&mut self, uuid: &UUID) -> std::io::Result<()>;
     fn write_position(&mut self, position: &Position) -> std::io::Result<()>;
+    /// Varint length prefixed array of positions, errors if any position doesn't fit in the
+    /// packed 26/12/26 bits.
+    fn write_position_array(&mut self, positions: &[Position]) -> std::io::Result<()>;
     fn write_bitset(&mut self, bitset: &BitSet) -> std::io::Result<()>;
+    fn write_fixed_bitset<const N: usize>(
+        &mut self,
+        bitset: &FixedBitSet<N>,
+    ) -> std::io::Result<()>;
     fn write_nbt(&mut self, nbt: &NBT) -> std::io::Result<()>;
 }
 
@@ -68,11 +82,7 @@ impl<T: Write> WriteExtPacket for T {
 
     fn write_string(&mut self, string: &str) -> std::io::Result<()> {
         let buf = string.as_bytes();
-        self.write_varint(
-            buf.len()
-                .try_into()
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
-        )?;
+        self.write_varint(buf.len().try_into().map_err(std::io::Error::other)?)?;
         self.write_all(buf)?;
         Ok(())
     }
@@ -95,12 +105,25 @@ impl<T: Write> WriteExtPacket for T {
         Ok(())
     }
 
+    fn write_position_array(&mut self, positions: &[Position]) -> std::io::Result<()> {
+        if let Some(position) = positions.iter().find(|p| !is_packable_position(p)) {
+            return Err(std::io::Error::other(format!(
+                "Position {:?} out of packable range",
+                position
+            )));
+        }
+        self.write_varint(positions.len().try_into().map_err(std::io::Error::other)?)?;
+        positions
+            .iter()
+            .try_for_each(|position| self.write_position(position))
+    }
+
     fn write_bitset(&mut self, bitset: &BitSet) -> std::io::Result<()> {
         self.write_varint(
             bitset
                 .num_longs()
                 .try_into()
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
+                .map_err(std::io::Error::other)?,
         )?;
         bitset
             .longs_iter()
@@ -109,11 +132,16 @@ impl<T: Write> WriteExtPacket for T {
         Ok(())
     }
 
+    fn write_fixed_bitset<const N: usize>(
+        &mut self,
+        bitset: &FixedBitSet<N>,
+    ) -> std::io::Result<()> {
+        self.write_all(bitset.bytes())?;
+        Ok(())
+    }
+
     fn write_nbt(&mut self, nbt: &NBT) -> std::io::Result<()> {
-        self.write_all(
-            &nbt.to_bytes_network()
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
-        )?;
+        self.write_all(&nbt.to_bytes_network().map_err(std::io::Error::other)?)?;
         Ok(())
     }
 }
@@ -151,4 +179,16 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn position_array_out_of_range() {
+        let mut data = Vec::new();
+        assert!(data
+            .write_position_array(&[Position::new(0, 0, 0), Position::new(1 << 25, 0, 0)])
+            .is_err());
+        assert!(data
+            .write_position_array(&[Position::new(0, 2048, 0)])
+            .is_err());
+        assert!(data.is_empty());
+    }
 }
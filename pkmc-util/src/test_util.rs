@@ -0,0 +1,64 @@
+//! Helpers for tests that talk over real connections, enabled with the "test-util" feature.
+
+use std::net::{TcpListener, TcpStream};
+
+use crate::packet::{Connection, ConnectionError, ConnectionSender, RawPacket};
+
+/// How long [`poll_until`] waits before giving up.
+pub const POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Packet id that [`recieve_all`] uses to know that everything before it was recieved.
+const MARKER_ID: i32 = i32::MAX;
+
+/// Connected (server, client) pair over localhost.
+pub fn connection_pair() -> Result<(Connection, Connection), ConnectionError> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let client = TcpStream::connect(listener.local_addr()?)?;
+    let (server, _) = listener.accept()?;
+    Ok((Connection::new(server)?, Connection::new(client)?))
+}
+
+/// Calls the function until it returns a value, panics if that takes longer than
+/// [`POLL_TIMEOUT`].
+pub fn poll_until<T, E>(mut f: impl FnMut() -> Result<Option<T>, E>) -> Result<T, E> {
+    let start = std::time::Instant::now();
+    loop {
+        if let Some(value) = f()? {
+            return Ok(value);
+        }
+        assert!(start.elapsed() < POLL_TIMEOUT, "Timed out polling");
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+/// Recieves packets until one with the id, other packets are skipped.
+pub fn recieve_id(client: &mut Connection, id: i32) -> Result<RawPacket, ConnectionError> {
+    poll_until(|| {
+        while let Some(raw) = client.recieve()? {
+            if raw.id == id {
+                return Ok(Some(raw));
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Every packet that was sent through the sender before this was called.
+pub fn recieve_all(
+    sender: &ConnectionSender,
+    client: &mut Connection,
+) -> Result<Vec<RawPacket>, ConnectionError> {
+    sender.send_raw(RawPacket::new(MARKER_ID, Box::new([])))?;
+    let mut packets = Vec::new();
+    poll_until(|| {
+        sender.flush()?;
+        while let Some(raw) = client.recieve()? {
+            if raw.id == MARKER_ID {
+                return Ok(Some(()));
+            }
+            packets.push(raw);
+        }
+        Ok::<_, ConnectionError>(None)
+    })?;
+    Ok(packets)
+}
@@ -9,13 +9,13 @@ macro_rules! primitive_int_int_transmutable {
     ($a:ty, $b:ty) => {
         impl Transmutable<$b> for $a {
             fn transmute(self) -> $b {
-                unsafe { std::mem::transmute::<$a, $b>(self) }
+                self as $b
             }
         }
 
         impl Transmutable<$a> for $b {
             fn transmute(self) -> $a {
-                unsafe { std::mem::transmute::<$b, $a>(self) }
+                self as $a
             }
         }
     };
@@ -26,16 +26,16 @@ macro_rules! primitive_int_float_transmutable {
     ($int:ty, $int_uint:ty, $float:ty) => {
         impl Transmutable<$float> for $int {
             fn transmute(self) -> $float {
-                #[allow(clippy::useless_transmute)]
-                <$float>::from_bits(unsafe { std::mem::transmute::<$int, $int_uint>(self) })
+                #[allow(clippy::unnecessary_cast)]
+                <$float>::from_bits(self as $int_uint)
             }
         }
 
         impl Transmutable<$int> for $float {
             fn transmute(self) -> $int {
-                #[allow(clippy::useless_transmute)]
-                unsafe {
-                    std::mem::transmute::<$int_uint, $int>(self.to_bits())
+                #[allow(clippy::unnecessary_cast)]
+                {
+                    self.to_bits() as $int
                 }
             }
         }
@@ -51,20 +51,94 @@ primitive_int_int_transmutable!(u64, i64);
 primitive_int_float_transmutable!(u64, u64, f64);
 primitive_int_float_transmutable!(i64, u64, f64);
 
+// All primitive transmutations above are between types of the same size & alignment, so
+// reinterpreting slices of them is fine.
+
+#[cfg(not(feature = "bytemuck"))]
 impl<I: Transmutable<O>, O> Transmutable<Box<[O]>> for Box<[I]> {
     fn transmute(self) -> Box<[O]> {
         unsafe { std::mem::transmute(self) }
     }
 }
 
+#[cfg(not(feature = "bytemuck"))]
 impl<'a, I: Transmutable<O>, O> Transmutable<&'a [O]> for &'a [I] {
     fn transmute(self) -> &'a [O] {
         unsafe { std::mem::transmute(self) }
     }
 }
 
+#[cfg(not(feature = "bytemuck"))]
 impl<'a, I: Transmutable<O>, O> Transmutable<&'a mut [O]> for &'a mut [I] {
     fn transmute(self) -> &'a mut [O] {
         unsafe { std::mem::transmute(self) }
     }
 }
+
+#[cfg(feature = "bytemuck")]
+impl<I: Transmutable<O> + bytemuck::Pod, O: bytemuck::Pod> Transmutable<Box<[O]>> for Box<[I]> {
+    fn transmute(self) -> Box<[O]> {
+        bytemuck::allocation::cast_slice_box(self)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'a, I: Transmutable<O> + bytemuck::Pod, O: bytemuck::Pod> Transmutable<&'a [O]> for &'a [I] {
+    fn transmute(self) -> &'a [O] {
+        bytemuck::cast_slice(self)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'a, I: Transmutable<O> + bytemuck::Pod, O: bytemuck::Pod> Transmutable<&'a mut [O]>
+    for &'a mut [I]
+{
+    fn transmute(self) -> &'a mut [O] {
+        bytemuck::cast_slice_mut(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Transmutable;
+
+    /// Reinterpreting a slice must give the same result as transmuting each element, with or
+    /// without the bytemuck feature.
+    fn assert_equivalent<I, O>(values: &[I])
+    where
+        I: Transmutable<O> + Copy + std::fmt::Debug,
+        O: Transmutable<I> + Copy + std::fmt::Debug + PartialEq,
+        for<'a> &'a [I]: Transmutable<&'a [O]>,
+        for<'a> &'a mut [I]: Transmutable<&'a mut [O]>,
+        Box<[I]>: Transmutable<Box<[O]>>,
+    {
+        let expected = values
+            .iter()
+            .map(|v| Transmutable::<O>::transmute(*v))
+            .collect::<Vec<O>>();
+        assert_eq!(Transmutable::<&[O]>::transmute(values), expected);
+        let mut copy = values.to_vec();
+        assert_eq!(
+            Transmutable::<&mut [O]>::transmute(copy.as_mut_slice()),
+            expected
+        );
+        let boxed: Box<[O]> = values.to_vec().into_boxed_slice().transmute();
+        assert_eq!(*boxed, *expected);
+    }
+
+    #[test]
+    fn test_slice_transmute() {
+        assert_equivalent::<u8, i8>(&[0, 1, 127, 128, 255]);
+        assert_equivalent::<i8, u8>(&[0, -1, i8::MIN, i8::MAX]);
+        assert_equivalent::<u16, i16>(&[0, 1, u16::MAX]);
+        assert_equivalent::<u32, i32>(&[0, 1, u32::MAX]);
+        assert_equivalent::<i32, f32>(&[0, i32::MIN, 1065353216, -1082130432]);
+        assert_equivalent::<u32, f32>(&[0, 1, 1065353216]);
+        assert_equivalent::<f32, i32>(&[0.0, -0.0, 1.5, f32::MAX]);
+        assert_equivalent::<u64, i64>(&[0, 1, u64::MAX, 1 << 63]);
+        assert_equivalent::<i64, u64>(&[0, -1, i64::MIN, i64::MAX]);
+        assert_equivalent::<i64, f64>(&[0, i64::MIN, 4607182418800017408]);
+        assert_equivalent::<u64, f64>(&[0, 4607182418800017408]);
+        assert_equivalent::<f64, u64>(&[0.0, -0.0, 1.5, f64::MIN]);
+    }
+}
@@ -27,6 +27,31 @@ impl ConfigImageFilteringMethod {
     }
 }
 
+/// Either a single MOTD, or a list of MOTDs to pick from.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ConfigMotdText {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl ConfigMotdText {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            ConfigMotdText::Single(text) => vec![text.clone()],
+            ConfigMotdText::Multiple(texts) => texts.clone(),
+        }
+    }
+}
+
+/// How a MOTD is picked for each server list ping.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum ConfigMotdSelection {
+    #[default]
+    Random,
+    RoundRobin,
+}
+
 fn config_default_brand() -> String {
     "Vulae/pkmc".to_owned()
 }
@@ -35,6 +60,30 @@ fn config_default_view_distance() -> u8 {
     12
 }
 
+fn config_default_simulation_distance() -> u8 {
+    6
+}
+
+fn config_default_spawn_chunks_radius() -> u8 {
+    2
+}
+
+fn config_default_autosave_interval() -> u64 {
+    300
+}
+
+/// A static entity that gets spawned on startup.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ConfigEntity {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub position: [f64; 3],
+    #[serde(default)]
+    pub yaw: f32,
+    #[serde(default)]
+    pub pitch: f32,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub address: String,
@@ -47,12 +96,40 @@ pub struct Config {
     pub world: PathBuf,
     #[serde(default = "config_default_view_distance", rename = "view-distance")]
     pub view_distance: u8,
+    /// Must be <= view distance.
+    #[serde(
+        default = "config_default_simulation_distance",
+        rename = "simulation-distance"
+    )]
+    pub simulation_distance: u8,
+    /// Chunks around spawn that are always loaded, 0 to disable.
+    #[serde(
+        default = "config_default_spawn_chunks_radius",
+        rename = "spawn-chunks-radius"
+    )]
+    pub spawn_chunks_radius: u8,
+    /// Seconds between saving the world, 0 to disable.
+    #[serde(
+        default = "config_default_autosave_interval",
+        rename = "autosave-interval"
+    )]
+    pub autosave_interval: u64,
     #[serde(rename = "motd-text")]
-    pub motd_text: Option<String>,
+    pub motd_text: Option<ConfigMotdText>,
+    #[serde(default, rename = "motd-selection")]
+    pub motd_selection: ConfigMotdSelection,
     #[serde(rename = "motd-icon")]
     pub motd_icon: Option<PathBuf>,
     #[serde(default, rename = "motd-icon-filtering-method")]
     pub motd_icon_filtering_method: ConfigImageFilteringMethod,
+    #[serde(default)]
+    pub entities: Vec<ConfigEntity>,
+    #[serde(default, rename = "debug-test-entity")]
+    pub debug_test_entity: bool,
+    #[serde(default, rename = "debug-log-unsupported-packets")]
+    pub debug_log_unsupported_packets: bool,
+    #[serde(default, rename = "debug-chunks")]
+    pub debug_chunks: bool,
 }
 
 impl Config {
@@ -78,6 +155,19 @@ impl Config {
         Ok(())
     }
 
+    /// Fixes invalid values, returning warnings for each value that was changed.
+    fn validate(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.simulation_distance > self.view_distance {
+            warnings.push(format!(
+                "simulation-distance ({}) is larger than view-distance ({}), clamping to {}",
+                self.simulation_distance, self.view_distance, self.view_distance,
+            ));
+            self.simulation_distance = self.view_distance;
+        }
+        warnings
+    }
+
     /// First file that is found is loaded as config.
     pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Config, Box<dyn Error>> {
         for path in paths {
@@ -87,6 +177,10 @@ impl Config {
                         let mut config: Config = toml::from_str(&str)?;
                         config.fix_paths(PathBuf::from(path.as_ref()))?;
                         config
+                            .validate()
+                            .iter()
+                            .for_each(|warning| println!("Config warning: {}", warning));
+                        config
                     })
                 }
                 Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
@@ -96,3 +190,70 @@ impl Config {
         Err("Could not find config file.".into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::config::{Config, ConfigMotdSelection, ConfigMotdText};
+
+    #[test]
+    fn test_motd_text() -> Result<(), toml::de::Error> {
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            motd-text = "Single"
+            "#,
+        )?;
+        assert_eq!(
+            config.motd_text.map(|m| m.to_vec()),
+            Some(vec!["Single".to_owned()])
+        );
+        assert_eq!(config.motd_selection, ConfigMotdSelection::Random);
+
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            motd-text = ["First", "Second"]
+            motd-selection = "RoundRobin"
+            "#,
+        )?;
+        assert_eq!(
+            config.motd_text,
+            Some(ConfigMotdText::Multiple(vec![
+                "First".to_owned(),
+                "Second".to_owned()
+            ]))
+        );
+        assert_eq!(config.motd_selection, ConfigMotdSelection::RoundRobin);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulation_distance_clamped() -> Result<(), toml::de::Error> {
+        let mut config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            view-distance = 8
+            simulation-distance = 4
+            "#,
+        )?;
+        assert!(config.validate().is_empty());
+        assert_eq!(config.simulation_distance, 4);
+
+        let mut config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            view-distance = 8
+            simulation-distance = 12
+            "#,
+        )?;
+        assert_eq!(config.validate().len(), 1);
+        assert_eq!(config.simulation_distance, 8);
+
+        Ok(())
+    }
+}
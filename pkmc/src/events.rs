@@ -0,0 +1,120 @@
+use std::fmt::Debug;
+
+use pkmc_defs::{
+    block::Block,
+    packet::play::{BlockFace, InteractKind},
+};
+use pkmc_util::{Position, Vec3};
+
+use crate::player::{Player, PlayerError};
+
+/// A chat message sent by the player.
+///
+/// By default the message is echoed back to the player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatEvent {
+    pub message: String,
+    pub cancelled: bool,
+}
+
+/// A command sent by the player, without the leading "/".
+///
+/// By default the player is told that the command is unknown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandEvent {
+    pub command: String,
+    pub cancelled: bool,
+}
+
+/// The player moved or rotated.
+///
+/// Cancelling keeps the player's previous position & rotation server side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveEvent {
+    pub position: Vec3<f64>,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub cancelled: bool,
+}
+
+/// The player right clicked on a block face.
+///
+/// By default nothing is placed, a listener may set [`BlockPlaceEvent::block`] to place a block.
+/// Cancelling resends the current block at [`BlockPlaceEvent::position`] to undo the client's
+/// prediction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPlaceEvent {
+    /// The block that was clicked on.
+    pub clicked: Position,
+    pub face: BlockFace,
+    /// The position that the block will be placed at.
+    pub position: Position,
+    pub block: Option<Block>,
+    pub cancelled: bool,
+}
+
+/// The player attacked or right clicked an entity.
+///
+/// Nothing happens by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractEvent {
+    pub entity_id: i32,
+    pub kind: InteractKind,
+    pub sneaking: bool,
+}
+
+/// A plugin message on a channel other than "minecraft:brand".
+///
+/// Messages on invalid channel names are dropped before reaching listeners.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginMessageEvent {
+    pub channel: String,
+    pub data: Box<[u8]>,
+}
+
+/// Hooks into [`Player`] packet handling.
+///
+/// Every listener sees every event, even if a previous listener cancelled it.
+/// Listeners are called in the order they were added.
+#[allow(unused_variables)]
+pub trait PlayerListener: Debug {
+    fn on_chat(&mut self, player: &mut Player, event: &mut ChatEvent) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    fn on_command(
+        &mut self,
+        player: &mut Player,
+        event: &mut CommandEvent,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    fn on_move(&mut self, player: &mut Player, event: &mut MoveEvent) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    fn on_block_place(
+        &mut self,
+        player: &mut Player,
+        event: &mut BlockPlaceEvent,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    fn on_plugin_message(
+        &mut self,
+        player: &mut Player,
+        event: &mut PluginMessageEvent,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    fn on_interact(
+        &mut self,
+        player: &mut Player,
+        event: &mut InteractEvent,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+}
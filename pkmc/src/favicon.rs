@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use base64::Engine as _;
+use thiserror::Error;
+
+use crate::config::ConfigImageFilteringMethod;
+
+/// Size of the favicon that the client expects.
+pub const FAVICON_SIZE: u32 = 64;
+/// Larger source images are quickly downscaled first, so the filtering method doesn't take ages.
+pub const MAX_SOURCE_SIZE: u32 = 1024;
+/// Images larger than this are refused, instead of allocating a huge amount of memory.
+const MAX_DECODE_SIZE: u32 = 16384;
+
+#[derive(Error, Debug)]
+pub enum FaviconError {
+    #[error("Failed to load motd-icon \"{}\", make sure the path exists and is a .png image: {source}", path.display())]
+    Load {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    #[error("Failed to encode motd-icon \"{}\": {source}", path.display())]
+    Encode {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+/// Loads & resizes the favicon, returning it as base64 encoded png.
+pub fn load_favicon(
+    path: &Path,
+    filtering_method: &ConfigImageFilteringMethod,
+) -> Result<String, FaviconError> {
+    let load_error = |source| FaviconError::Load {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut reader = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| load_error(err.into()))?;
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_SIZE);
+    limits.max_image_height = Some(MAX_DECODE_SIZE);
+    reader.limits(limits);
+    let mut img = reader.decode().map_err(load_error)?;
+    if img.width() != img.height() {
+        println!(
+            "Config warning: motd-icon \"{}\" is not square ({}x{}), it will be stretched",
+            path.display(),
+            img.width(),
+            img.height(),
+        );
+    }
+    if img.width() > MAX_SOURCE_SIZE || img.height() > MAX_SOURCE_SIZE {
+        println!(
+            "Config warning: motd-icon \"{}\" is very large ({}x{}), it should be {}x{}",
+            path.display(),
+            img.width(),
+            img.height(),
+            FAVICON_SIZE,
+            FAVICON_SIZE,
+        );
+        img = img.thumbnail_exact(
+            img.width().min(MAX_SOURCE_SIZE),
+            img.height().min(MAX_SOURCE_SIZE),
+        );
+    }
+    let img_resized = img.resize_exact(
+        FAVICON_SIZE,
+        FAVICON_SIZE,
+        filtering_method.to_image_rs_filtering_method(),
+    );
+    let mut png = std::io::Cursor::new(Vec::new());
+    img_resized
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|source| FaviconError::Encode {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    Ok(base64::prelude::BASE64_STANDARD.encode(png.into_inner()))
+}
+
+#[cfg(test)]
+mod test {
+    use base64::Engine as _;
+
+    use crate::{
+        config::ConfigImageFilteringMethod,
+        favicon::{load_favicon, FaviconError, FAVICON_SIZE},
+    };
+
+    #[test]
+    fn test_non_image_favicon() {
+        let path =
+            std::env::temp_dir().join(format!("pkmc-test-favicon-{}.png", std::process::id()));
+        std::fs::write(&path, "Not an image").unwrap();
+        let err = load_favicon(&path, &ConfigImageFilteringMethod::Nearest).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, FaviconError::Load { .. }));
+        let message = err.to_string();
+        assert!(message.contains("motd-icon"));
+        assert!(message.contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn test_large_favicon() {
+        let path = std::env::temp_dir().join(format!(
+            "pkmc-test-large-favicon-{}.png",
+            std::process::id()
+        ));
+        image::GrayImage::from_fn(1500, 1200, |x, y| image::Luma([(x ^ y) as u8]))
+            .save(&path)
+            .unwrap();
+        let favicon = load_favicon(&path, &ConfigImageFilteringMethod::Lanczos3);
+        std::fs::remove_file(&path).unwrap();
+
+        let png = base64::prelude::BASE64_STANDARD
+            .decode(favicon.unwrap())
+            .unwrap();
+        let img = image::load_from_memory_with_format(&png, image::ImageFormat::Png).unwrap();
+        assert_eq!((img.width(), img.height()), (FAVICON_SIZE, FAVICON_SIZE));
+    }
+}
@@ -1,137 +1,679 @@
 #![allow(unused)]
 
 mod config;
+mod events;
+mod favicon;
 mod player;
 
 use std::{
     error::Error,
-    net::TcpListener,
+    net::{SocketAddr, TcpListener},
     sync::{Arc, LazyLock, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 
-use base64::Engine as _;
-use config::Config;
-use pkmc_defs::{biome::Biome, registry::Registries};
+use config::{Config, ConfigEntity, ConfigMotdSelection};
+use favicon::load_favicon;
+use pkmc_defs::{
+    biome::Biome, entity::entity_type_id, registry::Registries,
+    text_component::convert_ampersand_formatting_codes,
+};
 use pkmc_server::{
-    entity_manager::{Entity, EntityManager},
-    world::{anvil::AnvilWorld, World},
-    ClientHandler,
+    broadcaster::Broadcaster,
+    entity_manager::{Entity, EntityBase, EntityManager},
+    player_registry::PlayerRegistry,
+    tick_timings::TickTimings,
+    world::{anvil::AnvilWorld, chunk_loader::ChunkPosition, DynWorld, ErasedWorld, World},
+    world_border::WorldBorder,
+    ClientHandler, ConnectionPhase, StatusDescriptions,
 };
-use pkmc_util::{normalize_identifier, packet::Connection, IdTable, IterRetain, UUID};
-use player::Player;
+use pkmc_util::{normalize_identifier, packet::Connection, IdTable, IterRetain, Vec3, UUID};
+use player::{Player, DEBUG_CHUNKS_TIME};
 
 pub static REGISTRIES: LazyLock<Registries> =
     LazyLock::new(|| serde_json::from_str(include_str!("./registry.json")).unwrap());
 
 #[derive(Debug, Clone)]
 pub struct ServerState {
-    pub world: Arc<Mutex<AnvilWorld>>,
+    pub world: Arc<Mutex<DynWorld>>,
     pub entities: Arc<Mutex<EntityManager>>,
+    pub broadcaster: Arc<Mutex<Broadcaster>>,
+    pub players: Arc<Mutex<PlayerRegistry>>,
+    pub tick_timings: Arc<Mutex<TickTimings>>,
+    pub world_border: Arc<Mutex<WorldBorder>>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let config = Config::load(&["pkmc.toml", "pkmc/pkmc.toml"])?;
+#[derive(Debug)]
+pub struct StaticEntity {
+    r#type: i32,
+}
 
-    let config_favicon = if let Some(icon_path) = config.motd_icon {
-        let img = image::open(icon_path)?;
-        let img_resized = img.resize_exact(
-            64,
-            64,
-            config
-                .motd_icon_filtering_method
-                .to_image_rs_filtering_method(),
-        );
-        let mut png = std::io::Cursor::new(Vec::new());
-        img_resized.write_to(&mut png, image::ImageFormat::Png)?;
-        let png_base64 = base64::prelude::BASE64_STANDARD.encode(png.into_inner());
-        Some(png_base64)
-    } else {
-        None
-    };
+impl Entity for StaticEntity {
+    fn r#type(&self) -> i32 {
+        self.r#type
+    }
+}
 
-    let biome_mapper: IdTable<Biome> = REGISTRIES
-        .get("minecraft:worldgen/biome")
-        .unwrap()
+fn spawn_config_entities(
+    entities: &mut EntityManager,
+    config_entities: &[ConfigEntity],
+) -> Result<Vec<EntityBase<StaticEntity>>, Box<dyn Error>> {
+    config_entities
         .iter()
-        .enumerate()
-        .map(|(i, (k, _v))| (normalize_identifier(k, "minecraft").into(), i as i32))
-        .collect();
-    let world = AnvilWorld::new(config.world, "minecraft:overworld", -4..=19, biome_mapper);
-    let state = ServerState {
-        world: Arc::new(Mutex::new(world)),
-        entities: Arc::new(Mutex::new(EntityManager::default())),
-    };
+        .map(|config_entity| {
+            let r#type = entity_type_id(&config_entity.r#type)
+                .ok_or_else(|| format!("Unknown entity type \"{}\"", config_entity.r#type))?;
+            let entity = entities.add_entity(StaticEntity { r#type }, UUID::new_v7());
+            let mut handler = entity.handler().lock().unwrap();
+            handler.set_position(Vec3::new(
+                config_entity.position[0],
+                config_entity.position[1],
+                config_entity.position[2],
+            ));
+            handler.set_rotation(config_entity.yaw, config_entity.pitch);
+            drop(handler);
+            Ok(entity)
+        })
+        .collect()
+}
+
+/// Kicks & removes players that are the same as a player that's joining, offline mode gives
+/// reconnecting players a new uuid so they're also matched by name.
+fn remove_previous_sessions(players: &mut Vec<Player>, uuid: &UUID, name: &str) {
+    players
+        .retain_returned(|player| player.uuid() != uuid && player.name() != name)
+        .into_iter()
+        .for_each(|mut player| {
+            // The previous connection is likely already broken, so errors are ignored.
+            let _ = player.kick("You logged in from another location");
+            println!(
+                "{} Disconnected (Logged in from another location)",
+                player.name()
+            );
+        });
+}
 
-    let listener = TcpListener::bind(config.address)?;
-    listener.set_nonblocking(true)?;
+/// Accepts connections & runs the game loop, see [`Server::tick`].
+struct Server {
+    config: Config,
+    listener: TcpListener,
+    state: ServerState,
+    status_descriptions: Option<StatusDescriptions>,
+    favicon: Option<String>,
+    clients: Vec<ClientHandler>,
+    players: Vec<Player>,
+    /// Entities are despawned when dropped.
+    static_entities: Vec<EntityBase<StaticEntity>>,
+    autosave_interval: Option<Duration>,
+    last_autosave: Instant,
+}
+
+impl Server {
+    fn new(config: Config, world: DynWorld) -> Result<Self, Box<dyn Error>> {
+        let favicon = config
+            .motd_icon
+            .as_ref()
+            .map(|icon_path| load_favicon(icon_path, &config.motd_icon_filtering_method))
+            .transpose()?;
+
+        let status_descriptions = config.motd_text.as_ref().map(|motd_text| {
+            let motds = motd_text
+                .to_vec()
+                .iter()
+                .map(|motd| convert_ampersand_formatting_codes(motd))
+                .collect::<Vec<_>>();
+            match config.motd_selection {
+                ConfigMotdSelection::Random => StatusDescriptions::random(motds),
+                ConfigMotdSelection::RoundRobin => StatusDescriptions::round_robin(motds),
+            }
+        });
 
-    println!("Server started on {}", listener.local_addr()?);
+        let state = ServerState {
+            world: Arc::new(Mutex::new(world)),
+            entities: Arc::new(Mutex::new(EntityManager::default())),
+            broadcaster: Arc::new(Mutex::new(Broadcaster::new())),
+            players: Arc::new(Mutex::new(PlayerRegistry::new())),
+            tick_timings: Arc::new(Mutex::new(TickTimings::new())),
+            world_border: Arc::new(Mutex::new(WorldBorder::new())),
+        };
 
-    let mut clients: Vec<ClientHandler> = Vec::new();
-    let mut players: Vec<Player> = Vec::new();
+        let listener = TcpListener::bind(&config.address)?;
+        listener.set_nonblocking(true)?;
 
-    // NOTE: Testing entity
-    #[derive(Debug)]
-    struct TestEntity;
-    impl Entity for TestEntity {
-        fn r#type(&self) -> i32 {
-            0
+        let mut static_entities =
+            spawn_config_entities(&mut state.entities.lock().unwrap(), &config.entities)?;
+        if config.debug_test_entity {
+            static_entities.push(
+                state
+                    .entities
+                    .lock()
+                    .unwrap()
+                    .add_entity(StaticEntity { r#type: 0 }, UUID::new_v7()),
+            );
         }
+
+        Ok(Self {
+            listener,
+            state,
+            status_descriptions,
+            favicon,
+            clients: Vec::new(),
+            players: Vec::new(),
+            static_entities,
+            autosave_interval: Some(Duration::from_secs(config.autosave_interval))
+                .filter(|interval| !interval.is_zero()),
+            last_autosave: Instant::now(),
+            config,
+        })
     }
-    let entity = state
-        .entities
-        .lock()
-        .unwrap()
-        .add_entity(TestEntity, UUID::new_v7());
-    std::mem::forget(entity);
 
-    loop {
-        std::thread::sleep(std::time::Duration::from_millis(1));
+    /// Saves the world if the autosave interval has passed, errors are logged instead of stopping
+    /// the server.
+    fn autosave(&mut self) {
+        let Some(interval) = self.autosave_interval else {
+            return;
+        };
+        if self.last_autosave.elapsed() < interval {
+            return;
+        }
+        self.last_autosave = Instant::now();
 
-        while let Ok((stream, _)) = listener.accept() {
+        let mut world = self.state.world.lock().unwrap();
+        if !world.has_unsaved_changes() {
+            return;
+        }
+        let save_start = Instant::now();
+        match world.save() {
+            Ok(()) => println!("Saved world in {}ms", save_start.elapsed().as_millis()),
+            Err(err) => println!("Failed to save world ({})", err),
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    fn tick(&mut self) -> Result<(), Box<dyn Error>> {
+        let tick_start = std::time::Instant::now();
+
+        while let Ok((stream, _)) = self.listener.accept() {
             let connection = Connection::new(stream)?;
             let mut client = ClientHandler::new(connection)
-                .with_brand(&config.brand)
-                .with_compression(config.compression_threshold, config.compression_level)
+                .with_brand(&self.config.brand)
+                .with_compression(
+                    self.config.compression_threshold,
+                    self.config.compression_level,
+                )
                 .with_registies(REGISTRIES.clone());
-            if let Some(status_description) = &config.motd_text {
-                client = client.with_status_description(status_description);
+            if let Some(status_descriptions) = &self.status_descriptions {
+                client = client.with_status_descriptions(status_descriptions.clone());
             }
-            if let Some(status_favicon) = &config_favicon {
+            if let Some(status_favicon) = &self.favicon {
                 client = client.with_status_favicon(status_favicon);
             }
-            clients.push(client);
+            self.clients.push(client);
         }
 
-        clients.iter_mut().try_for_each(|client| client.update())?;
+        self.clients.iter_mut().for_each(|client| {
+            if let Err(err) = client.update() {
+                let phase = client.closed_during().unwrap_or(client.phase());
+                println!("Client disconnected during {} ({})", phase, err);
+            }
+        });
 
-        clients
+        self.clients
             .retain_returned(|client| !client.is_finalized())
             .into_iter()
-            .flat_map(|player| player.finalized_play_state())
+            .flat_map(|client| {
+                // Errors are already logged, status pings close normally.
+                if let Some(phase @ (ConnectionPhase::Login | ConnectionPhase::Configuration)) =
+                    client
+                        .closed_during()
+                        .filter(|_| client.last_error().is_none())
+                {
+                    println!("Client disconnected during {}", phase);
+                }
+                client.finalized_play_state()
+            })
             .try_for_each(|player| {
+                let client_brand = player.client_brand;
+                let client_information = player.client_information;
+                let transferred = player.transferred;
+                remove_previous_sessions(&mut self.players, &player.player_id, &player.player_name);
                 let mut player = Player::new(
                     player.connection,
-                    state.clone(),
+                    self.state.clone(),
                     player.player_id,
                     player.player_name,
-                    config.view_distance,
+                    self.config.view_distance,
+                    self.config.simulation_distance,
                 )?;
+                player.set_log_unsupported_packets(self.config.debug_log_unsupported_packets);
+                if self.config.debug_chunks {
+                    player.set_debug_chunks(Some(DEBUG_CHUNKS_TIME));
+                }
+                player.set_client_brand(client_brand);
+                player.set_client_information(client_information);
+                player.set_transferred(transferred);
                 println!("{} Connected", player.name());
-                players.push(player);
+                self.players.push(player);
                 Ok::<_, Box<dyn Error>>(())
             })?;
 
-        players
+        self.players
             .retain_returned(|player| !player.is_closed())
             .into_iter()
-            .for_each(|player| {
-                println!("{} Disconnected", player.name());
+            .for_each(|player| match player.disconnect_cause() {
+                Some(cause) => println!("{} Disconnected ({})", player.name(), cause),
+                None => println!("{} Disconnected", player.name()),
             });
 
-        players.iter_mut().try_for_each(|player| player.update())?;
+        self.players
+            .iter_mut()
+            .try_for_each(|player| player.update())?;
+
+        self.state.world.lock().unwrap().update_viewers()?;
+        self.state.entities.lock().unwrap().update_viewers()?;
+
+        self.autosave();
+
+        let tick_duration = tick_start.elapsed();
+        if self
+            .state
+            .tick_timings
+            .lock()
+            .unwrap()
+            .record(tick_duration)
+        {
+            println!("Can't keep up! Tick took {}ms", tick_duration.as_millis());
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::load(&["pkmc.toml", "pkmc/pkmc.toml"])?;
+
+    let biome_mapper: IdTable<Biome> = REGISTRIES
+        .get("minecraft:worldgen/biome")
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(i, (k, _v))| (normalize_identifier(k, "minecraft").into(), i as i32))
+        .collect();
+    let mut world = AnvilWorld::new(
+        config.world.clone(),
+        "minecraft:overworld",
+        -4..=19,
+        biome_mapper,
+    );
+    if config.spawn_chunks_radius > 0 {
+        // Players always spawn at 0, 0.
+        let spawn_chunks = ChunkPosition::new(0, 0)
+            .square_around(config.spawn_chunks_radius as i32)
+            .collect::<Vec<_>>();
+        world.keep_loaded(&spawn_chunks)?;
+    }
+
+    let mut server = Server::new(config, ErasedWorld::boxed(world))?;
+
+    println!("Server started on {}", server.local_addr()?);
+
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        server.tick()?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        error::Error,
+        io::Write as _,
+        net::TcpStream,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use pkmc_defs::{
+        biome::Biome, block::Block, entity::entity_type_id, generated::generated, packet,
+    };
+    use pkmc_server::{
+        broadcaster::Broadcaster,
+        entity_manager::EntityManager,
+        player_registry::PlayerRegistry,
+        tick_timings::TickTimings,
+        world::{anvil::AnvilWorld, memory::MemoryWorld, ErasedWorld, World, WorldBlock},
+        world_border::WorldBorder,
+    };
+    use pkmc_util::{
+        packet::{
+            Connection, ConnectionError, ConnectionSender, RawPacket, ReadExtPacket as _,
+            WriteExtPacket as _,
+        },
+        test_util::{connection_pair, poll_until, recieve_id},
+        Position, Vec3, UUID,
+    };
+
+    use crate::{
+        config::Config, player::Player, remove_previous_sessions, spawn_config_entities, Server,
+        ServerState,
+    };
+
+    #[test]
+    fn test_config_entities() -> Result<(), Box<dyn Error>> {
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+
+            [[entities]]
+            type = "minecraft:armor_stand"
+            position = [0.5, 100.0, 0.5]
+            yaw = 90.0
+            "#,
+        )?;
+        let mut entities = EntityManager::default();
+        let spawned = spawn_config_entities(&mut entities, &config.entities)?;
+        assert_eq!(spawned.len(), 1);
+
+        let handler = entities.get_entity(spawned[0].id()).unwrap();
+        let handler = handler.lock().unwrap();
+        assert_eq!(handler.r#type(), entity_type_id("armor_stand").unwrap());
+        assert_eq!(handler.position(), Vec3::new(0.5, 100.0, 0.5));
+        assert_eq!(handler.rotation(), (90.0, 0.0));
+
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+
+            [[entities]]
+            type = "minecraft:not_an_entity"
+            position = [0.0, 0.0, 0.0]
+            "#,
+        )?;
+        assert!(spawn_config_entities(&mut entities, &config.entities).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconnect_removes_previous_session() -> Result<(), Box<dyn Error>> {
+        let state = ServerState {
+            world: Arc::new(Mutex::new(ErasedWorld::boxed(MemoryWorld::new(
+                "minecraft:overworld",
+                -4..=19,
+            )))),
+            entities: Arc::new(Mutex::new(EntityManager::default())),
+            broadcaster: Arc::new(Mutex::new(Broadcaster::new())),
+            players: Arc::new(Mutex::new(PlayerRegistry::new())),
+            tick_timings: Arc::new(Mutex::new(TickTimings::new())),
+            world_border: Arc::new(Mutex::new(WorldBorder::new())),
+        };
+        let join = |uuid: UUID, name: &str| {
+            let (server, _client) = connection_pair()?;
+            Ok::<_, Box<dyn Error>>(Player::new(
+                server,
+                state.clone(),
+                uuid,
+                name.to_owned(),
+                2,
+                2,
+            )?)
+        };
+
+        let mut players = vec![
+            join(UUID::new_v7(), "Test")?,
+            join(UUID::new_v7(), "Other")?,
+        ];
+
+        // Reconnecting in offline mode, with a new uuid.
+        let uuid = UUID::new_v7();
+        remove_previous_sessions(&mut players, &uuid, "Test");
+        players.push(join(uuid, "Test")?);
+
+        assert_eq!(players.len(), 2);
+        let registry = state.players.lock().unwrap();
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["Other", "Test"]);
+        assert!(registry.contains(&uuid));
+
+        Ok(())
+    }
+
+    fn client_send(
+        client: &Connection,
+        id: i32,
+        write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>,
+    ) -> Result<(), ConnectionError> {
+        let mut data = Vec::new();
+        write(&mut data)?;
+        client
+            .sender()
+            .send_raw(RawPacket::new(id, data.into_boxed_slice()))
+    }
+
+    /// Logs in as far as play, recieve waits for a packet with the id while the server runs.
+    fn login(
+        client: &mut Connection,
+        next_state: i32,
+        mut recieve: impl FnMut(&mut Connection, i32) -> Result<RawPacket, Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        client_send(
+            client,
+            generated::packet::handshake::SERVERBOUND_MINECRAFT_INTENTION,
+            |w| {
+                w.write_varint(769)?;
+                w.write_string("localhost")?;
+                w.write_all(&25565u16.to_be_bytes())?;
+                w.write_varint(next_state)
+            },
+        )?;
+        client_send(
+            client,
+            generated::packet::login::SERVERBOUND_MINECRAFT_HELLO,
+            |w| {
+                w.write_string("Test")?;
+                w.write_uuid(&UUID::new_v7())
+            },
+        )?;
+        recieve(
+            client,
+            generated::packet::login::CLIENTBOUND_MINECRAFT_LOGIN_FINISHED,
+        )?;
+        client_send(
+            client,
+            generated::packet::login::SERVERBOUND_MINECRAFT_LOGIN_ACKNOWLEDGED,
+            |_| Ok(()),
+        )?;
+
+        recieve(
+            client,
+            generated::packet::configuration::CLIENTBOUND_MINECRAFT_SELECT_KNOWN_PACKS,
+        )?;
+        let vanilla = packet::configuration::KnownPack::vanilla();
+        client_send(
+            client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_SELECT_KNOWN_PACKS,
+            |w| {
+                w.write_varint(1)?;
+                w.write_string(&vanilla.namespace)?;
+                w.write_string(&vanilla.id)?;
+                w.write_string(&vanilla.version)
+            },
+        )?;
+        recieve(
+            client,
+            generated::packet::configuration::CLIENTBOUND_MINECRAFT_FINISH_CONFIGURATION,
+        )?;
+        client_send(
+            client,
+            generated::packet::configuration::SERVERBOUND_MINECRAFT_FINISH_CONFIGURATION,
+            |_| Ok(()),
+        )?;
+
+        recieve(client, generated::packet::play::CLIENTBOUND_MINECRAFT_LOGIN)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_login() -> Result<(), Box<dyn Error>> {
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            view-distance = 2
+            simulation-distance = 2
+            "#,
+        )?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let (address_sender, address) = std::sync::mpsc::channel();
+        let server_thread = std::thread::spawn({
+            let running = running.clone();
+            // Players aren't Send, so the server has to be created on its own thread.
+            move || {
+                let mut server = Server::new(
+                    config,
+                    ErasedWorld::boxed(MemoryWorld::new("minecraft:overworld", -4..=19)),
+                )
+                .map_err(|err| err.to_string())?;
+                address_sender
+                    .send(server.local_addr().map_err(|err| err.to_string())?)
+                    .unwrap();
+                while running.load(Ordering::Relaxed) {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    server.tick().map_err(|err| err.to_string())?;
+                }
+                Ok::<_, String>(())
+            }
+        });
+        let mut client = Connection::new(TcpStream::connect(address.recv()?)?)?;
+
+        login(&mut client, 2, |client, id| Ok(recieve_id(client, id)?))?;
+        recieve_id(
+            &mut client,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_LEVEL_CHUNK_WITH_LIGHT,
+        )?;
+
+        running.store(false, Ordering::Relaxed);
+        server_thread.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_with_cookie() -> Result<(), Box<dyn Error>> {
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            view-distance = 2
+            simulation-distance = 2
+            "#,
+        )?;
+        let mut server = Server::new(
+            config,
+            ErasedWorld::boxed(MemoryWorld::new("minecraft:overworld", -4..=19)),
+        )?;
+        let mut client = Connection::new(TcpStream::connect(server.local_addr()?)?)?;
+        let tick_until_id = |server: &mut Server, client: &mut Connection, id: i32| {
+            poll_until(|| {
+                server.tick()?;
+                while let Some(raw) = client.recieve()? {
+                    if raw.id == id {
+                        return Ok(Some(raw));
+                    }
+                }
+                Ok::<_, Box<dyn Error>>(None)
+            })
+        };
+
+        // Transferred here from another server.
+        login(&mut client, 3, |client, id| {
+            tick_until_id(&mut server, client, id)
+        })?;
+        assert_eq!(server.players.len(), 1);
+        assert!(server.players[0].transferred());
+
+        // The previous server stored a cookie, which this one can read back.
+        server.players[0].request_cookie("pkmc:lobby")?;
+        let raw = tick_until_id(
+            &mut server,
+            &mut client,
+            generated::packet::play::CLIENTBOUND_MINECRAFT_COOKIE_REQUEST,
+        )?;
+        assert_eq!(std::io::Cursor::new(raw.data).read_string()?, "pkmc:lobby");
+        client_send(
+            &client,
+            generated::packet::play::SERVERBOUND_MINECRAFT_COOKIE_RESPONSE,
+            |w| {
+                w.write_string("pkmc:lobby")?;
+                w.write_bool(true)?;
+                w.write_varint(3)?;
+                w.write_all(&[1, 2, 3])
+            },
+        )?;
+        let payload = poll_until(|| {
+            server.tick()?;
+            Ok::<_, Box<dyn Error>>(server.players[0].cookie("pkmc:lobby").map(<[u8]>::to_vec))
+        })?;
+        assert_eq!(payload, [1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_autosave() -> Result<(), Box<dyn Error>> {
+        let config: Config = toml::from_str(
+            r#"
+            address = "127.0.0.1:0"
+            world = "world"
+            "#,
+        )?;
+        assert_eq!(config.autosave_interval, 300);
+
+        let root = std::env::temp_dir().join(format!("pkmc-autosave-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("region"))?;
+        std::fs::copy(
+            "../pkmc-server/src/world/anvil-test-server/world/region/r.0.0.mca",
+            root.join("region").join("r.0.0.mca"),
+        )?;
+        let new_world = || {
+            AnvilWorld::new(
+                &root,
+                "minecraft:overworld",
+                -4..=20,
+                [(Biome::default(), 0)].into_iter().collect(),
+            )
+        };
+
+        let mut server = Server::new(config, ErasedWorld::boxed(new_world()))?;
+        let position = Position::new(19, 80, 37);
+        let gold = WorldBlock::Block(Block::new("minecraft:gold_block"));
+        server
+            .state
+            .world
+            .lock()
+            .unwrap()
+            .set_block(position, gold.clone())?;
+        server.autosave_interval = Some(Duration::from_millis(500));
+        server.last_autosave = std::time::Instant::now();
+        server.tick()?;
+        assert_ne!(new_world().get_block(position)?, Some(gold.clone()));
+
+        std::thread::sleep(Duration::from_millis(600));
+        server.tick()?;
+        assert!(!server.state.world.lock().unwrap().has_unsaved_changes());
+        assert_eq!(new_world().get_block(position)?, Some(gold));
+
+        std::fs::remove_dir_all(&root)?;
 
-        state.world.lock().unwrap().update_viewers()?;
-        state.entities.lock().unwrap().update_viewers()?;
+        Ok(())
     }
 }
@@ -1,24 +1,42 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
 
-use pkmc_defs::{biome::Biome, block::Block, packet, text_component::TextComponent};
+use pkmc_defs::{
+    biome::Biome,
+    block::Block,
+    mob_effect::mob_effect_id,
+    packet,
+    text_component::{Color, TextComponent},
+};
 use pkmc_server::{
+    chat::{ChatAcknowledgementError, ChatAcknowledgements},
     entity_manager::{new_entity_id, EntityViewer},
     world::{
-        anvil::AnvilError,
         chunk_loader::{ChunkLoader, ChunkPosition},
-        World, WorldBlock, WorldViewer,
+        DynWorldError, World, WorldBlock, WorldViewer, CHUNK_SIZE, SECTION_SIZE,
     },
+    world_border::MAX_WORLD_BORDER_SIZE,
 };
 use pkmc_util::{
-    packet::{ClientboundPacket, Connection, ConnectionError},
+    is_valid_identifier, normalize_identifier,
+    packet::{ClientboundPacket, Connection, ConnectionError, DisconnectCause, KeepAliveTracker},
     IdTable, Position, Vec3, UUID,
 };
-use rand::Rng as _;
 use thiserror::Error;
 
-use crate::{ServerState, REGISTRIES};
+use crate::{
+    events::{
+        BlockPlaceEvent, ChatEvent, CommandEvent, InteractEvent, MoveEvent, PlayerListener,
+        PluginMessageEvent,
+    },
+    ServerState, REGISTRIES,
+};
 
 const KEEPALIVE_PING_TIME: std::time::Duration = std::time::Duration::from_millis(10000);
+const UNSUPPORTED_PACKETS_LOG_TIME: std::time::Duration = std::time::Duration::from_millis(30000);
+pub const DEBUG_CHUNKS_TIME: std::time::Duration = std::time::Duration::from_millis(1000);
 
 #[derive(Error, Debug)]
 pub enum PlayerError {
@@ -26,12 +44,51 @@ pub enum PlayerError {
     ConnectionError(#[from] ConnectionError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    /// Errors from the world, see [`PlayerError::level`].
     #[error(transparent)]
-    WorldError(#[from] AnvilError),
+    LevelError(Box<dyn std::error::Error + Send + Sync>),
     #[error(
         "Client bad keep alive response (No response, wrong id, or responded when not expected)"
     )]
     BadKeepAliveResponse,
+    #[error("Invalid plugin message channel \"{0}\"")]
+    InvalidPluginChannel(String),
+}
+
+impl From<DynWorldError> for PlayerError {
+    fn from(value: DynWorldError) -> Self {
+        PlayerError::LevelError(value.into_inner())
+    }
+}
+
+impl PlayerError {
+    /// Wraps an error from any [`World`] implementation.
+    pub fn level<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
+        PlayerError::LevelError(Box::new(err))
+    }
+}
+
+/// Index of the dimension in the minecraft:dimension_type registry.
+fn dimension_type_id(dimension: &str) -> i32 {
+    REGISTRIES
+        .get("minecraft:dimension_type")
+        .unwrap()
+        .keys()
+        .enumerate()
+        .find(|(_, v)| *v == dimension)
+        .unwrap()
+        .0 as i32
+}
+
+/// Index of the damage type in the minecraft:damage_type registry.
+fn damage_type_id(damage_type: &str) -> Option<i32> {
+    REGISTRIES
+        .get("minecraft:damage_type")?
+        .keys()
+        .position(|k| {
+            normalize_identifier(k, "minecraft") == normalize_identifier(damage_type, "minecraft")
+        })
+        .map(|i| i as i32)
 }
 
 #[derive(Debug)]
@@ -42,14 +99,36 @@ pub struct Player {
     entity_viewer: Arc<Mutex<EntityViewer>>,
     name: String,
     uuid: UUID,
-    keepalive_time: std::time::Instant,
-    keepalive_id: Option<i64>,
+    entity_id: i32,
+    keep_alive: KeepAliveTracker,
     position: Vec3<f64>,
     pitch: f32,
     yaw: f32,
     is_flying: bool,
     fly_speed: f32,
+    /// Id of the last teleport sent.
+    teleport_id: i32,
+    /// Movement is ignored until the client accepts the latest teleport.
+    awaiting_teleport: Option<i32>,
     slot: u16,
+    listeners: Vec<Box<dyn PlayerListener>>,
+    chat_acknowledgements: ChatAcknowledgements,
+    /// Packet id to number of times it was recieved since last logged.
+    unsupported_packets: BTreeMap<i32, usize>,
+    unsupported_packets_time: std::time::Instant,
+    log_unsupported_packets: bool,
+    /// Interval to show chunk loading stats at, see [`Player::set_debug_chunks`].
+    debug_chunks: Option<std::time::Duration>,
+    debug_chunks_time: std::time::Instant,
+    /// Mob effect ids currently applied to the player.
+    effects: BTreeSet<i32>,
+    /// Cookies the client has responded with, see [`Player::request_cookie`].
+    cookies: HashMap<String, Box<[u8]>>,
+    /// Kick message for when the player declines a resource pack.
+    resource_pack_decline_kick: Option<TextComponent>,
+    client_brand: Option<String>,
+    client_information: packet::configuration::ClientInformation,
+    transferred: bool,
 }
 
 impl Player {
@@ -59,6 +138,7 @@ impl Player {
         uuid: UUID,
         name: String,
         view_distance: u8,
+        simulation_distance: u8,
     ) -> Result<Self, PlayerError> {
         let world_viewer = server_state
             .world
@@ -71,11 +151,20 @@ impl Player {
             .loader
             .update_radius(view_distance.into());
 
+        server_state
+            .broadcaster
+            .lock()
+            .unwrap()
+            .add(&connection.sender());
+
+        server_state.players.lock().unwrap().add(uuid, &name);
+
         let entity_viewer = server_state
             .entities
             .lock()
             .unwrap()
             .add_viewer(connection.sender());
+        entity_viewer.lock().unwrap().radius = view_distance as f64 * CHUNK_SIZE as f64;
 
         let mut player = Self {
             connection,
@@ -84,14 +173,29 @@ impl Player {
             entity_viewer,
             name,
             uuid,
-            keepalive_time: std::time::Instant::now(),
-            keepalive_id: None,
+            entity_id: new_entity_id(),
+            keep_alive: KeepAliveTracker::new(KEEPALIVE_PING_TIME),
             position: Vec3::zero(),
             pitch: 0.0,
             yaw: 0.0,
             is_flying: true,
             fly_speed: 0.1,
+            teleport_id: 0,
+            awaiting_teleport: None,
             slot: 0,
+            listeners: Vec::new(),
+            chat_acknowledgements: ChatAcknowledgements::new(),
+            unsupported_packets: BTreeMap::new(),
+            unsupported_packets_time: std::time::Instant::now(),
+            log_unsupported_packets: false,
+            debug_chunks: None,
+            debug_chunks_time: std::time::Instant::now(),
+            effects: BTreeSet::new(),
+            cookies: HashMap::new(),
+            resource_pack_decline_kick: None,
+            client_brand: None,
+            client_information: packet::configuration::ClientInformation::default(),
+            transferred: false,
         };
 
         let dimension = player
@@ -99,11 +203,12 @@ impl Player {
             .world
             .lock()
             .unwrap()
-            .identifier()
+            .dimension()
+            .name()
             .to_owned();
 
         player.connection.send(&packet::play::Login {
-            entity_id: new_entity_id(),
+            entity_id: player.entity_id,
             is_hardcore: false,
             dimensions: REGISTRIES
                 .get("minecraft:dimension_type")
@@ -113,18 +218,11 @@ impl Player {
                 .collect(),
             max_players: 42069,
             view_distance: view_distance as i32,
-            simulation_distance: 6,
+            simulation_distance: simulation_distance as i32,
             reduced_debug_info: false,
             enable_respawn_screen: true,
             do_limited_crafting: false,
-            dimension_type: REGISTRIES
-                .get("minecraft:dimension_type")
-                .unwrap()
-                .keys()
-                .enumerate()
-                .find(|(_, v)| *v == &dimension)
-                .unwrap()
-                .0 as i32,
+            dimension_type: dimension_type_id(&dimension),
             dimension_name: dimension,
             hashed_seed: 0,
             game_mode: 1,
@@ -152,11 +250,21 @@ impl Player {
             ),
         ]))?;
 
+        let world_border = player
+            .server_state
+            .world_border
+            .lock()
+            .unwrap()
+            .init_packet();
+        player.connection.send(&world_border)?;
+
         player
             .connection
             .send(&packet::play::GameEvent::StartWaitingForLevelChunks)?;
 
+        let teleport_id = player.next_teleport_id();
         player.connection.send(&packet::play::PlayerPosition {
+            teleport_id,
             x: 0.0,
             y: 128.0,
             z: 0.0,
@@ -189,15 +297,17 @@ impl Player {
             .unwrap()
             .loader
             .update_radius(view_distance.into());
+        self.entity_viewer.lock().unwrap().radius = view_distance as f64 * CHUNK_SIZE as f64;
         self.connection
             .send(&packet::play::SetChunkChacheRadius(view_distance as i32))?;
         Ok(())
     }
 
     pub fn kick<T: Into<TextComponent>>(&mut self, text: T) -> Result<(), PlayerError> {
+        // Urgent so queued chunks can't get the reason dropped when closing.
         self.connection
-            .send(&packet::play::Disconnect(text.into()))?;
-        self.connection.close();
+            .send_urgent(&packet::play::Disconnect(text.into()))?;
+        self.connection.close_with(DisconnectCause::Kicked);
         Ok(())
     }
 
@@ -205,6 +315,743 @@ impl Player {
         self.connection.is_closed()
     }
 
+    pub fn disconnect_cause(&self) -> Option<DisconnectCause> {
+        self.connection.disconnect_cause()
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn server_state(&self) -> &ServerState {
+        &self.server_state
+    }
+
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    /// Sends the player to another server, the client only accepts this if the other server
+    /// has transfers enabled.
+    pub fn transfer(&mut self, host: &str, port: u16) -> Result<(), PlayerError> {
+        self.connection.send(&packet::play::Transfer {
+            host: host.to_owned(),
+            port,
+        })?;
+        Ok(())
+    }
+
+    pub fn send_resource_pack(
+        &mut self,
+        uuid: UUID,
+        url: &str,
+        hash: &str,
+        forced: bool,
+        prompt: Option<TextComponent>,
+    ) -> Result<(), PlayerError> {
+        self.connection.send(&packet::play::AddResourcePack {
+            uuid,
+            url: url.to_owned(),
+            hash: hash.to_owned(),
+            forced,
+            prompt,
+        })?;
+        Ok(())
+    }
+
+    /// Kick the player with the message when they decline a resource pack, None to allow
+    /// declining.
+    pub fn set_resource_pack_decline_kick(&mut self, message: Option<TextComponent>) {
+        self.resource_pack_decline_kick = message;
+    }
+
+    /// Sends a plugin message, channel must be a valid "namespace:path" identifier.
+    pub fn send_plugin_message(&mut self, channel: &str, data: &[u8]) -> Result<(), PlayerError> {
+        if !is_valid_identifier(channel) {
+            return Err(PlayerError::InvalidPluginChannel(channel.to_owned()));
+        }
+        self.connection
+            .send(&packet::play::CustomPayload::Unknown {
+                channel: channel.to_owned(),
+                data: data.into(),
+            })?;
+        Ok(())
+    }
+
+    /// Stores a cookie on the client, which is kept across transfers.
+    pub fn store_cookie(&mut self, key: &str, payload: &[u8]) -> Result<(), PlayerError> {
+        self.connection.send(&packet::play::StoreCookie {
+            key: key.to_owned(),
+            payload: payload.into(),
+        })?;
+        Ok(())
+    }
+
+    /// Asks the client for a cookie, once responded to it's available from [`Player::cookie`].
+    pub fn request_cookie(&mut self, key: &str) -> Result<(), PlayerError> {
+        self.connection.send(&packet::play::CookieRequest {
+            key: key.to_owned(),
+        })?;
+        Ok(())
+    }
+
+    pub fn cookie(&self, key: &str) -> Option<&[u8]> {
+        self.cookies.get(key).map(|payload| payload.as_ref())
+    }
+
+    /// Moves the player, the chunk cache center is sent immediately so that chunks around the
+    /// destination get loaded instead of around the old position.
+    pub fn teleport(&mut self, position: Vec3<f64>) -> Result<(), PlayerError> {
+        self.position = position;
+        let teleport_id = self.next_teleport_id();
+        self.connection.send(&packet::play::PlayerPosition {
+            teleport_id,
+            x: position.x,
+            y: position.y,
+            z: position.z,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            ..Default::default()
+        })?;
+
+        let mut world_viewer = self.world_viewer.lock().unwrap();
+        world_viewer.position = position;
+        let center = ChunkPosition::from_world_position(position);
+        if world_viewer.loader.update_center(Some(center)) {
+            world_viewer
+                .connection()
+                .send(&packet::play::SetChunkCacheCenter {
+                    chunk_x: center.chunk_x,
+                    chunk_z: center.chunk_z,
+                })?;
+        }
+        drop(world_viewer);
+
+        self.entity_viewer.lock().unwrap().position = position;
+
+        Ok(())
+    }
+
+    /// Kills the player, showing the death screen until they respawn.
+    pub fn kill(&mut self) -> Result<(), PlayerError> {
+        if let Some(source_type_id) = damage_type_id("generic_kill") {
+            self.connection.send(&packet::play::DamageEvent {
+                entity_id: self.entity_id,
+                source_type_id,
+                source_cause_id: None,
+                source_direct_id: None,
+                source_position: None,
+            })?;
+        }
+        self.connection.send(&packet::play::SetHealth {
+            health: 0.0,
+            food: 20,
+            food_saturation: 5.0,
+        })?;
+        self.connection.send(&packet::play::PlayerCombatKill {
+            player_id: self.entity_id,
+            message: TextComponent::new(format!("{} was killed", self.name)),
+        })?;
+        Ok(())
+    }
+
+    fn respawn(&mut self) -> Result<(), PlayerError> {
+        let dimension = self
+            .server_state
+            .world
+            .lock()
+            .unwrap()
+            .dimension()
+            .name()
+            .to_owned();
+        self.connection.send(&packet::play::Respawn {
+            dimension_type: dimension_type_id(&dimension),
+            dimension_name: dimension,
+            hashed_seed: 0,
+            game_mode: 1,
+            previous_game_mode: -1,
+            is_debug: false,
+            is_flat: false,
+            death: None,
+            portal_cooldown: 0,
+            sea_level: 0,
+            data_kept: 0,
+        })?;
+        // Effects aren't kept across respawns.
+        self.effects.clear();
+        self.connection.send(&packet::play::SetHealth {
+            health: 20.0,
+            food: 20,
+            food_saturation: 5.0,
+        })?;
+        self.update_flyspeed()?;
+        self.teleport(Vec3::new(0.0, 128.0, 0.0))?;
+        Ok(())
+    }
+
+    /// Brand the client sent, "vanilla" for unmodded clients.
+    pub fn client_brand(&self) -> Option<&str> {
+        self.client_brand.as_deref()
+    }
+
+    pub fn set_client_brand(&mut self, client_brand: Option<String>) {
+        self.client_brand = client_brand;
+    }
+
+    pub fn client_information(&self) -> &packet::configuration::ClientInformation {
+        &self.client_information
+    }
+
+    pub fn set_client_information(
+        &mut self,
+        client_information: packet::configuration::ClientInformation,
+    ) {
+        self.client_information = client_information;
+    }
+
+    /// Whether the player was transferred from another server, see [`Player::transfer`].
+    pub fn transferred(&self) -> bool {
+        self.transferred
+    }
+
+    pub fn set_transferred(&mut self, transferred: bool) {
+        self.transferred = transferred;
+    }
+
+    /// Log each unsupported packet as it's recieved, instead of periodically aggregated.
+    pub fn set_log_unsupported_packets(&mut self, log_unsupported_packets: bool) {
+        self.log_unsupported_packets = log_unsupported_packets;
+    }
+
+    /// Periodically resends the chunk cache center & shows the chunk loader's queue sizes in the
+    /// action bar, None to disable.
+    pub fn set_debug_chunks(&mut self, interval: Option<std::time::Duration>) {
+        self.debug_chunks = interval;
+    }
+
+    fn debug_chunks_text(&self) -> TextComponent {
+        let world_viewer = self.world_viewer.lock().unwrap();
+        let (to_load, loaded, to_unload) = world_viewer.loader.queue_sizes();
+        TextComponent::new(format!(
+            "Chunks: {} loaded, {} to load, {} to unload",
+            loaded, to_load, to_unload
+        ))
+    }
+
+    fn update_debug_chunks(&mut self) -> Result<(), PlayerError> {
+        let Some(interval) = self.debug_chunks else {
+            return Ok(());
+        };
+        if self.debug_chunks_time.elapsed() < interval {
+            return Ok(());
+        }
+        self.debug_chunks_time = std::time::Instant::now();
+
+        let center = self.world_viewer.lock().unwrap().loader.center();
+        if let Some(center) = center {
+            self.connection.send(&packet::play::SetChunkCacheCenter {
+                chunk_x: center.chunk_x,
+                chunk_z: center.chunk_z,
+            })?;
+        }
+        self.connection
+            .send(&packet::play::SetActionBarText(self.debug_chunks_text()))?;
+        Ok(())
+    }
+
+    /// Summary of unsupported packets recieved since last called.
+    fn take_unsupported_packets_log(&mut self) -> Option<String> {
+        self.unsupported_packets_time = std::time::Instant::now();
+        if self.unsupported_packets.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{} Unsupported packets: {}",
+            self.name,
+            std::mem::take(&mut self.unsupported_packets)
+                .into_iter()
+                .map(|(id, count)| format!("0x{:02X} x{}", id, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    pub fn add_listener(&mut self, listener: impl PlayerListener + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    fn dispatch<E>(
+        &mut self,
+        event: &mut E,
+        hook: impl Fn(&mut dyn PlayerListener, &mut Player, &mut E) -> Result<(), PlayerError>,
+    ) -> Result<(), PlayerError> {
+        // Listeners get taken out while dispatching, so that they may borrow the player.
+        let mut listeners = std::mem::take(&mut self.listeners);
+        let result = listeners
+            .iter_mut()
+            .try_for_each(|listener| hook(listener.as_mut(), self, event));
+        // Keep listeners that were added while dispatching.
+        listeners.append(&mut self.listeners);
+        self.listeners = listeners;
+        result
+    }
+
+    /// Id for a new teleport, movement is ignored until the client accepts it.
+    fn next_teleport_id(&mut self) -> i32 {
+        self.teleport_id = self.teleport_id.wrapping_add(1);
+        self.awaiting_teleport = Some(self.teleport_id);
+        self.teleport_id
+    }
+
+    fn handle_move(
+        &mut self,
+        position: Option<Vec3<f64>>,
+        rotation: Option<(f32, f32)>,
+    ) -> Result<(), PlayerError> {
+        // Movement from before the teleport was accepted is stale.
+        if self.awaiting_teleport.is_some() {
+            return Ok(());
+        }
+        let (pitch, yaw) = rotation.unwrap_or((self.pitch, self.yaw));
+        let mut event = MoveEvent {
+            position: position.unwrap_or(self.position),
+            pitch,
+            yaw,
+            cancelled: false,
+        };
+        self.dispatch(&mut event, |l, p, e| l.on_move(p, e))?;
+        if event.cancelled {
+            let teleport_id = self.next_teleport_id();
+            self.connection.send(&packet::play::PlayerPosition {
+                teleport_id,
+                x: self.position.x,
+                y: self.position.y,
+                z: self.position.z,
+                yaw: self.yaw,
+                pitch: self.pitch,
+                ..Default::default()
+            })?;
+            return Ok(());
+        }
+        self.position = event.position;
+        self.pitch = event.pitch;
+        self.yaw = event.yaw;
+        Ok(())
+    }
+
+    fn handle_chat_acknowledgement(
+        &mut self,
+        result: Result<(), ChatAcknowledgementError>,
+    ) -> Result<bool, PlayerError> {
+        match result {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                println!("{} {}", self.name(), err);
+                self.kick("Chat message validation failure")?;
+                Ok(false)
+            }
+        }
+    }
+
+    fn handle_chat(&mut self, chat: packet::play::Chat) -> Result<(), PlayerError> {
+        let result = self
+            .chat_acknowledgements
+            .apply_update(chat.message_count, &chat.acknowledged);
+        if !self.handle_chat_acknowledgement(result)? {
+            return Ok(());
+        }
+        let mut event = ChatEvent {
+            message: chat.message,
+            cancelled: false,
+        };
+        self.dispatch(&mut event, |l, p, e| l.on_chat(p, e))?;
+        if event.cancelled {
+            return Ok(());
+        }
+        self.server_state
+            .broadcaster
+            .lock()
+            .unwrap()
+            .broadcast(&packet::play::SystemChat {
+                content: TextComponent::rainbow(&format!("<{}> {}", self.name, event.message), 0.0),
+                overlay: false,
+            })?;
+        Ok(())
+    }
+
+    fn handle_command(&mut self, command: String) -> Result<(), PlayerError> {
+        let mut event = CommandEvent {
+            command,
+            cancelled: false,
+        };
+        self.dispatch(&mut event, |l, p, e| l.on_command(p, e))?;
+        if event.cancelled {
+            return Ok(());
+        }
+        let mut args = event.command.split_whitespace();
+        let content = match args.next().unwrap_or_default() {
+            "refresh" => match args.next().map(|r| r.parse::<u32>()).transpose() {
+                Ok(radius) => {
+                    let mut world_viewer = self.world_viewer.lock().unwrap();
+                    let max_radius = world_viewer.loader.radius().max(0) as u32;
+                    let radius = radius.unwrap_or(max_radius).min(max_radius);
+                    let center = ChunkPosition::from_world_position(self.position);
+                    let chunks = world_viewer
+                        .loader
+                        .loaded()
+                        .filter(|chunk| {
+                            chunk.chunk_x.abs_diff(center.chunk_x) <= radius
+                                && chunk.chunk_z.abs_diff(center.chunk_z) <= radius
+                        })
+                        .collect::<Vec<_>>();
+                    let count = chunks.len();
+                    world_viewer.loader.force_reload_many(chunks);
+                    TextComponent::new(format!("Refreshing {} chunks", count))
+                }
+                Err(_) => TextComponent::new("Usage: /refresh [radius]").with_color(Color::RED),
+            },
+            "list" => {
+                let players = self.server_state.players.lock().unwrap();
+                TextComponent::new(format!(
+                    "There are {} players online: {}",
+                    players.len(),
+                    players.names().collect::<Vec<_>>().join(", "),
+                ))
+            }
+            "tps" => {
+                let timings = self.server_state.tick_timings.lock().unwrap();
+                TextComponent::new(format!(
+                    "TPS: {:.1}, MSPT: {:.2}",
+                    timings.tps(),
+                    timings.mspt(),
+                ))
+            }
+            "effect" => self.effect_command(args)?,
+            "kill" => self.kill_command(args.next())?,
+            "setblock" => self.setblock_command(args)?,
+            "worldborder" => self.worldborder_command(args)?,
+            "transfer" => match (
+                args.next(),
+                args.next().map(|p| p.parse::<u16>()).unwrap_or(Ok(25565)),
+            ) {
+                (Some(host), Ok(port)) => {
+                    self.transfer(host, port)?;
+                    TextComponent::new(format!("Transferring to {}:{}", host, port))
+                }
+                _ => TextComponent::new("Usage: /transfer <host> [port]").with_color(Color::RED),
+            },
+            "camera" => match args.next().map(|id| id.parse::<i32>()) {
+                Some(Ok(entity_id)) if entity_id == self.entity_id => {
+                    self.connection
+                        .send(&packet::play::SetCamera { entity_id })?;
+                    TextComponent::new("Reset camera")
+                }
+                Some(Ok(entity_id)) => {
+                    if self
+                        .server_state
+                        .entities
+                        .lock()
+                        .unwrap()
+                        .get_entity(entity_id)
+                        .is_some()
+                    {
+                        self.connection
+                            .send(&packet::play::SetCamera { entity_id })?;
+                        TextComponent::new(format!("Now viewing entity {}", entity_id))
+                    } else {
+                        TextComponent::new(format!("Unknown entity: {}", entity_id))
+                            .with_color(Color::RED)
+                    }
+                }
+                _ => TextComponent::new(format!(
+                    "Usage: /camera <entity> (Your entity is {})",
+                    self.entity_id
+                ))
+                .with_color(Color::RED),
+            },
+            _ => TextComponent::new(format!("Unknown command: /{}", event.command))
+                .with_color(Color::RED),
+        };
+        self.connection.send(&packet::play::SystemChat {
+            content,
+            overlay: false,
+        })?;
+        Ok(())
+    }
+
+    /// `/effect give <effect> [seconds] [amplifier]` or `/effect clear [effect]`
+    fn effect_command<'a>(
+        &mut self,
+        mut args: impl Iterator<Item = &'a str>,
+    ) -> Result<TextComponent, PlayerError> {
+        let usage = || {
+            TextComponent::new("Usage: /effect give <effect> [seconds] [amplifier]")
+                .with_color(Color::RED)
+        };
+        let action = args.next();
+        let effect = args.next().map(|name| (name, mob_effect_id(name)));
+        match (action, effect) {
+            (Some("give"), Some((name, Some(effect_id)))) => {
+                let (Ok(seconds), Ok(amplifier)) = (
+                    args.next().map(|s| s.parse::<i32>()).unwrap_or(Ok(30)),
+                    args.next().map(|s| s.parse::<i32>()).unwrap_or(Ok(0)),
+                ) else {
+                    return Ok(usage());
+                };
+                let duration = if seconds < 0 {
+                    -1
+                } else {
+                    match seconds.checked_mul(20) {
+                        Some(duration) => duration,
+                        None => return Ok(usage()),
+                    }
+                };
+                self.connection.send(&packet::play::UpdateMobEffect {
+                    entity_id: self.entity_id,
+                    effect_id,
+                    amplifier,
+                    duration,
+                    flags: 0x06,
+                })?;
+                self.effects.insert(effect_id);
+                Ok(TextComponent::new(format!("Applied effect {}", name)))
+            }
+            (Some("clear"), None) => {
+                let effects = std::mem::take(&mut self.effects);
+                effects.iter().try_for_each(|effect_id| {
+                    self.connection.send(&packet::play::RemoveMobEffect {
+                        entity_id: self.entity_id,
+                        effect_id: *effect_id,
+                    })
+                })?;
+                Ok(TextComponent::new(format!(
+                    "Removed {} effects",
+                    effects.len()
+                )))
+            }
+            (Some("clear"), Some((name, Some(effect_id)))) => {
+                self.connection.send(&packet::play::RemoveMobEffect {
+                    entity_id: self.entity_id,
+                    effect_id,
+                })?;
+                self.effects.remove(&effect_id);
+                Ok(TextComponent::new(format!("Removed effect {}", name)))
+            }
+            (_, Some((name, None))) => {
+                Ok(TextComponent::new(format!("Unknown effect: {}", name)).with_color(Color::RED))
+            }
+            _ => Ok(usage()),
+        }
+    }
+
+    /// `/setblock <x> <y> <z> <block>`, block being a name with optional properties like
+    /// `oak_stairs[facing=east,half=top]`.
+    fn setblock_command<'a>(
+        &mut self,
+        mut args: impl Iterator<Item = &'a str>,
+    ) -> Result<TextComponent, PlayerError> {
+        let (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), Some(block)) = (
+            args.next().map(|x| x.parse::<i32>()),
+            args.next().map(|y| y.parse::<i16>()),
+            args.next().map(|z| z.parse::<i32>()),
+            args.next(),
+        ) else {
+            return Ok(
+                TextComponent::new("Usage: /setblock <x> <y> <z> <block>").with_color(Color::RED)
+            );
+        };
+        let Some(parsed) = Block::from_state_string(block) else {
+            return Ok(
+                TextComponent::new(format!("Unknown block: {}", block)).with_color(Color::RED)
+            );
+        };
+        let mut world = self.server_state.world.lock().unwrap();
+        if !world
+            .section_y_range()
+            .contains(&(y.div_euclid(SECTION_SIZE as i16) as i8))
+        {
+            return Ok(
+                TextComponent::new("Cannot place block outside of the world")
+                    .with_color(Color::RED),
+            );
+        }
+        let position = Position::new(x, y, z);
+        world.set_block(position, WorldBlock::Block(parsed))?;
+        Ok(TextComponent::new(format!(
+            "Changed the block at {}, {}, {}",
+            x, y, z
+        )))
+    }
+
+    /// `/worldborder set <size> [seconds]`, `/worldborder center <x> <z>`, or
+    /// `/worldborder warning <distance|time> <value>`, changes are broadcast to every player.
+    fn worldborder_command<'a>(
+        &mut self,
+        mut args: impl Iterator<Item = &'a str>,
+    ) -> Result<TextComponent, PlayerError> {
+        let mut world_border = self.server_state.world_border.lock().unwrap();
+        let mut broadcaster = self.server_state.broadcaster.lock().unwrap();
+        match (args.next(), args.next(), args.next()) {
+            (Some("set"), Some(size), seconds) => {
+                let usage = || {
+                    TextComponent::new("Usage: /worldborder set <size> [seconds]")
+                        .with_color(Color::RED)
+                };
+                let (Ok(size), Ok(seconds)) = (
+                    size.parse::<f64>(),
+                    seconds.map(|s| s.parse::<u64>()).unwrap_or(Ok(0)),
+                ) else {
+                    return Ok(usage());
+                };
+                // Lerp speed is sent in milliseconds.
+                let Some(speed) = i64::try_from(seconds)
+                    .ok()
+                    .and_then(|s| s.checked_mul(1000))
+                else {
+                    return Ok(usage());
+                };
+                if !(1.0..=MAX_WORLD_BORDER_SIZE).contains(&size) {
+                    return Ok(TextComponent::new(format!(
+                        "World border must be between 1 and {} blocks wide",
+                        MAX_WORLD_BORDER_SIZE
+                    ))
+                    .with_color(Color::RED));
+                }
+                let old_size = world_border.size();
+                world_border.set_size(size, std::time::Duration::from_secs(seconds));
+                if seconds == 0 {
+                    broadcaster.broadcast(&packet::play::SetBorderSize(size))?;
+                    Ok(TextComponent::new(format!(
+                        "Set the world border to {} blocks wide",
+                        size
+                    )))
+                } else {
+                    broadcaster.broadcast(&packet::play::SetBorderLerpSize {
+                        old_diameter: old_size,
+                        new_diameter: size,
+                        speed,
+                    })?;
+                    Ok(TextComponent::new(format!(
+                        "Moving the world border to {} blocks wide over {} seconds",
+                        size, seconds
+                    )))
+                }
+            }
+            (Some("center"), Some(x), Some(z)) => {
+                let (Ok(x), Ok(z)) = (x.parse::<f64>(), z.parse::<f64>()) else {
+                    return Ok(TextComponent::new("Usage: /worldborder center <x> <z>")
+                        .with_color(Color::RED));
+                };
+                world_border.set_center(x, z);
+                broadcaster.broadcast(&packet::play::SetBorderCenter { x, z })?;
+                Ok(TextComponent::new(format!(
+                    "Set the center of the world border to {}, {}",
+                    x, z
+                )))
+            }
+            (Some("warning"), Some(kind @ ("distance" | "time")), Some(value)) => {
+                let Some(value) = value.parse::<i32>().ok().filter(|v| *v >= 0) else {
+                    return Ok(TextComponent::new(format!(
+                        "Usage: /worldborder warning {} <value>",
+                        kind
+                    ))
+                    .with_color(Color::RED));
+                };
+                if kind == "distance" {
+                    world_border.set_warning_distance(value);
+                    broadcaster.broadcast(&packet::play::SetBorderWarningDistance(value))?;
+                    Ok(TextComponent::new(format!(
+                        "Set the world border warning distance to {} blocks",
+                        value
+                    )))
+                } else {
+                    world_border.set_warning_time(value);
+                    broadcaster.broadcast(&packet::play::SetBorderWarningDelay(value))?;
+                    Ok(TextComponent::new(format!(
+                        "Set the world border warning time to {} seconds",
+                        value
+                    )))
+                }
+            }
+            _ => Ok(TextComponent::new(
+                "Usage: /worldborder <set <size> [seconds]|center <x> <z>|warning <distance|time> <value>>",
+            )
+            .with_color(Color::RED)),
+        }
+    }
+
+    /// `/kill [target]`, target being `@s` or the player's name, `@e` for every entity, or an
+    /// entity id.
+    fn kill_command(&mut self, target: Option<&str>) -> Result<TextComponent, PlayerError> {
+        match target {
+            None | Some("@s") => {
+                self.kill()?;
+                Ok(TextComponent::new(format!("Killed {}", self.name)))
+            }
+            Some(name) if name == self.name => {
+                self.kill()?;
+                Ok(TextComponent::new(format!("Killed {}", self.name)))
+            }
+            Some("@e") => {
+                let mut entities = self.server_state.entities.lock().unwrap();
+                let killed = entities
+                    .entity_ids()
+                    .into_iter()
+                    .filter(|id| entities.remove_entity(*id))
+                    .count();
+                Ok(TextComponent::new(format!("Killed {} entities", killed)))
+            }
+            Some(target) => match target.parse::<i32>() {
+                Ok(entity_id) if entity_id == self.entity_id => {
+                    self.kill()?;
+                    Ok(TextComponent::new(format!("Killed {}", self.name)))
+                }
+                Ok(entity_id)
+                    if self
+                        .server_state
+                        .entities
+                        .lock()
+                        .unwrap()
+                        .remove_entity(entity_id) =>
+                {
+                    Ok(TextComponent::new(format!("Killed entity {}", entity_id)))
+                }
+                _ => Ok(TextComponent::new(format!("Unknown target: {}", target))
+                    .with_color(Color::RED)),
+            },
+        }
+    }
+
+    fn handle_block_place(
+        &mut self,
+        use_item_on: packet::play::UseItemOn,
+    ) -> Result<(), PlayerError> {
+        let mut event = BlockPlaceEvent {
+            clicked: use_item_on.location,
+            face: use_item_on.face,
+            position: use_item_on.location + use_item_on.face.offset(),
+            block: None,
+            cancelled: false,
+        };
+        self.dispatch(&mut event, |l, p, e| l.on_block_place(p, e))?;
+        let mut world = self.server_state.world.lock().unwrap();
+        if event.cancelled {
+            let current = world
+                .get_block(event.position)?
+                .and_then(|b| b.as_block().id())
+                .unwrap_or(0);
+            self.connection.send(&packet::play::BlockUpdate {
+                location: event.position,
+                block_id: current,
+            })?;
+        } else if let Some(block) = event.block {
+            world.set_block(event.position, WorldBlock::Block(block))?;
+        }
+        self.connection
+            .send(&packet::play::BlockChangedAck(use_item_on.sequence))?;
+        Ok(())
+    }
+
     fn update_flyspeed(&mut self) -> Result<(), PlayerError> {
         self.connection
             .send(&packet::play::PlayerAbilities_Clientbound {
@@ -216,50 +1063,67 @@ impl Player {
     }
 
     pub fn update(&mut self) -> Result<(), PlayerError> {
-        if std::time::Instant::now().duration_since(self.keepalive_time) >= KEEPALIVE_PING_TIME {
-            self.keepalive_time = std::time::Instant::now();
-            // Didn't respond to previous keepalive in time for new one.
-            if self.keepalive_id.is_some() {
-                return Err(PlayerError::BadKeepAliveResponse);
-            }
-            let id: i64 = rand::thread_rng().gen();
-            self.keepalive_id = Some(id);
-            self.connection.send(&packet::play::KeepAlive { id })?;
+        let now = std::time::Instant::now();
+        if self.keep_alive.is_timed_out(now) {
+            self.connection.close_with(DisconnectCause::Timeout);
+            return Err(PlayerError::BadKeepAliveResponse);
+        }
+        if let Some(id) = self.keep_alive.should_send(now) {
+            self.connection
+                .send_urgent(&packet::play::KeepAlive { id })?;
         }
 
-        while let Some(packet) = match self.connection.recieve_into::<packet::play::PlayPacket>() {
-            Ok(packet) => packet,
-            Err(err @ ConnectionError::UnsupportedPacket(..)) => {
-                println!("{} {}", self.name(), err);
-                None
-            }
-            Err(err) => Err(err)?,
-        } {
+        loop {
+            let packet = match self.connection.recieve_into::<packet::play::PlayPacket>() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => break,
+                Err(ConnectionError::UnsupportedPacket(_, id)) if !self.log_unsupported_packets => {
+                    *self.unsupported_packets.entry(id).or_default() += 1;
+                    // Other packets may still be waiting.
+                    continue;
+                }
+                Err(err @ ConnectionError::UnsupportedPacket(..)) => {
+                    println!("{} {}", self.name(), err);
+                    continue;
+                }
+                Err(err) => Err(err)?,
+            };
             match packet {
-                packet::play::PlayPacket::KeepAlive(keepalive) => match self.keepalive_id.take() {
-                    // Success so we do nothing.
-                    Some(keepalive_id) if keepalive_id == keepalive.id => {}
-                    // Either responded to invalid keepalive, or keepalive id is wrong.
-                    _ => return Err(PlayerError::BadKeepAliveResponse),
-                },
+                packet::play::PlayPacket::KeepAlive(keepalive) => {
+                    if self.keep_alive.acknowledge(keepalive.id).is_err() {
+                        self.connection.close_with(DisconnectCause::ProtocolError);
+                        return Err(PlayerError::BadKeepAliveResponse);
+                    }
+                }
                 packet::play::PlayPacket::PlayerLoaded(_player_loaded) => {}
-                packet::play::PlayPacket::AcceptTeleportation(_accept_teleportation) => {}
+                packet::play::PlayPacket::AcceptTeleportation(accept_teleportation) => {
+                    // Acknowledgements of older teleports are ignored.
+                    if self.awaiting_teleport == Some(accept_teleportation.teleport_id) {
+                        self.awaiting_teleport = None;
+                    }
+                }
                 packet::play::PlayPacket::MovePlayerPosRot(move_player_pos_rot) => {
-                    self.position.set(
-                        move_player_pos_rot.x,
-                        move_player_pos_rot.y,
-                        move_player_pos_rot.z,
-                    );
-                    self.pitch = move_player_pos_rot.pitch;
-                    self.yaw = move_player_pos_rot.yaw;
+                    self.handle_move(
+                        Some(Vec3::new(
+                            move_player_pos_rot.x,
+                            move_player_pos_rot.y,
+                            move_player_pos_rot.z,
+                        )),
+                        Some((move_player_pos_rot.pitch, move_player_pos_rot.yaw)),
+                    )?;
                 }
                 packet::play::PlayPacket::MovePlayerPos(move_player_pos) => {
-                    self.position
-                        .set(move_player_pos.x, move_player_pos.y, move_player_pos.z);
+                    self.handle_move(
+                        Some(Vec3::new(
+                            move_player_pos.x,
+                            move_player_pos.y,
+                            move_player_pos.z,
+                        )),
+                        None,
+                    )?;
                 }
                 packet::play::PlayPacket::MovePlayerRot(move_player_rot) => {
-                    self.pitch = move_player_rot.pitch;
-                    self.yaw = move_player_rot.yaw;
+                    self.handle_move(None, Some((move_player_rot.pitch, move_player_rot.yaw)))?;
                 }
                 packet::play::PlayPacket::MovePlayerStatusOnly(_move_player_status_only) => {}
                 packet::play::PlayPacket::ClientTickEnd(_client_tick_end) => {}
@@ -306,11 +1170,1054 @@ impl Player {
                         )?;
                     }
                 }
+                packet::play::PlayPacket::Chat(chat) => self.handle_chat(chat)?,
+                packet::play::PlayPacket::ChatAck(chat_ack) => {
+                    let result = self
+                        .chat_acknowledgements
+                        .apply_offset(chat_ack.message_count);
+                    self.handle_chat_acknowledgement(result)?;
+                }
+                packet::play::PlayPacket::ChatCommand(chat_command) => {
+                    self.handle_command(chat_command.command)?
+                }
+                packet::play::PlayPacket::UseItemOn(use_item_on) => {
+                    self.handle_block_place(use_item_on)?
+                }
+                packet::play::PlayPacket::ResourcePackResponse(response) => {
+                    if response.status == packet::play::ResourcePackStatus::Declined {
+                        if let Some(message) = self.resource_pack_decline_kick.clone() {
+                            self.kick(message)?;
+                        }
+                    }
+                }
+                packet::play::PlayPacket::CustomPayload(packet::play::CustomPayload::Brand(
+                    brand,
+                )) => self.client_brand = Some(brand),
+                packet::play::PlayPacket::CustomPayload(packet::play::CustomPayload::Unknown {
+                    channel,
+                    data,
+                }) => {
+                    if is_valid_identifier(&channel) {
+                        let mut event = PluginMessageEvent { channel, data };
+                        self.dispatch(&mut event, |l, p, e| l.on_plugin_message(p, e))?;
+                    }
+                }
+                packet::play::PlayPacket::ClientCommand(
+                    packet::play::ClientCommand::PerformRespawn,
+                ) => {
+                    self.respawn()?;
+                }
+                packet::play::PlayPacket::ClientCommand(
+                    packet::play::ClientCommand::RequestStats,
+                ) => {}
+                packet::play::PlayPacket::Interact(interact) => {
+                    let mut event = InteractEvent {
+                        entity_id: interact.entity_id,
+                        kind: interact.kind,
+                        sneaking: interact.sneaking,
+                    };
+                    self.dispatch(&mut event, |l, p, e| l.on_interact(p, e))?;
+                }
+                packet::play::PlayPacket::CookieResponse(cookie) => match cookie.payload {
+                    Some(payload) => {
+                        self.cookies.insert(cookie.key, payload);
+                    }
+                    None => {
+                        self.cookies.remove(&cookie.key);
+                    }
+                },
             }
         }
 
+        if std::time::Instant::now().duration_since(self.unsupported_packets_time)
+            >= UNSUPPORTED_PACKETS_LOG_TIME
+        {
+            if let Some(log) = self.take_unsupported_packets_log() {
+                println!("{}", log);
+            }
+        }
+
+        self.update_debug_chunks()?;
+
         let mut world_viewer = self.world_viewer.lock().unwrap();
         world_viewer.position = self.position;
+        let mut entity_viewer = self.entity_viewer.lock().unwrap();
+        entity_viewer.position = self.position;
+
+        Ok(())
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.server_state.players.lock().unwrap().remove(&self.uuid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        error::Error,
+        io::Write as _,
+        sync::{Arc, Mutex},
+    };
+
+    use pkmc_defs::{
+        biome::Biome, block::Block, generated::generated, packet, text_component::TextComponent,
+    };
+    use pkmc_server::{
+        broadcaster::Broadcaster,
+        entity_manager::EntityManager,
+        player_registry::PlayerRegistry,
+        tick_timings::TickTimings,
+        world::{
+            anvil::AnvilWorld,
+            chunk_loader::{ChunkLoader, ChunkPosition},
+            memory::MemoryWorld,
+            Dimension, ErasedWorld, World, WorldBlock, WorldViewer,
+        },
+        world_border::WorldBorder,
+    };
+    use pkmc_util::{
+        nbt::NBT,
+        packet::{
+            ClientboundPacket, Connection, ConnectionError, ConnectionSender, DisconnectCause,
+            RawPacket, ReadExtPacket as _, WriteExtPacket as _,
+        },
+        test_util::{connection_pair, poll_until, recieve_all, recieve_id},
+        IdTable, Position, ReadExt as _, Vec3, UUID,
+    };
+
+    use crate::{
+        events::{BlockPlaceEvent, ChatEvent, PlayerListener, PluginMessageEvent},
+        player::{Player, PlayerError},
+        ServerState, StaticEntity,
+    };
+
+    fn test_state() -> ServerState {
+        ServerState {
+            world: Arc::new(Mutex::new(ErasedWorld::boxed(MemoryWorld::new(
+                "minecraft:overworld",
+                -4..=19,
+            )))),
+            entities: Arc::new(Mutex::new(EntityManager::default())),
+            broadcaster: Arc::new(Mutex::new(Broadcaster::new())),
+            players: Arc::new(Mutex::new(PlayerRegistry::new())),
+            tick_timings: Arc::new(Mutex::new(TickTimings::new())),
+            world_border: Arc::new(Mutex::new(WorldBorder::new())),
+        }
+    }
+
+    struct ClientChat(&'static str);
+
+    impl ClientboundPacket for ClientChat {
+        const CLIENTBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CHAT;
+
+        fn packet_write(&self, mut writer: impl std::io::Write) -> Result<(), ConnectionError> {
+            writer.write_string(self.0)?;
+            writer.write_all(&0i64.to_be_bytes())?;
+            writer.write_all(&0i64.to_be_bytes())?;
+            writer.write_bool(false)?;
+            writer.write_varint(0)?;
+            writer.write_all(&[0; 3])?;
+            Ok(())
+        }
+    }
+
+    struct ClientCommand<'a>(&'a str);
+
+    impl ClientboundPacket for ClientCommand<'_> {
+        const CLIENTBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_CHAT_COMMAND;
+
+        fn packet_write(&self, mut writer: impl std::io::Write) -> Result<(), ConnectionError> {
+            writer.write_string(self.0)?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Mute;
+
+    impl PlayerListener for Mute {
+        fn on_chat(
+            &mut self,
+            _player: &mut Player,
+            event: &mut ChatEvent,
+        ) -> Result<(), PlayerError> {
+            event.cancelled = true;
+            Ok(())
+        }
+    }
+
+    /// Every packet that the player sent so far.
+    fn recieved_packets(
+        player: &Player,
+        client: &mut Connection,
+    ) -> Result<Vec<RawPacket>, ConnectionError> {
+        recieve_all(&player.connection.sender(), client)
+    }
+
+    /// Updates the player until it handled everything the client sent so far, returning every
+    /// packet the client recieved meanwhile. A /list command is sent last, its reply marks the
+    /// end.
+    fn sync(
+        player: &mut Player,
+        client: &mut Connection,
+    ) -> Result<Vec<RawPacket>, Box<dyn Error>> {
+        client.send(&ClientCommand("list"))?;
+        let mut packets = Vec::new();
+        poll_until(|| {
+            player.update()?;
+            while let Some(raw) = client.recieve()? {
+                if raw.id == packet::play::SystemChat::CLIENTBOUND_ID
+                    && String::from_utf8_lossy(&raw.data).contains("players online")
+                {
+                    return Ok(Some(()));
+                }
+                packets.push(raw);
+            }
+            Ok::<_, Box<dyn Error>>(None)
+        })?;
+        Ok(packets)
+    }
+
+    fn system_chats(packets: &[RawPacket]) -> Vec<String> {
+        packets
+            .iter()
+            .filter(|raw| raw.id == packet::play::SystemChat::CLIENTBOUND_ID)
+            .map(|raw| String::from_utf8_lossy(&raw.data).into_owned())
+            .collect()
+    }
+
+    fn chat_and_count(
+        player: &mut Player,
+        client: &mut Connection,
+    ) -> Result<usize, Box<dyn Error>> {
+        client.send(&ClientChat("Hello"))?;
+        Ok(system_chats(&sync(player, client)?).len())
+    }
+
+    #[test]
+    fn test_chat_listener_cancel() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+
+        assert_eq!(chat_and_count(&mut player, &mut client)?, 1);
+        player.add_listener(Mute);
+        assert_eq!(chat_and_count(&mut player, &mut client)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_teleport_chunk_cache_center() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+
+        player.teleport(Vec3::new(1000.0, 100.0, -1000.0))?;
+
+        let mut centers = Vec::new();
+        for raw in recieved_packets(&player, &mut client)? {
+            if raw.id == packet::play::SetChunkCacheCenter::CLIENTBOUND_ID {
+                let mut reader = std::io::Cursor::new(&raw.data);
+                centers.push((reader.read_varint()?, reader.read_varint()?));
+            }
+        }
+        assert_eq!(centers, vec![(62, -63)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_movement_before_teleport_accepted() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        let sender = client.sender();
+        let send = |id: i32, write: &dyn Fn(&mut Vec<u8>) -> std::io::Result<()>| {
+            let mut data = Vec::new();
+            write(&mut data)?;
+            sender.send_raw(RawPacket::new(id, data.into_boxed_slice()))
+        };
+        let move_to = |x: f64| {
+            send(
+                generated::packet::play::SERVERBOUND_MINECRAFT_MOVE_PLAYER_POS,
+                &|w| {
+                    w.write_all(&x.to_be_bytes())?;
+                    w.write_all(&100.0f64.to_be_bytes())?;
+                    w.write_all(&0.0f64.to_be_bytes())?;
+                    w.write_all(&[0])
+                },
+            )
+        };
+        let accept = |teleport_id: i32| {
+            send(
+                generated::packet::play::SERVERBOUND_MINECRAFT_ACCEPT_TELEPORTATION,
+                &|w| w.write_varint(teleport_id),
+            )
+        };
+
+        move_to(10.0)?;
+        sync(&mut player, &mut client)?;
+        assert_eq!(player.position(), Vec3::zero());
+
+        accept(player.teleport_id)?;
+        move_to(20.0)?;
+        sync(&mut player, &mut client)?;
+        assert_eq!(player.position(), Vec3::new(20.0, 100.0, 0.0));
+
+        // Accepting an older teleport doesn't count.
+        let old_teleport_id = player.teleport_id;
+        player.teleport(Vec3::new(0.0, 100.0, 0.0))?;
+        accept(old_teleport_id)?;
+        move_to(30.0)?;
+        sync(&mut player, &mut client)?;
+        assert_eq!(player.position(), Vec3::new(0.0, 100.0, 0.0));
+
+        accept(player.teleport_id)?;
+        move_to(40.0)?;
+        sync(&mut player, &mut client)?;
+        assert_eq!(player.position(), Vec3::new(40.0, 100.0, 0.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_command() -> Result<(), Box<dyn Error>> {
+        let state = test_state();
+        let (server_1, mut client_1) = connection_pair()?;
+        let mut player_1 = Player::new(
+            server_1,
+            state.clone(),
+            UUID::new_v7(),
+            "Alice".to_owned(),
+            2,
+            2,
+        )?;
+        let (server_2, _client_2) = connection_pair()?;
+        let player_2 = Player::new(
+            server_2,
+            state.clone(),
+            UUID::new_v7(),
+            "Bob".to_owned(),
+            2,
+            2,
+        )?;
+        recieved_packets(&player_1, &mut client_1)?;
+
+        client_1.send(&ClientCommand("list"))?;
+        let raw = poll_until(|| {
+            player_1.update()?;
+            Ok::<_, Box<dyn Error>>(client_1.recieve()?)
+        })?;
+        let replies = system_chats(&[raw]);
+        assert_eq!(replies.len(), 1);
+        assert!(replies[0].contains("There are 2 players online"));
+        assert!(replies[0].contains("Alice"));
+        assert!(replies[0].contains("Bob"));
+
+        drop(player_2);
+        assert_eq!(state.players.lock().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_login_simulation_distance() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            8,
+            5,
+        )?;
+
+        let raw = recieved_packets(&player, &mut client)?.remove(0);
+        assert_eq!(raw.id, packet::play::Login::CLIENTBOUND_ID);
+        let mut reader = std::io::Cursor::new(&raw.data);
+        reader.read_const::<4>()?; // entity_id
+        reader.read_bool()?; // is_hardcore
+        (0..reader.read_varint()?).try_for_each(|_| reader.read_string().map(|_| ()))?;
+        reader.read_varint()?; // max_players
+        assert_eq!(reader.read_varint()?, 8);
+        assert_eq!(reader.read_varint()?, 5);
+
+        Ok(())
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Mock world error")]
+    struct MockWorldError;
+
+    /// World that errors on every block access.
+    #[derive(Debug)]
+    struct MockWorld {
+        dimension: Dimension,
+    }
+
+    impl World for MockWorld {
+        type Error = MockWorldError;
+
+        fn add_viewer(&mut self, connection: ConnectionSender) -> Arc<Mutex<WorldViewer>> {
+            Arc::new(Mutex::new(WorldViewer::new(
+                0,
+                connection,
+                ChunkLoader::new(2),
+            )))
+        }
+
+        fn update_viewers(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn get_block(&mut self, _position: Position) -> Result<Option<WorldBlock>, Self::Error> {
+            Err(MockWorldError)
+        }
+
+        fn set_block(
+            &mut self,
+            _position: Position,
+            _block: WorldBlock,
+        ) -> Result<(), Self::Error> {
+            Err(MockWorldError)
+        }
+
+        fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+            Vec::new()
+        }
+
+        fn dimension(&self) -> &Dimension {
+            &self.dimension
+        }
+
+        fn set_biome(&mut self, _position: Position, _biome: Biome) -> Result<(), Self::Error> {
+            Err(MockWorldError)
+        }
+
+        fn keep_loaded(&mut self, _chunks: &[ChunkPosition]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
+            -4..=19
+        }
+    }
+
+    #[test]
+    fn test_level_error() -> Result<(), Box<dyn Error>> {
+        let mut state = test_state();
+        state.world = Arc::new(Mutex::new(ErasedWorld::boxed(MockWorld {
+            dimension: Dimension::new("minecraft:overworld"),
+        })));
+        let (server, client) = connection_pair()?;
+        let mut player = Player::new(server, state, UUID::new_v7(), "Test".to_owned(), 2, 2)?;
+
+        // Placing the block fails in the world, which ends the update.
+        client.send(&ClientCommand("setblock 0 64 0 stone"))?;
+        let err = poll_until(|| match player.update() {
+            Ok(()) => Ok(None),
+            Err(err) => Ok::<_, Box<dyn Error>>(Some(err)),
+        })?;
+        let PlayerError::LevelError(err) = err else {
+            panic!("Expected LevelError, got {:?}", err);
+        };
+        assert!(err.downcast_ref::<MockWorldError>().is_some());
+        assert_eq!(err.to_string(), "Mock world error");
+
+        Ok(())
+    }
+
+    /// Non-anvil world that only keeps track of placed blocks.
+    #[derive(Debug)]
+    struct PlacedBlocksWorld {
+        dimension: Dimension,
+        placed: Arc<Mutex<Vec<(Position, WorldBlock)>>>,
+    }
+
+    impl World for PlacedBlocksWorld {
+        type Error = MockWorldError;
+
+        fn add_viewer(&mut self, connection: ConnectionSender) -> Arc<Mutex<WorldViewer>> {
+            Arc::new(Mutex::new(WorldViewer::new(
+                0,
+                connection,
+                ChunkLoader::new(2),
+            )))
+        }
+
+        fn update_viewers(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn get_block(&mut self, _position: Position) -> Result<Option<WorldBlock>, Self::Error> {
+            Ok(None)
+        }
+
+        fn set_block(&mut self, position: Position, block: WorldBlock) -> Result<(), Self::Error> {
+            self.placed.lock().unwrap().push((position, block));
+            Ok(())
+        }
+
+        fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+            Vec::new()
+        }
+
+        fn dimension(&self) -> &Dimension {
+            &self.dimension
+        }
+
+        fn set_biome(&mut self, _position: Position, _biome: Biome) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn keep_loaded(&mut self, _chunks: &[ChunkPosition]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn section_y_range(&self) -> std::ops::RangeInclusive<i8> {
+            -4..=19
+        }
+    }
+
+    struct ClientResourcePackResponse(UUID, i32);
+
+    impl ClientboundPacket for ClientResourcePackResponse {
+        const CLIENTBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_RESOURCE_PACK;
+
+        fn packet_write(&self, mut writer: impl std::io::Write) -> Result<(), ConnectionError> {
+            writer.write_uuid(&self.0)?;
+            writer.write_varint(self.1)?;
+            Ok(())
+        }
+    }
+
+    struct ClientUseItemOn(Position);
+
+    impl ClientboundPacket for ClientUseItemOn {
+        const CLIENTBOUND_ID: i32 = generated::packet::play::SERVERBOUND_MINECRAFT_USE_ITEM_ON;
+
+        fn packet_write(&self, mut writer: impl std::io::Write) -> Result<(), ConnectionError> {
+            writer.write_varint(0)?;
+            writer.write_position(&self.0)?;
+            writer.write_varint(1)?;
+            writer.write_all(&[0; 12])?;
+            writer.write_bool(false)?;
+            writer.write_bool(false)?;
+            writer.write_varint(1)?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct RecordPluginMessages(Arc<Mutex<Vec<PluginMessageEvent>>>);
+
+    impl PlayerListener for RecordPluginMessages {
+        fn on_plugin_message(
+            &mut self,
+            _player: &mut Player,
+            event: &mut PluginMessageEvent,
+        ) -> Result<(), PlayerError> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct PlaceStone;
+
+    impl PlayerListener for PlaceStone {
+        fn on_block_place(
+            &mut self,
+            _player: &mut Player,
+            event: &mut BlockPlaceEvent,
+        ) -> Result<(), PlayerError> {
+            event.block = Some(Block::new("minecraft:stone"));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_non_anvil_world() -> Result<(), Box<dyn Error>> {
+        let placed = Arc::new(Mutex::new(Vec::new()));
+        let mut state = test_state();
+        state.world = Arc::new(Mutex::new(ErasedWorld::boxed(PlacedBlocksWorld {
+            dimension: Dimension::new("the_nether"),
+            placed: placed.clone(),
+        })));
+
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(server, state, UUID::new_v7(), "Test".to_owned(), 2, 2)?;
+        player.add_listener(PlaceStone);
+
+        client.send(&ClientUseItemOn(Position::new(1, 64, 2)))?;
+        sync(&mut player, &mut client)?;
+
+        assert_eq!(
+            *placed.lock().unwrap(),
+            vec![(
+                Position::new(1, 65, 2),
+                WorldBlock::Block(Block::new("minecraft:stone"))
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_packets_aggregated() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+
+        (0..50).try_for_each(|_| {
+            client.sender().send_raw(RawPacket::new(
+                generated::packet::play::SERVERBOUND_MINECRAFT_CONTAINER_CLICK,
+                Box::new([]),
+            ))
+        })?;
+        client.send(&ClientChat("Hello"))?;
+        sync(&mut player, &mut client)?;
+
+        assert_eq!(
+            player.take_unsupported_packets_log(),
+            Some("Test Unsupported packets: 0x10 x50".to_owned())
+        );
+        assert_eq!(player.take_unsupported_packets_log(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tps_command() -> Result<(), Box<dyn Error>> {
+        let state = test_state();
+        (0..10).for_each(|_| {
+            state
+                .tick_timings
+                .lock()
+                .unwrap()
+                .record(std::time::Duration::from_millis(200));
+        });
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(server, state, UUID::new_v7(), "Test".to_owned(), 2, 2)?;
+        recieved_packets(&player, &mut client)?;
+
+        client.send(&ClientCommand("tps"))?;
+        let replies = system_chats(&sync(&mut player, &mut client)?);
+        assert_eq!(replies.len(), 1);
+        assert!(replies[0].contains("TPS: 5.0, MSPT: 200.00"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_command() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        recieved_packets(&player, &mut client)?;
+        {
+            let mut world_viewer = player.world_viewer.lock().unwrap();
+            world_viewer
+                .loader
+                .update_center(Some(ChunkPosition::new(0, 0)));
+            while world_viewer.loader.next_to_load().is_some() {}
+            assert!(world_viewer.loader.loaded().count() > 0);
+        }
+
+        // Huge radiuses are clamped to the view distance, negative ones are rejected.
+        client.send(&ClientCommand("refresh 2147483647"))?;
+        client.send(&ClientCommand("refresh -1"))?;
+        let messages = system_chats(&sync(&mut player, &mut client)?);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("Refreshing "));
+        assert!(messages[1].contains("Usage: /refresh [radius]"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effect_command() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        recieved_packets(&player, &mut client)?;
+
+        client.send(&ClientCommand("effect give speed 10 2"))?;
+        // Overflows when converted to ticks.
+        client.send(&ClientCommand("effect give speed 2147483647"))?;
+        client.send(&ClientCommand("effect clear"))?;
+
+        let mut effects = Vec::new();
+        for raw in sync(&mut player, &mut client)? {
+            let mut reader = std::io::Cursor::new(&raw.data);
+            if raw.id == packet::play::UpdateMobEffect::CLIENTBOUND_ID {
+                assert_eq!(reader.read_varint()?, player.entity_id);
+                effects.push((
+                    "give",
+                    reader.read_varint()?,
+                    reader.read_varint()?,
+                    reader.read_varint()?,
+                ));
+            } else if raw.id == packet::play::RemoveMobEffect::CLIENTBOUND_ID {
+                assert_eq!(reader.read_varint()?, player.entity_id);
+                effects.push(("clear", reader.read_varint()?, 0, 0));
+            }
+        }
+        assert_eq!(effects, vec![("give", 0, 2, 200), ("clear", 0, 0, 0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_camera_command() -> Result<(), Box<dyn Error>> {
+        let state = test_state();
+        let entity = state
+            .entities
+            .lock()
+            .unwrap()
+            .add_entity(StaticEntity { r#type: 0 }, UUID::new_v7());
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(server, state, UUID::new_v7(), "Test".to_owned(), 2, 2)?;
+        recieved_packets(&player, &mut client)?;
+
+        client.send(&ClientCommand("camera 999999"))?;
+        client.send(&ClientCommand(&format!("camera {}", entity.id())))?;
+
+        let mut cameras = Vec::new();
+        for raw in sync(&mut player, &mut client)? {
+            if raw.id == packet::play::SetCamera::CLIENTBOUND_ID {
+                cameras.push(std::io::Cursor::new(&raw.data).read_varint()?);
+            }
+        }
+        assert_eq!(cameras, vec![entity.id()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kill_command() -> Result<(), Box<dyn Error>> {
+        let state = test_state();
+        let entity = state
+            .entities
+            .lock()
+            .unwrap()
+            .add_entity(StaticEntity { r#type: 0 }, UUID::new_v7());
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            state.clone(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+
+        client.send(&ClientCommand(&format!("kill {}", entity.id())))?;
+        sync(&mut player, &mut client)?;
+
+        let entities = state.entities.lock().unwrap();
+        assert!(entities.get_entity(entity.id()).is_none());
+        assert!(!entities.entity_ids().contains(&entity.id()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_pack_decline_kick() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        let pack = UUID::new_v7();
+        player.send_resource_pack(pack, "https://example.com/pack.zip", "", false, None)?;
+
+        // Declining without a kick message set is allowed.
+        client.send(&ClientResourcePackResponse(pack, 1))?;
+        sync(&mut player, &mut client)?;
+        assert!(!player.is_closed());
+
+        player.set_resource_pack_decline_kick(Some(TextComponent::new("Resource pack required")));
+        client.send(&ClientResourcePackResponse(pack, 3))?;
+        sync(&mut player, &mut client)?;
+        assert!(!player.is_closed());
+
+        client.send(&ClientResourcePackResponse(pack, 1))?;
+        poll_until(|| {
+            player.update()?;
+            Ok::<_, Box<dyn Error>>(player.is_closed().then_some(()))
+        })?;
+        assert_eq!(player.disconnect_cause(), Some(DisconnectCause::Kicked));
+
+        recieve_id(&mut client, packet::play::Disconnect::CLIENTBOUND_ID)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_brand() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        player.set_client_brand(Some("vanilla".to_owned()));
+        assert_eq!(player.client_brand(), Some("vanilla"));
+
+        let mut data = Vec::new();
+        data.write_string("minecraft:brand")?;
+        data.write_string("fabric")?;
+        client.sender().send_raw(RawPacket::new(
+            generated::packet::play::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD,
+            data.into_boxed_slice(),
+        ))?;
+        sync(&mut player, &mut client)?;
+        assert_eq!(player.client_brand(), Some("fabric"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_messages() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        let recieved = Arc::new(Mutex::new(Vec::new()));
+        player.add_listener(RecordPluginMessages(recieved.clone()));
+        recieved_packets(&player, &mut client)?;
+
+        assert!(matches!(
+            player.send_plugin_message("Invalid Channel", &[]),
+            Err(PlayerError::InvalidPluginChannel(..))
+        ));
+        player.send_plugin_message("example:channel", &[1, 2, 3])?;
+        let raw = recieved_packets(&player, &mut client)?.remove(0);
+        assert_eq!(raw.id, packet::play::CustomPayload::CLIENTBOUND_ID);
+        let sent = raw.data;
+
+        // Echo it back, with an invalid channel that should be dropped.
+        client.sender().send_raw(RawPacket::new(
+            generated::packet::play::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD,
+            sent,
+        ))?;
+        let mut invalid = Vec::new();
+        invalid.write_string("Invalid Channel")?;
+        client.sender().send_raw(RawPacket::new(
+            generated::packet::play::SERVERBOUND_MINECRAFT_CUSTOM_PAYLOAD,
+            invalid.into_boxed_slice(),
+        ))?;
+        sync(&mut player, &mut client)?;
+
+        assert_eq!(
+            *recieved.lock().unwrap(),
+            vec![PluginMessageEvent {
+                channel: "example:channel".to_owned(),
+                data: Box::new([1, 2, 3]),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setblock_command() -> Result<(), Box<dyn Error>> {
+        let mut state = test_state();
+        // Test world only has sections up to 19.
+        state.world = Arc::new(Mutex::new(ErasedWorld::boxed(AnvilWorld::new(
+            "../pkmc-server/src/world/anvil-test-server/world/",
+            "minecraft:overworld",
+            -4..=20,
+            [(Biome::default(), 0)].into_iter().collect(),
+        ))));
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            state.clone(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+
+        let position = Position::new(3, 330, 5);
+        assert_eq!(state.world.lock().unwrap().get_block(position)?, None);
+        client.send(&ClientCommand("setblock 3 330 5 oak_stairs[facing=east]"))?;
+        client.send(&ClientCommand("setblock 3 400 5 stone"))?;
+        sync(&mut player, &mut client)?;
+
+        assert_eq!(
+            state.world.lock().unwrap().get_block(position)?,
+            Some(WorldBlock::Block(
+                Block::from_properties("oak_stairs", [("facing", "east")]).unwrap()
+            ))
+        );
+        assert_eq!(
+            state
+                .world
+                .lock()
+                .unwrap()
+                .get_block(Position::new(3, 400, 5))?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_chunks() -> Result<(), Box<dyn Error>> {
+        let (server, mut client) = connection_pair()?;
+        let mut player = Player::new(
+            server,
+            test_state(),
+            UUID::new_v7(),
+            "Test".to_owned(),
+            2,
+            2,
+        )?;
+        let mut action_bars = |player: &mut Player| -> Result<Vec<NBT>, Box<dyn Error>> {
+            // Longer than the debug chunks interval.
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            player.update()?;
+            let mut action_bars = Vec::new();
+            for raw in recieved_packets(player, &mut client)? {
+                if raw.id == packet::play::SetActionBarText::CLIENTBOUND_ID {
+                    action_bars.push(NBT::read_network(&*raw.data)?);
+                }
+            }
+            Ok(action_bars)
+        };
+
+        // Only the welcome message.
+        assert_eq!(action_bars(&mut player)?.len(), 1);
+        assert!(action_bars(&mut player)?.is_empty());
+
+        player.set_debug_chunks(Some(std::time::Duration::from_millis(10)));
+        let expected = player.debug_chunks_text().to_nbt();
+        for _ in 0..3 {
+            assert_eq!(action_bars(&mut player)?, vec![expected.clone()]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_worldborder_command() -> Result<(), Box<dyn Error>> {
+        let state = test_state();
+        let (server_1, mut client_1) = connection_pair()?;
+        let mut player_1 = Player::new(
+            server_1,
+            state.clone(),
+            UUID::new_v7(),
+            "Alice".to_owned(),
+            2,
+            2,
+        )?;
+        recieved_packets(&player_1, &mut client_1)?;
+
+        client_1.send(&ClientCommand("worldborder set 100 10"))?;
+
+        let mut lerps = Vec::new();
+        for raw in sync(&mut player_1, &mut client_1)? {
+            if raw.id == packet::play::SetBorderLerpSize::CLIENTBOUND_ID {
+                let mut reader = std::io::Cursor::new(&raw.data);
+                lerps.push((
+                    f64::from_be_bytes(reader.read_const()?),
+                    f64::from_be_bytes(reader.read_const()?),
+                    reader.read_all()?,
+                ));
+            }
+        }
+        // 10000ms as a varlong.
+        assert_eq!(
+            lerps,
+            vec![(59_999_968.0, 100.0, vec![0x90, 0x4E].into_boxed_slice())]
+        );
+
+        // Durations that overflow as milliseconds are rejected.
+        for seconds in ["10000000000000000", &u64::MAX.to_string()] {
+            client_1.send(&ClientCommand(&format!("worldborder set 50 {}", seconds)))?;
+            let packets = sync(&mut player_1, &mut client_1)?;
+            assert!(!packets
+                .iter()
+                .any(|raw| raw.id == packet::play::SetBorderLerpSize::CLIENTBOUND_ID));
+            let replies = system_chats(&packets);
+            assert_eq!(replies.len(), 1);
+            assert!(replies[0].contains("Usage: /worldborder set"));
+        }
+
+        let (server_2, mut client_2) = connection_pair()?;
+        let player_2 = Player::new(
+            server_2,
+            state.clone(),
+            UUID::new_v7(),
+            "Bob".to_owned(),
+            2,
+            2,
+        )?;
+        let mut sizes = Vec::new();
+        for raw in recieved_packets(&player_2, &mut client_2)? {
+            if raw.id == packet::play::InitializeBorder::CLIENTBOUND_ID {
+                let mut reader = std::io::Cursor::new(&raw.data);
+                // Skip center & old diameter.
+                reader.read_const::<24>()?;
+                sizes.push(f64::from_be_bytes(reader.read_const()?));
+            }
+        }
+        assert_eq!(sizes, vec![100.0]);
 
         Ok(())
     }